@@ -0,0 +1,42 @@
+// With the `compile-shaders` feature, the bundled shaders are compiled from GLSL into OUT_DIR
+// and included instead of the committed SPIR-V in src/shaders/spv.
+
+#[cfg(feature = "compile-shaders")]
+fn compile_shader(name: &str, stage: glslang::ShaderStage) {
+    use glslang::{Compiler, CompilerOptions, ShaderInput, ShaderSource, SpirvVersion, Target, VulkanVersion};
+
+    let path = format!("src/shaders/src/{}", name);
+    println!("cargo:rerun-if-changed={}", path);
+    let source = std::fs::read_to_string(&path).expect("Failed to read shader source.");
+
+    let compiler = Compiler::acquire().expect("Failed to acquire glslang.");
+    let source = ShaderSource::from(source);
+    let options = CompilerOptions {
+        target: Target::Vulkan {
+            version: VulkanVersion::Vulkan1_0,
+            spirv_version: SpirvVersion::SPIRV1_0,
+        },
+        ..Default::default()
+    };
+    let input = ShaderInput::new(&source, stage, &options, None, None)
+        .unwrap_or_else(|err| panic!("Failed to compile {}: {}", path, err));
+    let code = compiler
+        .create_shader(input)
+        .and_then(|shader| shader.compile())
+        .unwrap_or_else(|err| panic!("Failed to compile {}: {}", path, err));
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let bytes = code.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<_>>();
+    std::fs::write(format!("{}/{}.spv", out_dir, name), bytes).expect("Failed to write SPIR-V.");
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "compile-shaders")]
+    {
+        compile_shader("vert.vert", glslang::ShaderStage::Vertex);
+        compile_shader("frag.frag", glslang::ShaderStage::Fragment);
+        compile_shader("frag_subpixel.frag", glslang::ShaderStage::Fragment);
+    }
+}
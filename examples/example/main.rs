@@ -5,7 +5,7 @@ use std::os::raw::c_char;
 use std::os::raw::c_void;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::{collections::HashSet, u64};
+use std::collections::HashSet;
 
 use anyhow::Result;
 use ash::extensions::ext::DebugUtils;
@@ -14,7 +14,6 @@ use ash::{vk, Device, Entry, Instance};
 use ash_window::{create_surface, enumerate_required_extensions};
 use cgmath::{Deg, Matrix4, Point3, Vector3};
 use crevice::std140::{AsStd140, Std140};
-use egui::TextBuffer;
 use gpu_allocator::vulkan::*;
 use memoffset::offset_of;
 #[cfg(debug_assertions)]
@@ -32,10 +31,10 @@ const VALIDATION: &[&str] = &["VK_LAYER_KHRONOS_validation"];
 
 const DEVICE_EXTENSIONS: &[&str] = &["VK_KHR_swapchain", "VK_KHR_synchronization2"];
 
-const VERTEX_SHADER_PASS: &'static str = "examples/example/shaders/spv/vert.spv";
-const FRAGMENT_SHADER_PASS: &'static str = "examples/example/shaders/spv/frag.spv";
+const VERTEX_SHADER_PASS: &str = "examples/example/shaders/spv/vert.spv";
+const FRAGMENT_SHADER_PASS: &str = "examples/example/shaders/spv/frag.spv";
 
-const MODEL_PATH: &'static str = "examples/example/assets/monkey.obj";
+const MODEL_PATH: &str = "examples/example/assets/monkey.obj";
 
 const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
@@ -224,10 +223,7 @@ impl App {
 
             // Get extensions for creating Surface
             let extension_names = enumerate_required_extensions(&window)?;
-            let mut extension_names = extension_names
-                .iter()
-                .map(|name| *name)
-                .collect::<Vec<_>>();
+            let mut extension_names = extension_names.to_vec();
             
             if ENABLE_VALIDATION_LAYERS {
                 extension_names.push(DebugUtils::name().as_ptr());
@@ -462,19 +458,16 @@ impl App {
                     .unwrap()
             };
 
-            let format = formats
-                .iter()
-                .find(|f| {
-                    f.format == vk::Format::B8G8R8A8_UNORM || f.format == vk::Format::R8G8B8A8_UNORM
-                })
-                .unwrap_or(&formats[0])
-                .clone();
+            let format = egui_winit_ash_integration::SurfaceFormatPreference::default()
+                .select(&formats)
+                .expect("No surface format.")
+                .format;
             let present_mode = present_modes
                 .into_iter()
                 .find(|&p| p == vk::PresentModeKHR::MAILBOX)
                 .unwrap_or(vk::PresentModeKHR::FIFO);
             let extent = {
-                if capabilities.current_extent.width != u32::max_value() {
+                if capabilities.current_extent.width != u32::MAX {
                     capabilities.current_extent
                 } else {
                     vk::Extent2D {
@@ -797,8 +790,9 @@ impl App {
                 use std::fs::File;
                 use std::io::Read;
 
-                let spv_file = File::open(&Path::new(VERTEX_SHADER_PASS))?;
-                let bytes_code: Vec<u8> = spv_file.bytes().filter_map(|byte| byte.ok()).collect();
+                let mut spv_file = File::open(Path::new(VERTEX_SHADER_PASS))?;
+                let mut bytes_code = Vec::new();
+                spv_file.read_to_end(&mut bytes_code)?;
 
                 let shader_module_create_info = vk::ShaderModuleCreateInfo {
                     code_size: bytes_code.len(),
@@ -811,8 +805,9 @@ impl App {
                 use std::fs::File;
                 use std::io::Read;
 
-                let spv_file = File::open(&Path::new(FRAGMENT_SHADER_PASS))?;
-                let bytes_code: Vec<u8> = spv_file.bytes().filter_map(|byte| byte.ok()).collect();
+                let mut spv_file = File::open(Path::new(FRAGMENT_SHADER_PASS))?;
+                let mut bytes_code = Vec::new();
+                spv_file.read_to_end(&mut bytes_code)?;
 
                 let shader_module_create_info = vk::ShaderModuleCreateInfo {
                     code_size: bytes_code.len(),
@@ -1122,8 +1117,8 @@ impl App {
             device.clone(),
             Arc::clone(&allocator),
             swapchain_loader.clone(),
-            swapchain.clone(),
-            format.clone(),
+            swapchain,
+            format,
         ));
         // #### egui ##########################################################################
 
@@ -1192,20 +1187,19 @@ impl App {
         unsafe {
             // Wait Fence
             let fence = self.fences[self.current_frame];
-            self.device.wait_for_fences(&[fence], true, std::u64::MAX)?;
+            self.device.wait_for_fences(&[fence], true, u64::MAX)?;
 
             // Acquire next image
-            let image_index;
-            match self.swapchain_loader.acquire_next_image(
+            let image_index = match self.swapchain_loader.acquire_next_image(
                 self.swapchain,
-                std::u64::MAX,
+                u64::MAX,
                 self.image_available_semaphores[self.current_frame],
                 vk::Fence::null(),
             ) {
-                Ok((index, _is_suboptimal)) => image_index = index as usize,
+                Ok((index, _is_suboptimal)) => index as usize,
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return self.recreate_swapchain(),
                 Err(error) => panic!("Error while acquiring next image: {}", error),
-            }
+            };
 
             // Reset fence
             self.device.reset_fences(&[fence])?;
@@ -1405,11 +1399,13 @@ impl App {
                     ui.separator();
                     ui.text_edit_singleline(&mut self.text);
                 });
-            let (_, textures_delta, shapes) = self.egui_integration.end_frame(&mut self.window);
-            let clipped_meshes = self.egui_integration.context().tessellate(shapes);
-            
-            self.egui_integration
-                .paint(command_buffer, image_index, textures_delta, clipped_meshes);
+            let (textures_delta, clipped_meshes) =
+                self.egui_integration.end_frame_and_tessellate(&self.window);
+            // still submit and present, which reports the out of date swapchain
+            let needs_swapchain_update = self
+                .egui_integration
+                .paint(command_buffer, image_index, textures_delta, clipped_meshes)
+                .is_err();
             // #### egui ##########################################################################
 
             self.device.end_command_buffer(command_buffer)?;
@@ -1434,7 +1430,9 @@ impl App {
                     .image_indices(&[image_index as u32])
                     .wait_semaphores(&[self.render_finished_semaphores[self.current_frame]]),
             ) {
-                Ok(is_suboptimal) if is_suboptimal == true => return self.recreate_swapchain(),
+                Ok(is_suboptimal) if is_suboptimal || needs_swapchain_update => {
+                    return self.recreate_swapchain()
+                }
                 Ok(_is_suboptimal) => (),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return self.recreate_swapchain(),
                 Err(error) => panic!("Failed to present queue: {}", error),
@@ -1500,19 +1498,16 @@ impl App {
                     .get_physical_device_surface_present_modes(self.physical_device, self.surface)?
             };
 
-            self.format = formats
-                .iter()
-                .find(|f| {
-                    f.format == vk::Format::B8G8R8A8_UNORM || f.format == vk::Format::R8G8B8A8_UNORM
-                })
-                .unwrap_or(&formats[0])
-                .clone();
+            self.format = egui_winit_ash_integration::SurfaceFormatPreference::default()
+                .select(&formats)
+                .expect("No surface format.")
+                .format;
             let present_mode = present_modes
                 .into_iter()
                 .find(|&p| p == vk::PresentModeKHR::MAILBOX)
                 .unwrap_or(vk::PresentModeKHR::FIFO);
             self.extent = {
-                if capabilities.current_extent.width != u32::max_value() {
+                if capabilities.current_extent.width != u32::MAX {
                     capabilities.current_extent
                 } else {
                     vk::Extent2D {
@@ -1736,8 +1731,9 @@ impl App {
         self.egui_integration.update_swapchain(
             self.width,
             self.height,
-            self.swapchain.clone(),
-            self.format.clone(),
+            Some(self.window.scale_factor()),
+            self.swapchain,
+            self.format,
         );
         // #### egui ##########################################################################
 
@@ -1826,6 +1822,8 @@ fn main() -> Result<()> {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            // nothing to render to while minimized
+            _ if app.egui_integration.is_occluded() => (),
             Event::WindowEvent {
                 event: WindowEvent::Resized(_),
                 ..
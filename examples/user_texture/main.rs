@@ -5,11 +5,11 @@ use std::os::raw::c_char;
 use std::os::raw::c_void;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::{collections::HashSet, u64};
+use std::collections::HashSet;
 
 use anyhow::Result;
 use ash::extensions::ext::DebugUtils;
-use ash::extensions::khr::{Surface, Swapchain};
+use ash::extensions::khr::{Surface, Swapchain, Synchronization2};
 use ash::{vk, Device, Entry, Instance};
 use ash_window::{create_surface, enumerate_required_extensions};
 use cgmath::{Deg, Matrix4, Point3, Vector3};
@@ -29,13 +29,13 @@ const ENABLE_VALIDATION_LAYERS: bool = true;
 const ENABLE_VALIDATION_LAYERS: bool = false;
 const VALIDATION: &[&str] = &["VK_LAYER_KHRONOS_validation"];
 
-const DEVICE_EXTENSIONS: &[&str] = &["VK_KHR_swapchain"];
+const DEVICE_EXTENSIONS: &[&str] = &["VK_KHR_swapchain", "VK_KHR_synchronization2"];
 
-const VERTEX_SHADER_PASS: &'static str = "examples/user_texture/shaders/spv/vert.spv";
-const FRAGMENT_SHADER_PASS: &'static str = "examples/user_texture/shaders/spv/frag.spv";
+const VERTEX_SHADER_PASS: &str = "examples/user_texture/shaders/spv/vert.spv";
+const FRAGMENT_SHADER_PASS: &str = "examples/user_texture/shaders/spv/frag.spv";
 
-const MODEL_PATH: &'static str = "examples/user_texture/assets/monkey.obj";
-const IMAGE_PATH: &'static str = "examples/user_texture/assets/Mandrill.bmp";
+const MODEL_PATH: &str = "examples/user_texture/assets/monkey.obj";
+const IMAGE_PATH: &str = "examples/user_texture/assets/Mandrill.bmp";
 
 const SCENE_IMAGE_WIDTH: u32 = 640;
 const SCENE_IMAGE_HEIGHT: u32 = 420;
@@ -226,7 +226,7 @@ impl App {
             let app_name = CString::new(title)?;
             let engine_name = CString::new("Vulkan Engine")?;
             let app_info = vk::ApplicationInfo::builder()
-                .api_version(vk::make_api_version(0, 1, 2, 0))
+                .api_version(vk::make_api_version(0, 1, 3, 0))
                 .application_version(vk::make_api_version(0, 0, 1, 0))
                 .application_name(&app_name)
                 .engine_version(vk::make_api_version(0, 0, 1, 0))
@@ -234,10 +234,7 @@ impl App {
 
             // Get extensions for creating Surface
             let extension_names = enumerate_required_extensions(&window)?;
-            let mut extension_names = extension_names
-                .iter()
-                .map(|name| name.as_ptr())
-                .collect::<Vec<_>>();
+            let mut extension_names = extension_names.to_vec();
             if ENABLE_VALIDATION_LAYERS {
                 extension_names.push(DebugUtils::name().as_ptr());
             }
@@ -428,11 +425,14 @@ impl App {
                 queue_create_infos.push(queue_create_info);
             }
 
-            let enabled_extension_names = [Swapchain::name().as_ptr()];
+            let enabled_extension_names = [Swapchain::name().as_ptr(), Synchronization2::name().as_ptr()];
+            let mut features = vk::PhysicalDeviceVulkan13Features::builder().synchronization2(true).build();
+            let mut vk13features = vk::PhysicalDeviceFeatures2::builder().push_next::<vk::PhysicalDeviceVulkan13Features>(&mut features);
 
             let device_create_info = vk::DeviceCreateInfo::builder()
                 .queue_create_infos(queue_create_infos.as_slice())
-                .enabled_extension_names(&enabled_extension_names);
+                .enabled_extension_names(&enabled_extension_names)
+                .push_next(&mut vk13features);
 
             unsafe { instance.create_device(physical_device, &device_create_info, None)? }
         };
@@ -466,19 +466,16 @@ impl App {
                     .unwrap()
             };
 
-            let format = formats
-                .iter()
-                .find(|f| {
-                    f.format == vk::Format::B8G8R8A8_UNORM || f.format == vk::Format::R8G8B8A8_UNORM
-                })
-                .unwrap_or(&formats[0])
-                .clone();
+            let format = egui_winit_ash_integration::SurfaceFormatPreference::default()
+                .select(&formats)
+                .expect("No surface format.")
+                .format;
             let present_mode = present_modes
                 .into_iter()
                 .find(|&p| p == vk::PresentModeKHR::MAILBOX)
                 .unwrap_or(vk::PresentModeKHR::FIFO);
             let extent = {
-                if capabilities.current_extent.width != u32::max_value() {
+                if capabilities.current_extent.width != u32::MAX {
                     capabilities.current_extent
                 } else {
                     vk::Extent2D {
@@ -842,8 +839,9 @@ impl App {
                 use std::fs::File;
                 use std::io::Read;
 
-                let spv_file = File::open(&Path::new(VERTEX_SHADER_PASS))?;
-                let bytes_code: Vec<u8> = spv_file.bytes().filter_map(|byte| byte.ok()).collect();
+                let mut spv_file = File::open(Path::new(VERTEX_SHADER_PASS))?;
+                let mut bytes_code = Vec::new();
+                spv_file.read_to_end(&mut bytes_code)?;
 
                 let shader_module_create_info = vk::ShaderModuleCreateInfo {
                     code_size: bytes_code.len(),
@@ -856,8 +854,9 @@ impl App {
                 use std::fs::File;
                 use std::io::Read;
 
-                let spv_file = File::open(&Path::new(FRAGMENT_SHADER_PASS))?;
-                let bytes_code: Vec<u8> = spv_file.bytes().filter_map(|byte| byte.ok()).collect();
+                let mut spv_file = File::open(Path::new(FRAGMENT_SHADER_PASS))?;
+                let mut bytes_code = Vec::new();
+                spv_file.read_to_end(&mut bytes_code)?;
 
                 let shader_module_create_info = vk::ShaderModuleCreateInfo {
                     code_size: bytes_code.len(),
@@ -1124,7 +1123,7 @@ impl App {
 
         // Load User Texture image
         let (image, image_allocation, image_view) = {
-            let image_object = image::open(&Path::new(IMAGE_PATH))?;
+            let image_object = image::open(Path::new(IMAGE_PATH))?;
             let (image_width, image_height) = (image_object.width(), image_object.height());
             let image_data = image_object.to_rgba8().into_raw();
 
@@ -1403,17 +1402,17 @@ impl App {
             device.clone(),
             Arc::clone(&allocator),
             swapchain_loader.clone(),
-            swapchain.clone(),
-            format.clone(),
+            swapchain,
+            format,
         ));
 
         // Register user texture
         let image_texture_id =
-            egui_integration.register_user_texture(image_view.clone(), sampler.clone());
+            egui_integration.register_user_texture(image_view, sampler);
         let scene_texture_ids = color_image_views
             .iter()
             .map(|image_view| {
-                egui_integration.register_user_texture(image_view.clone(), sampler.clone())
+                egui_integration.register_user_texture(*image_view, sampler)
             })
             .collect::<Vec<_>>();
         // #### egui ##########################################################################
@@ -1495,20 +1494,19 @@ impl App {
         unsafe {
             // Wait Fence
             let fence = self.fences[self.current_frame];
-            self.device.wait_for_fences(&[fence], true, std::u64::MAX)?;
+            self.device.wait_for_fences(&[fence], true, u64::MAX)?;
 
             // Acquire next image
-            let image_index;
-            match self.swapchain_loader.acquire_next_image(
+            let image_index = match self.swapchain_loader.acquire_next_image(
                 self.swapchain,
-                std::u64::MAX,
+                u64::MAX,
                 self.image_available_semaphores[self.current_frame],
                 vk::Fence::null(),
             ) {
-                Ok((index, _is_suboptimal)) => image_index = index as usize,
+                Ok((index, _is_suboptimal)) => index as usize,
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return self.recreate_swapchain(),
                 Err(error) => panic!("Error while acquiring next image: {}", error),
-            }
+            };
 
             // Reset fence
             self.device.reset_fences(&[fence])?;
@@ -1777,8 +1775,8 @@ impl App {
                             );
                             ui.painter().add(Shape::Mesh(mesh));
 
-                            if response.dragged() {
-                                if ui.input().pointer.button_down(PointerButton::Primary) {
+                            if response.dragged()
+                                && ui.input().pointer.button_down(PointerButton::Primary) {
                                     if let Some(pointer_pos) = ui.input().pointer.interact_pos() {
                                         if let Some(prev_pointer_pos) = prev_pointer_pos {
                                             let delta = pointer_pos - *prev_pointer_pos;
@@ -1788,7 +1786,6 @@ impl App {
                                         *prev_pointer_pos = Some(pointer_pos);
                                     }
                                 }
-                            }
                             if response.drag_released() {
                                 *prev_pointer_pos = None;
                             }
@@ -1796,10 +1793,15 @@ impl App {
                         ui.label("You can drag the scene to rotate the model.");
                     });
             }
-            let (_, shapes) = self.egui_integration.end_frame(&mut self.window);
-            let clipped_meshes = self.egui_integration.context().tessellate(shapes);
+            let output = self.egui_integration.end_frame();
             self.egui_integration
-                .paint(command_buffer, image_index, clipped_meshes);
+                .handle_platform_output(&self.window, output.platform_output);
+            let clipped_meshes = self.egui_integration.context().tessellate(output.shapes);
+            // still submit and present, which reports the out of date swapchain
+            let needs_swapchain_update = self
+                .egui_integration
+                .paint(command_buffer, image_index, output.textures_delta, clipped_meshes)
+                .is_err();
             // #### egui ##########################################################################
 
             self.device.end_command_buffer(command_buffer)?;
@@ -1824,7 +1826,9 @@ impl App {
                     .image_indices(&[image_index as u32])
                     .wait_semaphores(&[self.render_finished_semaphores[self.current_frame]]),
             ) {
-                Ok(is_suboptimal) if is_suboptimal == true => return self.recreate_swapchain(),
+                Ok(is_suboptimal) if is_suboptimal || needs_swapchain_update => {
+                    return self.recreate_swapchain()
+                }
                 Ok(_is_suboptimal) => (),
                 Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return self.recreate_swapchain(),
                 Err(error) => panic!("Failed to present queue: {}", error),
@@ -1868,19 +1872,16 @@ impl App {
                     .get_physical_device_surface_present_modes(self.physical_device, self.surface)?
             };
 
-            self.format = formats
-                .iter()
-                .find(|f| {
-                    f.format == vk::Format::B8G8R8A8_UNORM || f.format == vk::Format::R8G8B8A8_UNORM
-                })
-                .unwrap_or(&formats[0])
-                .clone();
+            self.format = egui_winit_ash_integration::SurfaceFormatPreference::default()
+                .select(&formats)
+                .expect("No surface format.")
+                .format;
             let present_mode = present_modes
                 .into_iter()
                 .find(|&p| p == vk::PresentModeKHR::MAILBOX)
                 .unwrap_or(vk::PresentModeKHR::FIFO);
             self.extent = {
-                if capabilities.current_extent.width != u32::max_value() {
+                if capabilities.current_extent.width != u32::MAX {
                     capabilities.current_extent
                 } else {
                     vk::Extent2D {
@@ -1948,8 +1949,9 @@ impl App {
         self.egui_integration.update_swapchain(
             self.width,
             self.height,
-            self.swapchain.clone(),
-            self.format.clone(),
+            Some(self.window.scale_factor()),
+            self.swapchain,
+            self.format,
         );
         // #### egui ##########################################################################
 
@@ -2061,6 +2063,8 @@ fn main() -> Result<()> {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            // nothing to render to while minimized
+            _ if app.egui_integration.is_occluded() => (),
             Event::WindowEvent {
                 event: WindowEvent::Resized(_),
                 ..
@@ -6,6 +6,9 @@ use ash::vk::*;
 
 pub trait AllocationTrait {
     /// Returns the vk::DeviceMemory object that is backing this allocation.
+    ///
+    /// # Safety
+    /// The returned memory must not be freed while the allocation is still alive.
     unsafe fn memory(&self) -> DeviceMemory;
 
     /// Returns the offset of the allocation on the vk::DeviceMemory. When binding the memory to a buffer or image, this offset needs to be supplied as well.
@@ -30,8 +33,29 @@ pub enum MemoryLocation {
     GpuToCpu,
 }
 
+/// Resource an allocation is requested for, see [`AllocationCreateInfoTrait::dedicated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedicatedAllocation {
+    /// The allocation will only be bound to this image.
+    Image(Image),
+    /// The allocation will only be bound to this buffer.
+    Buffer(Buffer),
+}
+
 pub trait AllocationCreateInfoTrait {
+    /// `linear` is true for buffers and linear tiling images and false for optimal tiling images, which must not alias linear resources within `bufferImageGranularity`.
     fn new(requirements: MemoryRequirements, location: MemoryLocation, linear: bool) -> Self;
+
+    /// Request a dedicated allocation (`VK_KHR_dedicated_allocation`) for `resource`.
+    ///
+    /// Requested for large images, e.g. the font atlas once CJK fonts are loaded, and whenever the driver prefers one. Allocators without dedicated allocations ignore it.
+    fn dedicated(self, resource: DedicatedAllocation) -> Self
+    where
+        Self: Sized,
+    {
+        let _ = resource;
+        self
+    }
 }
 
 pub trait AllocatorTrait {
@@ -0,0 +1,158 @@
+#![warn(missing_docs)]
+
+use anyhow::{anyhow, Result};
+use copypasta::{ClipboardContext, ClipboardProvider};
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+use copypasta::x11_clipboard::{Primary, X11ClipboardContext};
+
+/// Clipboard used by the [`Platform`](crate::Platform) for copy, cut and paste.
+///
+/// The default is [`SystemClipboard`], which only handles text, or [`MemoryClipboard`] if
+/// there is no system clipboard, e.g. headless. Implement the image methods
+/// to support image copy and paste, e.g. with `arboard`, and install it with
+/// [`Platform::set_clipboard`](crate::Platform::set_clipboard).
+pub trait Clipboard {
+    /// Get the text on the clipboard.
+    fn get_text(&mut self) -> Result<String>;
+
+    /// Put text on the clipboard.
+    fn set_text(&mut self, text: String) -> Result<()>;
+
+    /// Get the image on the clipboard, if there is one.
+    fn get_image(&mut self) -> Result<Option<egui::ColorImage>> {
+        Ok(None)
+    }
+
+    /// Put an image on the clipboard.
+    fn set_image(&mut self, _image: &egui::ColorImage) -> Result<()> {
+        Err(anyhow!("This clipboard does not support images."))
+    }
+
+    /// Get the text of the primary selection, pasted with the middle mouse button on X11.
+    ///
+    /// `None` if the platform has no primary selection.
+    fn get_primary(&mut self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Put text in the primary selection. Does nothing if the platform has none.
+    fn set_primary(&mut self, _text: String) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Text clipboard of the operating system.
+///
+/// On Linux and the BSDs it also handles the X11 primary selection, if an X server is available.
+pub struct SystemClipboard {
+    context: ClipboardContext,
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+    primary: Option<X11ClipboardContext<Primary>>,
+}
+
+impl SystemClipboard {
+    /// Connect to the clipboard of the operating system.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            context: ClipboardContext::new().map_err(|err| anyhow!("{}", err))?,
+            // not every session has an X server, e.g. pure Wayland
+            #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+            primary: X11ClipboardContext::new().ok(),
+        })
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn get_text(&mut self) -> Result<String> {
+        self.context.get_contents().map_err(|err| anyhow!("{}", err))
+    }
+
+    fn set_text(&mut self, text: String) -> Result<()> {
+        self.context.set_contents(text).map_err(|err| anyhow!("{}", err))
+    }
+
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+    fn get_primary(&mut self) -> Result<Option<String>> {
+        match &mut self.primary {
+            Some(primary) => primary.get_contents().map(Some).map_err(|err| anyhow!("{}", err)),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "ios", target_os = "emscripten"))))]
+    fn set_primary(&mut self, text: String) -> Result<()> {
+        match &mut self.primary {
+            Some(primary) => primary.set_contents(text).map_err(|err| anyhow!("{}", err)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Clipboard that only lives in memory, for headless use and tests.
+#[derive(Default)]
+pub struct MemoryClipboard {
+    /// Text on the clipboard.
+    pub text: String,
+    /// Image on the clipboard.
+    pub image: Option<egui::ColorImage>,
+    /// Text of the primary selection.
+    pub primary: String,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get_text(&mut self) -> Result<String> {
+        Ok(self.text.clone())
+    }
+
+    fn set_text(&mut self, text: String) -> Result<()> {
+        self.text = text;
+        self.image = None;
+        Ok(())
+    }
+
+    fn get_image(&mut self) -> Result<Option<egui::ColorImage>> {
+        Ok(self.image.clone())
+    }
+
+    fn set_image(&mut self, image: &egui::ColorImage) -> Result<()> {
+        self.image = Some(image.clone());
+        self.text.clear();
+        Ok(())
+    }
+
+    fn get_primary(&mut self) -> Result<Option<String>> {
+        Ok(Some(self.primary.clone()))
+    }
+
+    fn set_primary(&mut self, text: String) -> Result<()> {
+        self.primary = text;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_clipboard_holds_text_or_image() {
+        let mut clipboard = MemoryClipboard::default();
+        clipboard.set_text("text".to_owned()).unwrap();
+        assert_eq!(clipboard.get_text().unwrap(), "text");
+        assert!(clipboard.get_image().unwrap().is_none());
+
+        let image = egui::ColorImage::new([2, 1], egui::Color32::RED);
+        clipboard.set_image(&image).unwrap();
+        assert_eq!(clipboard.get_image().unwrap().unwrap().pixels, image.pixels);
+        assert_eq!(clipboard.get_text().unwrap(), "");
+    }
+
+    #[test]
+    fn primary_selection_is_separate_from_clipboard() {
+        let mut clipboard = MemoryClipboard::default();
+        clipboard.set_text("copied".to_owned()).unwrap();
+        clipboard.set_primary("selected".to_owned()).unwrap();
+        assert_eq!(clipboard.get_text().unwrap(), "copied");
+        assert_eq!(clipboard.get_primary().unwrap().as_deref(), Some("selected"));
+    }
+}
@@ -0,0 +1,211 @@
+#![warn(missing_docs)]
+
+use std::ffi::CStr;
+use std::fmt;
+
+use ash::{vk, Instance};
+
+use crate::IntegrationOptions;
+
+/// An optional Vulkan feature or extension, listed in a [`SupportReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceFeature {
+    /// `synchronization2` (Vulkan 1.3), used for all barriers.
+    Synchronization2,
+    /// Descriptor update templates (Vulkan 1.1), used to write the descriptor sets of textures.
+    DescriptorUpdateTemplate,
+    /// `VK_KHR_push_descriptor`.
+    PushDescriptor,
+    /// `descriptorIndexing` (Vulkan 1.2).
+    DescriptorIndexing,
+    /// `samplerYcbcrConversion` (Vulkan 1.1), for video textures in YCbCr formats.
+    SamplerYcbcrConversion,
+    /// `dualSrcBlend`, needed by [`IntegrationOptions::subpixel_text`].
+    DualSrcBlend,
+    /// `samplerAnisotropy`, needed by [`IntegrationOptions::max_sampler_anisotropy`].
+    SamplerAnisotropy,
+    /// `VK_EXT_descriptor_buffer` with its `descriptorBuffer` feature, needed by
+    /// [`IntegrationOptions::descriptor_buffer`].
+    DescriptorBuffer,
+    /// `dynamicRendering` (Vulkan 1.3), needed by [`IntegrationOptions::dynamic_rendering`].
+    DynamicRendering,
+}
+
+const DEVICE_FEATURES: [DeviceFeature; 9] = [
+    DeviceFeature::Synchronization2,
+    DeviceFeature::DescriptorUpdateTemplate,
+    DeviceFeature::PushDescriptor,
+    DeviceFeature::DescriptorIndexing,
+    DeviceFeature::SamplerYcbcrConversion,
+    DeviceFeature::DualSrcBlend,
+    DeviceFeature::SamplerAnisotropy,
+    DeviceFeature::DescriptorBuffer,
+    DeviceFeature::DynamicRendering,
+];
+
+impl DeviceFeature {
+    // Whether the integration uses the feature with `options`.
+    fn required(self, options: &IntegrationOptions) -> bool {
+        match self {
+            DeviceFeature::Synchronization2 => true,
+            DeviceFeature::DescriptorUpdateTemplate => options.descriptor_buffer.is_none(),
+            DeviceFeature::PushDescriptor
+            | DeviceFeature::DescriptorIndexing
+            | DeviceFeature::SamplerYcbcrConversion => false,
+            DeviceFeature::DualSrcBlend => options.subpixel_text,
+            DeviceFeature::SamplerAnisotropy => options.max_sampler_anisotropy.is_some(),
+            DeviceFeature::DescriptorBuffer => options.descriptor_buffer.is_some(),
+            DeviceFeature::DynamicRendering => options.dynamic_rendering,
+        }
+    }
+}
+
+/// Whether `physical_device` has a unified memory architecture, i.e. all of its memory is
+/// device local, as on integrated GPUs. See [`IntegrationOptions::unified_memory`].
+pub fn has_unified_memory(instance: &Instance, physical_device: vk::PhysicalDevice) -> bool {
+    let memory_properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+    memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+        .iter()
+        .all(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+}
+
+/// Support of a [`DeviceFeature`] by a physical device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSupport {
+    /// The feature.
+    pub feature: DeviceFeature,
+    /// The physical device supports it.
+    pub available: bool,
+    /// The integration uses it with the options checked; it must then also be enabled when
+    /// creating the device.
+    pub required: bool,
+}
+
+/// Which optional paths a physical device supports and which the options require, returned
+/// by [`Integration::check_device_support`](crate::Integration::check_device_support).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportReport {
+    /// `apiVersion` of the physical device.
+    pub api_version: u32,
+    /// Every [`DeviceFeature`].
+    pub features: Vec<FeatureSupport>,
+}
+
+impl SupportReport {
+    /// Query `physical_device` for the features used with `options`.
+    pub fn query(instance: &Instance, physical_device: vk::PhysicalDevice, options: &IntegrationOptions) -> Self {
+        let api_version = unsafe { instance.get_physical_device_properties(physical_device) }.api_version;
+        let extensions = unsafe { instance.enumerate_device_extension_properties(physical_device) }
+            .expect("Failed to enumerate device extension properties.");
+        let has_extension = |name: &CStr| {
+            extensions
+                .iter()
+                .any(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == name)
+        };
+        let push_descriptor = has_extension(vk::KhrPushDescriptorFn::name());
+        let descriptor_buffer_extension = has_extension(vk::ExtDescriptorBufferFn::name());
+
+        // only the structures of versions and extensions the device supports may be chained
+        let mut vulkan_11 = vk::PhysicalDeviceVulkan11Features::default();
+        let mut vulkan_12 = vk::PhysicalDeviceVulkan12Features::default();
+        let mut vulkan_13 = vk::PhysicalDeviceVulkan13Features::default();
+        let mut descriptor_buffer = vk::PhysicalDeviceDescriptorBufferFeaturesEXT::default();
+        let mut features2 = vk::PhysicalDeviceFeatures2::builder();
+        if api_version >= vk::API_VERSION_1_2 {
+            features2 = features2.push_next(&mut vulkan_11).push_next(&mut vulkan_12);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            features2 = features2.push_next(&mut vulkan_13);
+        }
+        if descriptor_buffer_extension {
+            features2 = features2.push_next(&mut descriptor_buffer);
+        }
+        let mut features2 = features2.build();
+        unsafe {
+            instance.get_physical_device_features2(physical_device, &mut features2);
+        }
+        let features = features2.features;
+
+        Self::new(api_version, options, |feature| match feature {
+            DeviceFeature::Synchronization2 => vulkan_13.synchronization2 == vk::TRUE,
+            DeviceFeature::DescriptorUpdateTemplate => api_version >= vk::API_VERSION_1_1,
+            DeviceFeature::PushDescriptor => push_descriptor,
+            DeviceFeature::DescriptorIndexing => vulkan_12.descriptor_indexing == vk::TRUE,
+            DeviceFeature::SamplerYcbcrConversion => vulkan_11.sampler_ycbcr_conversion == vk::TRUE,
+            DeviceFeature::DualSrcBlend => features.dual_src_blend == vk::TRUE,
+            DeviceFeature::SamplerAnisotropy => features.sampler_anisotropy == vk::TRUE,
+            DeviceFeature::DescriptorBuffer => descriptor_buffer.descriptor_buffer == vk::TRUE,
+            DeviceFeature::DynamicRendering => vulkan_13.dynamic_rendering == vk::TRUE,
+        })
+    }
+
+    fn new(api_version: u32, options: &IntegrationOptions, available: impl Fn(DeviceFeature) -> bool) -> Self {
+        Self {
+            api_version,
+            features: DEVICE_FEATURES
+                .iter()
+                .map(|&feature| FeatureSupport {
+                    feature,
+                    available: available(feature),
+                    required: feature.required(options),
+                })
+                .collect(),
+        }
+    }
+
+    /// Features the options require that the device lacks.
+    pub fn missing(&self) -> impl Iterator<Item = DeviceFeature> + '_ {
+        self.features
+            .iter()
+            .filter(|support| support.required && !support.available)
+            .map(|support| support.feature)
+    }
+
+    /// Ok if the device supports everything the options require.
+    pub fn result(&self) -> Result<(), UnsupportedDevice> {
+        let missing = self.missing().collect::<Vec<_>>();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(UnsupportedDevice { missing })
+        }
+    }
+}
+
+/// The features a device lacks for the chosen options, from [`SupportReport::result`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedDevice {
+    /// The required features the device does not support.
+    pub missing: Vec<DeviceFeature>,
+}
+
+impl fmt::Display for UnsupportedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The device does not support {:?}, which the integration options require", self.missing)
+    }
+}
+
+impl std::error::Error for UnsupportedDevice {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_decide_the_required_features() {
+        let options = IntegrationOptions {
+            subpixel_text: true,
+            ..Default::default()
+        };
+        let report = SupportReport::new(vk::API_VERSION_1_3, &options, |feature| {
+            feature != DeviceFeature::DualSrcBlend && feature != DeviceFeature::DescriptorBuffer
+        });
+        assert_eq!(report.missing().collect::<Vec<_>>(), [DeviceFeature::DualSrcBlend]);
+        assert!(report.result().is_err());
+
+        let report = SupportReport::new(vk::API_VERSION_1_3, &Default::default(), |feature| {
+            feature != DeviceFeature::DualSrcBlend && feature != DeviceFeature::DescriptorBuffer
+        });
+        assert_eq!(report.result(), Ok(()));
+    }
+}
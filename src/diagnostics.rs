@@ -0,0 +1,202 @@
+#![warn(missing_docs)]
+
+use ash::vk;
+use egui::{Context, Grid};
+
+use crate::{TextureInfo, TextureKind};
+
+/// What the last frame drew, returned by
+/// [`Renderer::frame_stats`](crate::Renderer::frame_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Render passes the frame was painted in.
+    pub passes: usize,
+    /// Meshes drawn, including those skipped for unregistered textures.
+    pub meshes: usize,
+    /// Vertices of the meshes.
+    pub vertices: usize,
+    /// Indices of the meshes.
+    pub indices: usize,
+    /// Paint callbacks.
+    pub callbacks: usize,
+}
+
+/// The images the [`Renderer`](crate::Renderer) paints into, returned by
+/// [`Renderer::render_target_info`](crate::Renderer::render_target_info).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTargetInfo {
+    /// Size of the images in pixels.
+    pub extent: vk::Extent2D,
+    /// Format of the images.
+    pub format: vk::Format,
+    /// Number of images.
+    pub image_count: usize,
+    /// Physical pixels per point.
+    pub scale_factor: f64,
+    /// The images are those of a swapchain, not set with
+    /// [`Renderer::set_render_targets`](crate::Renderer::set_render_targets).
+    pub swapchain: bool,
+    /// The swapchain must be updated before painting again, see
+    /// [`NeedsSwapchainUpdate`](crate::NeedsSwapchainUpdate).
+    pub needs_swapchain_update: bool,
+}
+
+/// Everything the diagnostics window of
+/// [`Integration::show_diagnostics`](crate::Integration::show_diagnostics) shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics {
+    /// Number of frames painted so far.
+    pub frame_count: u64,
+    /// What the last frame drew.
+    pub frame_stats: FrameStats,
+    /// The render targets.
+    pub render_target: RenderTargetInfo,
+    /// The textures the renderer holds.
+    pub textures: Vec<TextureInfo>,
+    /// Window events handled since the platform was created.
+    pub window_events: u64,
+    /// egui input events of the current frame.
+    pub input_events: usize,
+}
+
+impl Diagnostics {
+    // Show the diagnostics window in `context`.
+    pub(crate) fn show(&self, context: &Context) {
+        egui::Window::new("Diagnostics").show(context, |ui| {
+            let dt = context.input().unstable_dt;
+            ui.heading("Frame");
+            Grid::new("diagnostics_frame").num_columns(2).show(ui, |ui| {
+                ui.label("Frames painted");
+                ui.label(self.frame_count.to_string());
+                ui.end_row();
+                ui.label("Frame time");
+                ui.label(format!("{:.2} ms ({:.0} fps)", dt * 1000.0, 1.0 / dt.max(f32::EPSILON)));
+                ui.end_row();
+                ui.label("Passes");
+                ui.label(self.frame_stats.passes.to_string());
+                ui.end_row();
+                ui.label("Meshes");
+                ui.label(format!(
+                    "{} ({} vertices, {} indices)",
+                    self.frame_stats.meshes, self.frame_stats.vertices, self.frame_stats.indices
+                ));
+                ui.end_row();
+                ui.label("Paint callbacks");
+                ui.label(self.frame_stats.callbacks.to_string());
+                ui.end_row();
+            });
+
+            ui.heading("Render target");
+            let render_target = &self.render_target;
+            Grid::new("diagnostics_render_target").num_columns(2).show(ui, |ui| {
+                ui.label("Images");
+                ui.label(format!(
+                    "{} x {}x{} {:?}{}",
+                    render_target.image_count,
+                    render_target.extent.width,
+                    render_target.extent.height,
+                    render_target.format,
+                    if render_target.swapchain { " (swapchain)" } else { "" }
+                ));
+                ui.end_row();
+                ui.label("Scale factor");
+                ui.label(format!("{:.2}", render_target.scale_factor));
+                ui.end_row();
+                ui.label("Needs swapchain update");
+                ui.label(render_target.needs_swapchain_update.to_string());
+                ui.end_row();
+            });
+
+            ui.heading("Events");
+            Grid::new("diagnostics_events").num_columns(2).show(ui, |ui| {
+                ui.label("Window events");
+                ui.label(self.window_events.to_string());
+                ui.end_row();
+                ui.label("Input events this frame");
+                ui.label(self.input_events.to_string());
+                ui.end_row();
+            });
+
+            let memory_bytes = |kind: TextureKind| {
+                self.textures
+                    .iter()
+                    .filter(|texture| texture.kind == kind)
+                    .map(|texture| texture.memory_bytes)
+                    .sum::<u64>()
+            };
+            ui.heading("Memory");
+            Grid::new("diagnostics_memory").num_columns(2).show(ui, |ui| {
+                for (label, kind) in [
+                    ("egui textures", TextureKind::Managed),
+                    ("Integration textures", TextureKind::Owned),
+                ] {
+                    ui.label(label);
+                    ui.label(format_bytes(memory_bytes(kind)));
+                    ui.end_row();
+                }
+            });
+
+            ui.collapsing(format!("Textures ({})", self.textures.len()), |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    Grid::new("diagnostics_textures").num_columns(4).striped(true).show(ui, |ui| {
+                        for texture in &self.textures {
+                            ui.label(format!("{:?}", texture.id));
+                            ui.label(format!("{:?}", texture.kind));
+                            ui.label(match texture.size {
+                                Some((width, height)) => format!("{}x{}", width, height),
+                                None => "?".to_owned(),
+                            });
+                            ui.label(format_bytes(texture.memory_bytes));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        });
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_window_lists_the_textures() {
+        let diagnostics = Diagnostics {
+            frame_count: 3,
+            frame_stats: FrameStats::default(),
+            render_target: RenderTargetInfo {
+                extent: vk::Extent2D { width: 800, height: 600 },
+                format: vk::Format::B8G8R8A8_SRGB,
+                image_count: 3,
+                scale_factor: 1.0,
+                swapchain: true,
+                needs_swapchain_update: false,
+            },
+            textures: vec![TextureInfo {
+                id: egui::TextureId::Managed(0),
+                kind: TextureKind::Managed,
+                size: Some((2048, 64)),
+                format: Some(vk::Format::R8G8B8A8_UNORM),
+                memory_bytes: 2048 * 64 * 8,
+                last_update_frame: Some(1),
+                last_draw_frame: Some(3),
+            }],
+            window_events: 10,
+            input_events: 2,
+        };
+        let context = Context::default();
+        context.begin_frame(Default::default());
+        diagnostics.show(&context);
+        assert!(!context.end_frame().shapes.is_empty());
+        assert_eq!(format_bytes(2048 * 64 * 8), "1.0 MiB");
+    }
+}
@@ -0,0 +1,178 @@
+#![warn(missing_docs)]
+
+use std::os::raw::c_char;
+
+use anyhow::Result;
+use ash::extensions::khr::{Display, Surface};
+use ash::{vk, Entry, Instance};
+
+use crate::{SurfaceFormatChoice, SurfaceFormatPreference};
+
+/// Display settings for [`create_display_surface`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Index of the display in `get_physical_device_display_properties`.
+    pub display_index: usize,
+    /// Resolution of the display mode. The largest mode with the highest refresh rate if
+    /// `None`.
+    pub visible_region: Option<vk::Extent2D>,
+}
+
+/// A Vulkan surface presenting directly to a display, without a window system. Create a
+/// swapchain for it and draw [`UiInstance`](crate::UiInstance)s with
+/// [`Renderer::paint_ui_instance`](crate::Renderer::paint_ui_instance).
+pub struct DisplaySurface {
+    /// Surface extension loader.
+    pub surface_loader: Surface,
+    /// The surface. Destroy it with `surface_loader` before the instance.
+    pub surface: vk::SurfaceKHR,
+    /// Width of the display mode in pixels.
+    pub physical_width: u32,
+    /// Height of the display mode in pixels.
+    pub physical_height: u32,
+    /// Refresh rate of the display mode in millihertz.
+    pub refresh_rate: u32,
+    /// Physical size of the display in millimeters, zero if unknown.
+    pub physical_dimensions: vk::Extent2D,
+}
+
+impl DisplaySurface {
+    /// Pick the most preferred surface format the surface supports.
+    pub fn select_surface_format(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        preference: &SurfaceFormatPreference,
+    ) -> Result<SurfaceFormatChoice> {
+        let formats = unsafe {
+            self.surface_loader
+                .get_physical_device_surface_formats(physical_device, self.surface)?
+        };
+        preference
+            .select(&formats)
+            .ok_or_else(|| anyhow::anyhow!("The surface supports none of the preferred formats: {:?}", formats))
+    }
+}
+
+/// Instance extensions required by [`create_display_surface`]. The device additionally needs
+/// `VK_KHR_swapchain` as with a window.
+pub fn required_display_instance_extensions() -> Vec<*const c_char> {
+    vec![Surface::name().as_ptr(), Display::name().as_ptr()]
+}
+
+/// Create a surface presenting to a display of `physical_device` in the mode picked by
+/// `options`, on the first plane that can show the display.
+///
+/// # Safety
+/// `instance` must have been created with [`required_display_instance_extensions`], and must
+/// be destroyed after the returned surface.
+pub unsafe fn create_display_surface(
+    entry: &Entry,
+    instance: &Instance,
+    physical_device: vk::PhysicalDevice,
+    options: &DisplayOptions,
+) -> Result<DisplaySurface> {
+    let display_loader = Display::new(entry, instance);
+    let displays = display_loader.get_physical_device_display_properties(physical_device)?;
+    let display = displays.get(options.display_index).ok_or_else(|| {
+        anyhow::anyhow!(
+            "There is no display {}; the device has {} displays.",
+            options.display_index,
+            displays.len()
+        )
+    })?;
+    let modes = display_loader.get_display_mode_properties(physical_device, display.display)?;
+    let mode = select_display_mode(&modes, options.visible_region).ok_or_else(|| {
+        anyhow::anyhow!("The display has no mode with visible region {:?}.", options.visible_region)
+    })?;
+
+    let planes = display_loader.get_physical_device_display_plane_properties(physical_device)?;
+    let mut plane_index = None;
+    for (index, plane) in planes.iter().enumerate() {
+        // the plane must be free or already show the display
+        if plane.current_display != vk::DisplayKHR::null() && plane.current_display != display.display {
+            continue;
+        }
+        let supported = display_loader.get_display_plane_supported_displays(physical_device, index as u32)?;
+        if supported.contains(&display.display) {
+            plane_index = Some(index as u32);
+            break;
+        }
+    }
+    let plane_index = plane_index.ok_or_else(|| anyhow::anyhow!("No plane can show the display."))?;
+    let capabilities = display_loader.get_display_plane_capabilities(physical_device, mode.display_mode, plane_index)?;
+    let alpha_mode = [
+        vk::DisplayPlaneAlphaFlagsKHR::OPAQUE,
+        vk::DisplayPlaneAlphaFlagsKHR::GLOBAL,
+        vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL,
+        vk::DisplayPlaneAlphaFlagsKHR::PER_PIXEL_PREMULTIPLIED,
+    ]
+    .into_iter()
+    .find(|&alpha_mode| capabilities.supported_alpha.contains(alpha_mode))
+    .ok_or_else(|| anyhow::anyhow!("The plane supports no alpha mode."))?;
+
+    let extent = mode.parameters.visible_region;
+    let surface = display_loader.create_display_plane_surface(
+        &vk::DisplaySurfaceCreateInfoKHR::builder()
+            .display_mode(mode.display_mode)
+            .plane_index(plane_index)
+            .plane_stack_index(planes[plane_index as usize].current_stack_index)
+            .transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
+            .global_alpha(1.0)
+            .alpha_mode(alpha_mode)
+            .image_extent(extent),
+        None,
+    )?;
+    Ok(DisplaySurface {
+        surface_loader: Surface::new(entry, instance),
+        surface,
+        physical_width: extent.width,
+        physical_height: extent.height,
+        refresh_rate: mode.parameters.refresh_rate,
+        physical_dimensions: display.physical_dimensions,
+    })
+}
+
+// The mode with `visible_region`, or the largest mode, preferring higher refresh rates.
+fn select_display_mode(
+    modes: &[vk::DisplayModePropertiesKHR],
+    visible_region: Option<vk::Extent2D>,
+) -> Option<vk::DisplayModePropertiesKHR> {
+    modes
+        .iter()
+        .filter(|mode| visible_region.is_none_or(|region| mode.parameters.visible_region == region))
+        .max_by_key(|mode| {
+            let region = mode.parameters.visible_region;
+            (region.width as u64 * region.height as u64, mode.parameters.refresh_rate)
+        })
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn mode(raw: u64, width: u32, height: u32, refresh_rate: u32) -> vk::DisplayModePropertiesKHR {
+        vk::DisplayModePropertiesKHR {
+            display_mode: vk::DisplayModeKHR::from_raw(raw),
+            parameters: vk::DisplayModeParametersKHR {
+                visible_region: vk::Extent2D { width, height },
+                refresh_rate,
+            },
+        }
+    }
+
+    #[test]
+    fn the_largest_fastest_mode_is_picked_by_default() {
+        let modes = [
+            mode(1, 1280, 720, 60000),
+            mode(2, 1920, 1080, 30000),
+            mode(3, 1920, 1080, 60000),
+            mode(4, 800, 480, 75000),
+        ];
+        let pick = |region| select_display_mode(&modes, region).map(|mode| mode.display_mode.as_raw());
+        assert_eq!(pick(None), Some(3));
+        assert_eq!(pick(Some(vk::Extent2D { width: 800, height: 480 })), Some(4));
+        assert_eq!(pick(Some(vk::Extent2D { width: 640, height: 480 })), None);
+    }
+}
@@ -0,0 +1,61 @@
+#![warn(missing_docs)]
+
+use egui::epaint::Fonts;
+use egui::{pos2, FontId, Rect, Vec2};
+
+/// Id of the font atlas texture, the texture egui draws all text from.
+pub const FONT_TEXTURE_ID: egui::TextureId = egui::TextureId::Managed(0);
+
+/// Where a glyph lies in the font atlas, returned by
+/// [`Platform::glyph_uv`](crate::Platform::glyph_uv).
+///
+/// Lets paint callbacks and custom text renderers draw glyphs rasterized by egui from
+/// [`FONT_TEXTURE_ID`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphUv {
+    /// Normalized texture coordinates of the glyph; empty for glyphs without texels, e.g. spaces.
+    pub uv: Rect,
+    /// Offset of the top left corner of the glyph from the pen position on the baseline row,
+    /// in points.
+    pub offset: Vec2,
+    /// Size of the glyph on screen, in points.
+    pub size: Vec2,
+    /// Distance to move the pen after the glyph, in points.
+    pub advance: f32,
+}
+
+// Lay `c` out on its own to find, or rasterize, its glyph.
+pub(crate) fn glyph_uv(fonts: &Fonts, font_id: &FontId, c: char) -> Option<GlyphUv> {
+    let galley = fonts.layout_no_wrap(c.to_string(), font_id.clone(), egui::Color32::WHITE);
+    let glyph = galley.rows.first()?.glyphs.first()?;
+    // read after the layout, which may have grown the atlas
+    let [width, height] = fonts.font_image_size();
+    let uv_rect = glyph.uv_rect;
+    let uv = |[x, y]: [u16; 2]| pos2(x as f32 / width as f32, y as f32 / height as f32);
+    Some(GlyphUv {
+        uv: Rect::from_min_max(uv(uv_rect.min), uv(uv_rect.max)),
+        offset: uv_rect.offset,
+        size: uv_rect.size,
+        advance: glyph.size.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Context;
+
+    #[test]
+    fn glyphs_lie_inside_the_atlas() {
+        let context = Context::default();
+        context.begin_frame(Default::default());
+        let fonts = context.fonts();
+        let glyph = glyph_uv(&fonts, &FontId::proportional(14.0), 'A').unwrap();
+        assert!(glyph.uv.width() > 0.0 && glyph.uv.height() > 0.0);
+        assert!(Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)).contains_rect(glyph.uv));
+        assert!(glyph.advance > 0.0);
+
+        let space = glyph_uv(&fonts, &FontId::proportional(14.0), ' ').unwrap();
+        assert_eq!(space.uv.area(), 0.0);
+    }
+}
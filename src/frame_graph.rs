@@ -0,0 +1,85 @@
+#![warn(missing_docs)]
+
+use ash::vk;
+use egui::TexturesDelta;
+
+use crate::{AllocatorTrait, NeedsSwapchainUpdate, Renderer};
+
+/// An image a pass reads or writes, with the layouts, stages and accesses of the pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDependency {
+    /// The image, if the integration knows it. User textures are registered by view, so only
+    /// those registered with an owning queue family report their image.
+    pub image: Option<vk::Image>,
+    /// The view the pass uses.
+    pub image_view: vk::ImageView,
+    /// Layout the image must be in when the pass starts.
+    pub layout: vk::ImageLayout,
+    /// Layout the pass leaves the image in.
+    pub final_layout: vk::ImageLayout,
+    /// Stages the pass accesses the image at.
+    pub stage_mask: vk::PipelineStageFlags2,
+    /// Accesses of the pass.
+    pub access_mask: vk::AccessFlags2,
+}
+
+/// The images a pass depends on, for scheduling it in a frame graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PassDependencies {
+    /// Images sampled by the pass, e.g. the font atlas and user textures.
+    pub reads: Vec<ImageDependency>,
+    /// Images rendered into: the render target and the depth attachment, if any.
+    pub writes: Vec<ImageDependency>,
+}
+
+/// A pass that can be registered as a node of a frame graph.
+///
+/// The frame graph calls [`dependencies`](Self::dependencies) when building the graph,
+/// then records the barriers the dependencies call for and [`begin`](Self::begin),
+/// [`record`](Self::record) and [`end`](Self::end) in order into the command buffer of the
+/// node.
+pub trait FrameGraphNode {
+    /// Error of [`record`](Self::record).
+    type Error;
+
+    /// The images the pass reads and writes.
+    fn dependencies(&self) -> PassDependencies;
+
+    /// Record the commands that come before the pass.
+    fn begin(&mut self, _command_buffer: vk::CommandBuffer) {}
+
+    /// Record the pass.
+    fn record(&mut self, command_buffer: vk::CommandBuffer) -> Result<(), Self::Error>;
+
+    /// Record the commands that come after the pass.
+    fn end(&mut self, _command_buffer: vk::CommandBuffer) {}
+}
+
+/// The egui pass of a frame as a [`FrameGraphNode`], created with
+/// [`Renderer::frame_graph_pass`].
+///
+/// Recording it paints the frame like [`Renderer::paint`], texture uploads included.
+pub struct EguiPass<'a, A: AllocatorTrait> {
+    pub(crate) renderer: &'a mut Renderer<A>,
+    pub(crate) swapchain_image_index: usize,
+    pub(crate) textures_delta: TexturesDelta,
+    pub(crate) clipped_primitives: Vec<egui::ClippedPrimitive>,
+}
+
+impl<'a, A: AllocatorTrait> FrameGraphNode for EguiPass<'a, A> {
+    type Error = NeedsSwapchainUpdate;
+
+    fn dependencies(&self) -> PassDependencies {
+        self.renderer
+            .pass_dependencies(self.swapchain_image_index, &self.clipped_primitives)
+    }
+
+    fn record(&mut self, command_buffer: vk::CommandBuffer) -> Result<(), NeedsSwapchainUpdate> {
+        self.renderer.paint(
+            command_buffer,
+            self.swapchain_image_index,
+            std::mem::take(&mut self.textures_delta),
+            std::mem::take(&mut self.clipped_primitives),
+        )
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::mpsc::Sender;
+
+use egui::{ClippedPrimitive, TexturesDelta};
+
+pub(crate) struct QueuedFrame {
+    pub(crate) textures_delta: TexturesDelta,
+    pub(crate) clipped_primitives: Vec<ClippedPrimitive>,
+}
+
+/// Handle for sending tessellated frames from the [`Platform`](crate::Platform) thread to
+/// the [`Renderer`](crate::Renderer).
+///
+/// Get one with [`Renderer::frame_queue`](crate::Renderer::frame_queue). Queued frames are
+/// drawn by [`Renderer::paint_queued`](crate::Renderer::paint_queued).
+#[derive(Clone)]
+pub struct FrameQueue {
+    pub(crate) sender: Sender<QueuedFrame>,
+}
+
+impl FrameQueue {
+    /// Queue a frame, e.g. the result of
+    /// [`Platform::end_frame_and_tessellate`](crate::Platform::end_frame_and_tessellate).
+    pub fn send(&self, textures_delta: TexturesDelta, clipped_primitives: Vec<ClippedPrimitive>) {
+        let frame = QueuedFrame {
+            textures_delta,
+            clipped_primitives,
+        };
+        if self.sender.send(frame).is_err() {
+            log::warn!("The renderer has been dropped; the frame is ignored.");
+        }
+    }
+}
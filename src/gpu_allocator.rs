@@ -1,28 +1,32 @@
 use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
-use gpu_allocator::vulkan::*;
 
 use crate::allocator::{AllocationCreateInfoTrait, AllocationTrait, AllocatorTrait};
 
+// The allocator types the integration is implemented for, so applications do not need a
+// matching gpu-allocator dependency of their own.
+pub use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, Allocator, AllocatorCreateDesc};
+
 impl AllocationTrait for Allocation {
     unsafe fn memory(&self) -> ash::vk::DeviceMemory {
-        Allocation::memory(&self)
+        Allocation::memory(self)
     }
 
     fn offset(&self) -> u64 {
-        Allocation::offset(&self)
+        Allocation::offset(self)
     }
 
     fn size(&self) -> u64 {
-        Allocation::size(&self)
+        Allocation::size(self)
     }
 
     fn mapped_ptr(&self) -> Option<std::ptr::NonNull<std::ffi::c_void>> {
-        Allocation::mapped_ptr(&self)
+        Allocation::mapped_ptr(self)
     }
 }
 
+// gpu-allocator 0.18 has no dedicated allocations, so `dedicated` keeps its default.
 impl AllocationCreateInfoTrait for AllocationCreateDesc<'static> {
     fn new(
         requirements: ash::vk::MemoryRequirements,
@@ -0,0 +1,174 @@
+#![warn(missing_docs)]
+
+use ash::vk;
+
+/// An image view of the application to draw as a user texture, with the metadata
+/// [`Renderer::register_texture`](crate::Renderer::register_texture) validates.
+///
+/// Mistakes like a view in the wrong layout otherwise only show up as garbage on screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserTexture {
+    /// View of the image, with a color aspect.
+    pub image_view: vk::ImageView,
+    /// Sampler the texture is drawn with.
+    pub sampler: vk::Sampler,
+    /// Format of the view.
+    pub format: vk::Format,
+    /// Size of the view in texels.
+    pub extent: vk::Extent2D,
+    /// Layout the image is in whenever a frame drawing it runs: `SHADER_READ_ONLY_OPTIMAL`,
+    /// `READ_ONLY_OPTIMAL` or `GENERAL`.
+    pub layout: vk::ImageLayout,
+    /// Name for the leak report of [`Renderer::destroy`](crate::Renderer::destroy), texture
+    /// debuggers and validation messages.
+    pub name: Option<String>,
+    /// The image of the view, all of whose mip levels and layers are transitioned by
+    /// [`Renderer::transition_user_texture`](crate::Renderer::transition_user_texture).
+    pub image: Option<vk::Image>,
+}
+
+impl UserTexture {
+    /// A texture in `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn new(image_view: vk::ImageView, sampler: vk::Sampler, format: vk::Format, extent: vk::Extent2D) -> Self {
+        Self {
+            image_view,
+            sampler,
+            format,
+            extent,
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            name: None,
+            image: None,
+        }
+    }
+
+    /// Name the texture.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Set the image of the view.
+    pub fn with_image(mut self, image: vk::Image) -> Self {
+        self.image = Some(image);
+        self
+    }
+
+    // Panic on textures that cannot be sampled as registered.
+    pub(crate) fn validate(&self) {
+        let name = self.name.as_deref().unwrap_or("user texture");
+        assert_ne!(self.image_view, vk::ImageView::null(), "The image view of {} is null.", name);
+        assert_ne!(self.sampler, vk::Sampler::null(), "The sampler of {} is null.", name);
+        assert!(
+            self.extent.width > 0 && self.extent.height > 0,
+            "{} is empty: {}x{}",
+            name,
+            self.extent.width,
+            self.extent.height
+        );
+        assert!(
+            is_color_format(self.format),
+            "{} has format {:?}, which is not a color format.",
+            name,
+            self.format
+        );
+        assert!(
+            matches!(
+                self.layout,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL | vk::ImageLayout::READ_ONLY_OPTIMAL | vk::ImageLayout::GENERAL
+            ),
+            "{} is in layout {:?}, which cannot be sampled.",
+            name,
+            self.layout
+        );
+    }
+}
+
+/// An image of the application to render the UI into, see
+/// [`Renderer::set_target_images`](crate::Renderer::set_target_images).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetImage {
+    /// The image, created with `COLOR_ATTACHMENT` usage.
+    pub image: vk::Image,
+    /// Format of the image.
+    pub format: vk::Format,
+    /// Size of the image in pixels.
+    pub extent: vk::Extent2D,
+    /// Array layer rendered into.
+    pub layer: u32,
+    /// Name for validation messages.
+    pub name: Option<String>,
+}
+
+impl TargetImage {
+    /// Layer 0 of `image`.
+    pub fn new(image: vk::Image, format: vk::Format, extent: vk::Extent2D) -> Self {
+        Self {
+            image,
+            format,
+            extent,
+            layer: 0,
+            name: None,
+        }
+    }
+
+    /// Name the image.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+}
+
+// Panic on target images that cannot be rendered into together.
+pub(crate) fn validate_target_images(targets: &[TargetImage]) {
+    let first = targets.first().expect("No target images.");
+    for target in targets {
+        let name = target.name.as_deref().unwrap_or("target image");
+        assert_ne!(target.image, vk::Image::null(), "The image of {} is null.", name);
+        assert!(
+            is_color_format(target.format),
+            "{} has format {:?}, which is not a color format.",
+            name,
+            target.format
+        );
+        assert!(
+            (target.format, target.extent, target.layer) == (first.format, first.extent, first.layer),
+            "{} does not match the format, extent and layer of the first target image.",
+            name
+        );
+    }
+}
+
+fn is_color_format(format: vk::Format) -> bool {
+    !matches!(
+        format,
+        vk::Format::UNDEFINED
+            | vk::Format::D16_UNORM
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D32_SFLOAT
+            | vk::Format::S8_UINT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    #[should_panic(expected = "minimap is in layout COLOR_ATTACHMENT_OPTIMAL")]
+    fn textures_in_attachment_layout_are_rejected() {
+        let mut texture = UserTexture::new(
+            vk::ImageView::from_raw(1),
+            vk::Sampler::from_raw(2),
+            vk::Format::R8G8B8A8_SRGB,
+            vk::Extent2D { width: 256, height: 256 },
+        )
+        .with_name("minimap");
+        texture.validate();
+        texture.layout = vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+        texture.validate();
+    }
+}
@@ -0,0 +1,83 @@
+#![warn(missing_docs)]
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use crate::UiInstance;
+
+/// Input of a [`UiInstance`] that does not come from winit, e.g. evdev devices of a kiosk
+/// presenting to a display with [`create_display_surface`](crate::create_display_surface).
+pub trait InputSource {
+    /// Push the input received since the last poll into `ui_instance`. Called once per frame,
+    /// before [`UiInstance::begin_frame`].
+    fn poll(&mut self, ui_instance: &mut UiInstance);
+}
+
+/// Events sent by another thread, e.g. one blocking on evdev reads.
+///
+/// When the sending thread exits, a warning is logged once and the source stays empty.
+pub struct ChannelInput {
+    receiver: Receiver<egui::Event>,
+    disconnected: bool,
+}
+
+impl ChannelInput {
+    /// Receive the events sent to the sender of `receiver`.
+    pub fn new(receiver: Receiver<egui::Event>) -> Self {
+        Self {
+            receiver,
+            disconnected: false,
+        }
+    }
+
+    /// Whether every sender has been dropped, e.g. because the input thread exited.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+}
+
+impl InputSource for ChannelInput {
+    fn poll(&mut self, ui_instance: &mut UiInstance) {
+        if self.disconnected {
+            return;
+        }
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) => ui_instance.push_event(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    log::warn!("The input source disconnected.");
+                    self.disconnected = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn events_sent_by_another_thread_reach_the_next_frame() {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || sender.send(egui::Event::Text("evdev".to_owned())).unwrap())
+            .join()
+            .unwrap();
+        let mut source = ChannelInput::new(receiver);
+        let mut ui_instance = UiInstance::new(640, 480, 1.0);
+        ui_instance.poll_input(&mut source);
+        ui_instance.begin_frame();
+        assert_eq!(
+            ui_instance.context().input().events,
+            vec![egui::Event::Text("evdev".to_owned())]
+        );
+        ui_instance.end_frame();
+
+        // the thread exited, which is only reported once
+        assert!(source.is_disconnected());
+        ui_instance.poll_input(&mut source);
+        assert!(source.is_disconnected());
+    }
+}
@@ -0,0 +1,162 @@
+#![warn(missing_docs)]
+
+use egui::epaint::ClippedShape;
+use egui::{pos2, Context, Id, LayerId, Order, Rect, Shape};
+
+// egui drains the shapes of a frame without their layers, so the end of every known layer is
+// marked with a Noop whose clip rect no painter produces, holding the index of the layer.
+const MARKER_TAG: f32 = -8_388_607.0;
+
+fn marker(index: usize) -> Rect {
+    Rect::from_min_max(pos2(f32::INFINITY, index as f32), pos2(f32::NEG_INFINITY, MARKER_TAG))
+}
+
+fn marker_index(shape: &ClippedShape) -> Option<usize> {
+    let ClippedShape(clip_rect, shape) = shape;
+    let is_marker = matches!(shape, Shape::Noop)
+        && clip_rect.min.x == f32::INFINITY
+        && clip_rect.max.x == f32::NEG_INFINITY
+        && clip_rect.max.y == MARKER_TAG;
+    is_marker.then_some(clip_rect.min.y as usize)
+}
+
+// Layers egui paints to that are not areas.
+pub(crate) fn default_tracked_layers() -> Vec<LayerId> {
+    vec![
+        LayerId::background(),
+        LayerId::new(Order::Foreground, Id::new("panel_resize")),
+        LayerId::debug(),
+    ]
+}
+
+// Mark the end of `layer_ids` in the shapes of the current frame of `context`.
+pub(crate) fn mark_layers(context: &Context, layer_ids: &[LayerId]) {
+    for (index, &layer_id) in layer_ids.iter().enumerate() {
+        let mut painter = context.layer_painter(layer_id);
+        painter.set_clip_rect(marker(index));
+        painter.add(Shape::Noop);
+    }
+}
+
+/// Shapes of a frame grouped by the egui layer they were painted on, back to front.
+///
+/// Returned by [`Platform::end_frame_layered`](crate::Platform::end_frame_layered). Take the
+/// layers of each render pass, tessellate them with [`egui::Context::tessellate`] and paint
+/// them with [`Renderer::paint_passes`](crate::Renderer::paint_passes), e.g.
+/// `shapes.take(|layer_id| layer_id.order == Order::Background)` for the panels behind a
+/// modal dialog.
+///
+/// Layers painted with [`egui::Context::layer_painter`] that are not areas are only known
+/// once registered with [`Platform::track_layer`](crate::Platform::track_layer); until then
+/// their shapes count as part of the layer drawn after them.
+#[derive(Default)]
+pub struct LayeredShapes {
+    layers: Vec<(LayerId, Vec<ClippedShape>)>,
+}
+
+impl LayeredShapes {
+    // Split `shapes` drained after `mark_layers(layer_ids)`.
+    pub(crate) fn new(layer_ids: &[LayerId], shapes: Vec<ClippedShape>) -> Self {
+        let mut layers: Vec<(LayerId, Vec<ClippedShape>)> = vec![];
+        let mut run = vec![];
+        for shape in shapes {
+            match marker_index(&shape) {
+                Some(index) => {
+                    if !run.is_empty() {
+                        layers.push((layer_ids[index], std::mem::take(&mut run)));
+                    }
+                }
+                None => run.push(shape),
+            }
+        }
+        // painted after the last marker, e.g. by egui while ending the frame
+        if !run.is_empty() {
+            match layers.last_mut() {
+                Some((_, shapes)) => shapes.append(&mut run),
+                None => layers.push((LayerId::debug(), run)),
+            }
+        }
+        Self { layers }
+    }
+
+    /// Layers with shapes left, back to front.
+    pub fn layer_ids(&self) -> impl Iterator<Item = LayerId> + '_ {
+        self.layers.iter().map(|&(layer_id, _)| layer_id)
+    }
+
+    /// Remove the shapes of the layers `filter` returns true for, back to front.
+    pub fn take(&mut self, mut filter: impl FnMut(LayerId) -> bool) -> Vec<ClippedShape> {
+        let mut shapes = vec![];
+        self.layers.retain_mut(|(layer_id, layer_shapes)| {
+            if filter(*layer_id) {
+                shapes.append(layer_shapes);
+                false
+            } else {
+                true
+            }
+        });
+        shapes
+    }
+
+    /// Split the shapes of the layers left into the groups before each of `orders`, which
+    /// must be ascending, and the group after the last, back to front.
+    ///
+    /// E.g. `[Order::Foreground]` splits the panels and windows from popups and tooltips for
+    /// [`Renderer::paint_layered`](crate::Renderer::paint_layered).
+    pub fn split_before(mut self, orders: &[Order]) -> Vec<Vec<ClippedShape>> {
+        let mut groups = orders
+            .iter()
+            .map(|&order| self.take(|layer_id| layer_id.order < order))
+            .collect::<Vec<_>>();
+        groups.push(self.into_shapes());
+        groups
+    }
+
+    /// The shapes of all layers left, back to front.
+    pub fn into_shapes(self) -> Vec<ClippedShape> {
+        self.layers.into_iter().flat_map(|(_, shapes)| shapes).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Color32;
+
+    #[test]
+    fn shapes_are_grouped_by_layer() {
+        let context = Context::default();
+        context.begin_frame(Default::default());
+        let window = LayerId::new(Order::Middle, Id::new("window"));
+        let tooltip = LayerId::new(Order::Tooltip, Id::new("tooltip"));
+        let rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        for layer_id in [tooltip, window, LayerId::background()] {
+            context.layer_painter(layer_id).rect_filled(rect, 0.0, Color32::RED);
+        }
+        let layer_ids = [LayerId::background(), window, tooltip];
+        mark_layers(&context, &layer_ids);
+        let mut shapes = LayeredShapes::new(&layer_ids, context.end_frame().shapes);
+
+        assert_eq!(shapes.layer_ids().collect::<Vec<_>>(), layer_ids);
+        assert_eq!(shapes.take(|layer_id| layer_id.order == Order::Middle).len(), 1);
+        assert_eq!(shapes.into_shapes().len(), 2);
+    }
+
+    #[test]
+    fn layers_are_split_before_the_given_orders() {
+        let context = Context::default();
+        context.begin_frame(Default::default());
+        let window = LayerId::new(Order::Middle, Id::new("window"));
+        let tooltip = LayerId::new(Order::Tooltip, Id::new("tooltip"));
+        let rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        for layer_id in [tooltip, window, LayerId::background()] {
+            context.layer_painter(layer_id).rect_filled(rect, 0.0, Color32::RED);
+        }
+        let layer_ids = [LayerId::background(), window, tooltip];
+        mark_layers(&context, &layer_ids);
+        let shapes = LayeredShapes::new(&layer_ids, context.end_frame().shapes);
+
+        let groups = shapes.split_before(&[Order::Foreground, Order::Foreground]);
+        assert_eq!(groups.iter().map(Vec::len).collect::<Vec<_>>(), [2, 0, 1]);
+    }
+}
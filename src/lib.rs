@@ -4,6 +4,9 @@
 //! [gpu_allocator](https://github.com/Traverse-Research/gpu-allocator),
 //! but you can also implement AllocatorTrait.
 //!
+//! Warnings and errors are reported through the [log](https://docs.rs/log) facade, with
+//! texture updates and render target rebuilds at trace level.
+//!
 //! # Example
 //! ```sh
 //! cargo run --example example
@@ -15,7 +18,7 @@
 //!
 //! # Usage
 //!
-//! ```
+//! ```ignore
 //! fn main() -> Result<()> {
 //!     let event_loop = EventLoop::new();
 //!     // (1) Call Integration::<Arc<Mutex<Allocator>>>::new() in App::new().
@@ -39,8 +42,9 @@
 //!             }
 //!             Event::MainEventsCleared => app.window.request_redraw(),
 //!             Event::RedrawRequested(_window_id) => {
-//!                 // (4) Call integration.begin_frame(), integration.end_frame(&mut window),
-//!                 // integration.context().tessellate(shapes), integration.paint(...)
+//!                 // (4) Call integration.begin_frame(), integration.end_frame(),
+//!                 // integration.handle_platform_output(&window, output.platform_output),
+//!                 // integration.context().tessellate(output.shapes), integration.paint(...)
 //!                 // in app.draw().
 //!                 app.draw().unwrap();
 //!             }
@@ -54,12 +58,72 @@
 //! [Full example is in examples directory](https://github.com/MatchaChoco010/egui-winit-ash-integration/tree/main/examples)
 
 mod allocator;
+mod clipboard;
+mod device_support;
+mod diagnostics;
+mod display;
+mod font_atlas;
+mod frame_graph;
+mod frame_queue;
+mod handles;
+mod input_source;
 mod integration;
+mod layers;
+mod options;
+mod platform;
+mod queue_audit;
+mod readback;
+mod renderer;
+mod runtime_error;
+mod slow_path;
+mod surface_format;
+mod texture_queue;
+mod touch;
+mod ui_config;
+mod ui_instance;
+mod viewport;
+mod world_space;
 
 pub use allocator::*;
+pub use clipboard::*;
+pub use device_support::*;
+pub use diagnostics::*;
+pub use display::*;
+pub use font_atlas::*;
+pub use frame_graph::*;
+pub use frame_queue::*;
+pub use handles::*;
+pub use input_source::*;
 pub use integration::*;
+pub use layers::*;
+pub use options::*;
+pub use platform::*;
+pub use readback::*;
+pub use renderer::*;
+pub use runtime_error::*;
+pub use surface_format::*;
+pub use texture_queue::*;
+pub use ui_config::*;
+pub use ui_instance::*;
+pub use viewport::*;
+pub use world_space::*;
 
 #[cfg(feature = "gpu-allocator-feature")]
 mod gpu_allocator;
 #[cfg(feature = "gpu-allocator-feature")]
 pub use crate::gpu_allocator::*;
+
+#[cfg(any(test, feature = "mock-allocator"))]
+mod mock_allocator;
+#[cfg(any(test, feature = "mock-allocator"))]
+pub use mock_allocator::*;
+
+#[cfg(feature = "software-renderer")]
+mod software;
+#[cfg(feature = "software-renderer")]
+pub use software::*;
+
+#[cfg(feature = "window")]
+mod window;
+#[cfg(feature = "window")]
+pub use window::*;
@@ -0,0 +1,260 @@
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use ash::vk::{self, Handle};
+
+use crate::allocator::{AllocationCreateInfoTrait, AllocationTrait, AllocatorTrait, DedicatedAllocation};
+use crate::MemoryLocation;
+
+/// Allocation handed out by [`MockAllocator`].
+///
+/// Host visible allocations are backed by heap memory, so `mapped_ptr` can be written to.
+pub struct MockAllocation {
+    id: u64,
+    size: u64,
+    location: MemoryLocation,
+    dedicated: Option<DedicatedAllocation>,
+    host_memory: Option<Box<[u64]>>,
+}
+
+impl MockAllocation {
+    /// Location the allocation was requested with.
+    pub fn location(&self) -> MemoryLocation {
+        self.location
+    }
+
+    /// Resource a dedicated allocation was requested for.
+    pub fn dedicated(&self) -> Option<DedicatedAllocation> {
+        self.dedicated
+    }
+
+    /// Contents of a host visible allocation.
+    pub fn host_memory(&self) -> Option<&[u8]> {
+        self.host_memory
+            .as_ref()
+            .map(|memory| &bytemuck::cast_slice(memory)[..self.size as usize])
+    }
+}
+
+impl AllocationTrait for MockAllocation {
+    unsafe fn memory(&self) -> vk::DeviceMemory {
+        vk::DeviceMemory::from_raw(self.id)
+    }
+
+    fn offset(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn mapped_ptr(&self) -> Option<NonNull<c_void>> {
+        self.host_memory
+            .as_ref()
+            .and_then(|memory| NonNull::new(memory.as_ptr() as *mut c_void))
+    }
+}
+
+/// Create info for [`MockAllocator`].
+#[derive(Debug, Clone, Copy)]
+pub struct MockAllocationCreateInfo {
+    /// Memory requirements of the resource.
+    pub requirements: vk::MemoryRequirements,
+    /// Requested memory location.
+    pub location: MemoryLocation,
+    /// Whether the resource is linear (buffers) or not (optimal tiling images).
+    pub linear: bool,
+    /// Resource a dedicated allocation was requested for.
+    pub dedicated: Option<DedicatedAllocation>,
+}
+
+impl AllocationCreateInfoTrait for MockAllocationCreateInfo {
+    fn new(requirements: vk::MemoryRequirements, location: MemoryLocation, linear: bool) -> Self {
+        Self {
+            requirements,
+            location,
+            linear,
+            dedicated: None,
+        }
+    }
+
+    fn dedicated(self, resource: DedicatedAllocation) -> Self {
+        Self {
+            dedicated: Some(resource),
+            ..self
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockAllocatorState {
+    next_id: u64,
+    live: Vec<(u64, u64, MemoryLocation)>,
+    total_allocations: usize,
+    fail_after: Option<usize>,
+}
+
+/// An [`AllocatorTrait`] implementation that does not need a Vulkan device.
+///
+/// Clones share the same bookkeeping, so a clone kept by a test can inspect what the
+/// integration allocated and freed.
+#[derive(Clone, Default)]
+pub struct MockAllocator {
+    state: Arc<Mutex<MockAllocatorState>>,
+}
+
+impl MockAllocator {
+    /// Create an allocator with no live allocations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of allocations that have not been freed yet.
+    pub fn live_allocations(&self) -> usize {
+        self.state.lock().unwrap().live.len()
+    }
+
+    /// Total size of allocations that have not been freed yet.
+    pub fn live_bytes(&self) -> u64 {
+        self.state.lock().unwrap().live.iter().map(|&(_, size, _)| size).sum()
+    }
+
+    /// Number of live allocations in `location`.
+    pub fn live_allocations_in(&self, location: MemoryLocation) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .live
+            .iter()
+            .filter(|&&(_, _, live_location)| live_location == location)
+            .count()
+    }
+
+    /// Number of successful allocations since creation, including freed ones.
+    pub fn total_allocations(&self) -> usize {
+        self.state.lock().unwrap().total_allocations
+    }
+
+    /// Make every allocation fail once `count` more allocations have succeeded.
+    pub fn fail_after(&self, count: usize) {
+        self.state.lock().unwrap().fail_after = Some(count);
+    }
+}
+
+impl AllocatorTrait for MockAllocator {
+    type Allocation = MockAllocation;
+    type AllocationCreateInfo = MockAllocationCreateInfo;
+
+    fn allocate(&self, desc: Self::AllocationCreateInfo) -> Result<Self::Allocation> {
+        let mut state = self.state.lock().unwrap();
+        match state.fail_after {
+            Some(0) => bail!("MockAllocator: out of memory"),
+            Some(ref mut count) => *count -= 1,
+            None => {}
+        }
+
+        // ids start at 1 so memory handles are never null
+        state.next_id += 1;
+        let id = state.next_id;
+        let size = desc.requirements.size;
+        state.live.push((id, size, desc.location));
+        state.total_allocations += 1;
+
+        let host_memory = match desc.location {
+            MemoryLocation::CpuToGpu | MemoryLocation::GpuToCpu => {
+                Some(vec![0u64; size.div_ceil(8) as usize].into_boxed_slice())
+            }
+            MemoryLocation::Unknown | MemoryLocation::GpuOnly => None,
+        };
+
+        Ok(MockAllocation {
+            id,
+            size,
+            location: desc.location,
+            dedicated: desc.dedicated,
+            host_memory,
+        })
+    }
+
+    fn free(&self, allocation: Self::Allocation) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match state.live.iter().position(|&(id, _, _)| id == allocation.id) {
+            Some(index) => {
+                state.live.swap_remove(index);
+                Ok(())
+            }
+            None => bail!("MockAllocator: allocation {} was not allocated by this allocator or already freed", allocation.id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_info(size: u64, location: MemoryLocation) -> MockAllocationCreateInfo {
+        MockAllocationCreateInfo::new(
+            vk::MemoryRequirements {
+                size,
+                alignment: 4,
+                memory_type_bits: !0,
+            },
+            location,
+            true,
+        )
+    }
+
+    #[test]
+    fn tracks_live_allocations_across_clones() {
+        let allocator = MockAllocator::new();
+        let observer = allocator.clone();
+
+        let a = allocator.allocate(create_info(16, MemoryLocation::CpuToGpu)).unwrap();
+        let b = allocator.allocate(create_info(64, MemoryLocation::GpuOnly)).unwrap();
+        assert_eq!(observer.live_allocations(), 2);
+        assert_eq!(observer.live_bytes(), 80);
+        assert_eq!(observer.live_allocations_in(MemoryLocation::GpuOnly), 1);
+
+        allocator.free(a).unwrap();
+        allocator.free(b).unwrap();
+        assert_eq!(observer.live_allocations(), 0);
+        assert_eq!(observer.total_allocations(), 2);
+    }
+
+    #[test]
+    fn only_host_visible_memory_is_mapped() {
+        let allocator = MockAllocator::new();
+        let host = allocator.allocate(create_info(3, MemoryLocation::CpuToGpu)).unwrap();
+        let device = allocator.allocate(create_info(3, MemoryLocation::GpuOnly)).unwrap();
+
+        let ptr = host.mapped_ptr().unwrap().as_ptr() as *mut u8;
+        unsafe { ptr.copy_from_nonoverlapping([1u8, 2, 3].as_ptr(), 3) };
+        assert_eq!(host.host_memory(), Some(&[1u8, 2, 3][..]));
+        assert!(device.mapped_ptr().is_none());
+        assert_ne!(unsafe { host.memory() }, unsafe { device.memory() });
+    }
+
+    #[test]
+    fn dedicated_allocations_remember_their_resource() {
+        let allocator = MockAllocator::new();
+        let image = vk::Image::from_raw(7);
+        let dedicated = allocator
+            .allocate(create_info(4, MemoryLocation::GpuOnly).dedicated(DedicatedAllocation::Image(image)))
+            .unwrap();
+        let shared = allocator.allocate(create_info(4, MemoryLocation::GpuOnly)).unwrap();
+        assert_eq!(dedicated.dedicated(), Some(DedicatedAllocation::Image(image)));
+        assert_eq!(shared.dedicated(), None);
+    }
+
+    #[test]
+    fn fail_after_simulates_out_of_memory() {
+        let allocator = MockAllocator::new();
+        allocator.fail_after(1);
+        assert!(allocator.allocate(create_info(4, MemoryLocation::GpuOnly)).is_ok());
+        assert!(allocator.allocate(create_info(4, MemoryLocation::GpuOnly)).is_err());
+        assert_eq!(allocator.live_allocations(), 1);
+    }
+}
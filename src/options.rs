@@ -0,0 +1,327 @@
+use ash::vk;
+
+use crate::{MemoryLocation, UiConfig};
+
+/// Options for [`Integration::with_options`](crate::Integration::with_options).
+///
+/// The defaults match the behaviour of [`Integration::new`](crate::Integration::new).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrationOptions {
+    /// Treat vertex and texture colors as premultiplied alpha end-to-end.
+    ///
+    /// egui outputs premultiplied sRGB colors for vertices and textures. With this enabled
+    /// texels are converted to linear like vertex colors before they are multiplied, and
+    /// alpha is composited "over" the target too, which avoids dark fringes and keeps
+    /// coverage when rendering to transparent targets.
+    pub premultiplied_alpha: bool,
+
+    /// Queue family of the queue the command buffers recorded by
+    /// [`Integration::paint`](crate::Integration::paint) are submitted to.
+    ///
+    /// Only used for the queue family ownership transfers of user textures and render
+    /// targets shared with other queue families.
+    pub queue_family_index: u32,
+
+    /// Debug mode tracking the queue family each image of the integration was last used on.
+    ///
+    /// Report the queue family of every submission with
+    /// [`Integration::audit_submission`](crate::Integration::audit_submission); images used
+    /// on another queue family without an ownership transfer, e.g. textures uploaded with
+    /// [`update_textures`](crate::Integration::update_textures) on a transfer queue, are
+    /// logged as warnings instead of causing sporadic device loss.
+    pub queue_audit: bool,
+
+    /// Render the UI into an intermediate image owned by the integration and composite it
+    /// over the render target, see [`UiLayerOptions`].
+    pub ui_layer: Option<UiLayerOptions>,
+
+    /// Draw text with subpixel (LCD) antialiasing using dual-source blending.
+    ///
+    /// Sharper text on standard-DPI monitors with horizontal RGB subpixels. The device must
+    /// be created with the `dualSrcBlend` feature enabled. Not applied to the
+    /// [`ui_layer`](Self::ui_layer), which is composited with a single coverage per pixel.
+    pub subpixel_text: bool,
+
+    /// Record the draws of every frame into a secondary command buffer and execute it again
+    /// while the UI of a swapchain image does not change.
+    ///
+    /// Static UIs then cost almost no CPU per frame. Frames painted with several passes or
+    /// into the [`ui_layer`](Self::ui_layer) are always recorded again. The command pool of
+    /// the secondary command buffers is created for
+    /// [`queue_family_index`](Self::queue_family_index).
+    pub reuse_static_frames: bool,
+
+    /// Tune the render passes for tile-based GPUs (Adreno, Mali, PowerVR, Apple).
+    ///
+    /// The render target is expected in `PRESENT_SRC_KHR` layout instead of
+    /// `COLOR_ATTACHMENT_OPTIMAL` when [`paint`](crate::Integration::paint) is recorded, i.e.
+    /// as the application's own render pass leaves it, so the transition happens inside the
+    /// egui render pass instead of in a separate barrier that flushes the tiles.
+    pub tile_based_gpu: bool,
+
+    /// Layouts of the render target before and after [`paint`](crate::Integration::paint),
+    /// e.g. to run more passes of the application on it afterwards without a transition
+    /// barrier, see [`TargetLayouts`]. Takes precedence over the initial layout of
+    /// [`tile_based_gpu`](Self::tile_based_gpu).
+    pub target_layouts: Option<TargetLayouts>,
+
+    /// Draw into the render target with `vkCmdBeginRendering` (Vulkan 1.3 dynamic rendering)
+    /// instead of a render pass and framebuffers of the integration.
+    ///
+    /// The device must be created with the `dynamicRendering` feature enabled. The layout
+    /// transitions of [`target_layouts`](Self::target_layouts) and
+    /// [`tile_based_gpu`](Self::tile_based_gpu) are recorded as barriers around the draws.
+    /// Not applied to the [`ui_layer`](Self::ui_layer) and offscreen panels, which keep
+    /// their own render passes.
+    pub dynamic_rendering: bool,
+
+    /// Create the pipelines of a render target with a new format on a background thread.
+    ///
+    /// [`update_swapchain`](crate::Integration::update_swapchain) then returns without waiting
+    /// for the driver to compile the shaders; the next [`paint`](crate::Integration::paint)
+    /// waits for them if they are not ready yet. Resizes that keep the format and depth
+    /// attachment reuse the pipelines either way. Not applied to the
+    /// [`ui_layer`](Self::ui_layer), whose pipelines are always created in place.
+    pub background_pipeline_creation: bool,
+
+    /// Size of the pool the descriptor sets of textures are allocated from, see
+    /// [`DescriptorPoolOptions`].
+    pub descriptor_pool: DescriptorPoolOptions,
+
+    /// `maxSamplerAnisotropy` of the device limits if the device was created with the
+    /// `samplerAnisotropy` feature enabled.
+    ///
+    /// Limits [`SamplerOptions::anisotropy`]; `None` disables anisotropic filtering.
+    pub max_sampler_anisotropy: Option<f32>,
+
+    /// Sampler of the font atlas, [`FONT_TEXTURE_ID`](crate::FONT_TEXTURE_ID), independently
+    /// of other textures.
+    ///
+    /// E.g. `filter: vk::Filter::NEAREST` and `max_lod: 0.0` draw small text crisper at
+    /// integer scale factors, where texels map to whole pixels.
+    pub font_sampler: SamplerOptions,
+
+    /// Place the descriptors of textures in a descriptor buffer (`VK_EXT_descriptor_buffer`)
+    /// instead of allocating descriptor sets from a pool, see [`DescriptorBufferOptions`].
+    pub descriptor_buffer: Option<DescriptorBufferOptions>,
+
+    /// Build the egui pipeline layout from the application's descriptor set layouts, see
+    /// [`DescriptorSetLayoutOptions`].
+    pub descriptor_set_layout: Option<DescriptorSetLayoutOptions>,
+
+    /// Size in bytes of push constants of the application, following the ones of egui at
+    /// [`CUSTOM_PUSH_CONSTANTS_OFFSET`](crate::CUSTOM_PUSH_CONSTANTS_OFFSET), e.g. a time or
+    /// theme parameters for custom shaders.
+    ///
+    /// Vulkan allows a single range per shader stage, so they extend the range of egui, visible
+    /// to the vertex and fragment stages. Must be a multiple of 4, and within
+    /// `maxPushConstantsSize` together with the constants of egui. Set them with
+    /// [`Renderer::set_custom_push_constants`](crate::Renderer::set_custom_push_constants).
+    pub custom_push_constants: u32,
+
+    /// Memory the buffers and images of each kind are allocated in, see [`MemoryLocations`].
+    pub memory_locations: MemoryLocations,
+
+    /// Write the texels of egui textures straight into host visible linear images, without
+    /// a staging buffer and copy. Partial updates, e.g. of the font atlas, are still copied
+    /// from a staging buffer, as frames in flight may sample the image.
+    ///
+    /// Only for unified memory architectures (integrated GPUs), where sampling host visible
+    /// memory costs nothing extra, see [`has_unified_memory`](crate::has_unified_memory).
+    /// The images are allocated in `CpuToGpu` memory whatever
+    /// [`MemoryLocations::texture`] is, and sampled in `GENERAL` layout. The device must
+    /// support sampling and copying into linear `R8G8B8A8_UNORM` and, for the font atlas,
+    /// `R8_UNORM` images, which nearly all do.
+    pub unified_memory: bool,
+
+    /// Largest width or height of the textures the integration creates from egui images
+    /// and [`TextureQueue::create_texture`](crate::TextureQueue::create_texture), e.g.
+    /// `maxImageDimension2D` of the device limits or a smaller cap to save memory.
+    ///
+    /// Larger images are downscaled on the CPU with a box filter, keeping their aspect
+    /// ratio, instead of failing to create; meshes sample them with the same UVs. `None`
+    /// creates textures at the size egui sends. Also the max texture side of the font atlas,
+    /// see [`Platform::set_max_texture_side`](crate::Platform::set_max_texture_side).
+    pub max_texture_dimension: Option<u32>,
+
+    /// Rasterize the UI on the CPU with [`SoftwareRenderer`](crate::SoftwareRenderer) and
+    /// only upload and draw the finished frame on the GPU.
+    ///
+    /// A fallback for devices where the regular path renders incorrectly. User textures
+    /// are not drawn in this mode.
+    #[cfg(feature = "software-renderer")]
+    pub software_renderer: bool,
+
+    /// Style, scale and font sizes overriding those passed to the constructor, e.g. loaded
+    /// from a configuration file with the `serde` feature.
+    pub ui_config: UiConfig,
+}
+
+/// Options for [`IntegrationOptions::descriptor_pool`].
+///
+/// Every texture, user texture and streaming texture slot takes one descriptor set. Sets of
+/// released textures are kept for reuse until
+/// [`Integration::trim_caches`](crate::Integration::trim_caches).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorPoolOptions {
+    /// Maximum number of descriptor sets. Also the number of slots of the
+    /// [`descriptor_buffer`](IntegrationOptions::descriptor_buffer).
+    pub max_sets: u32,
+    /// Number of combined image sampler descriptors in the pool.
+    pub combined_image_samplers: u32,
+}
+
+impl Default for DescriptorPoolOptions {
+    fn default() -> Self {
+        Self {
+            max_sets: 1024,
+            combined_image_samplers: 1024,
+        }
+    }
+}
+
+/// Options for [`IntegrationOptions::descriptor_buffer`].
+///
+/// The device must be created with the `descriptorBuffer` and `bufferDeviceAddress` features
+/// enabled, and the allocator must allocate memory with device addresses, e.g. gpu-allocator
+/// created with `buffer_device_address: true`.
+#[derive(Debug, Clone, Copy)]
+pub struct DescriptorBufferOptions {
+    /// Loads the extension functions, e.g. `instance.fp_v1_0().get_device_proc_addr`.
+    pub get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+    /// `descriptorBufferOffsetAlignment` of `VkPhysicalDeviceDescriptorBufferPropertiesEXT`.
+    pub descriptor_buffer_offset_alignment: u64,
+    /// `combinedImageSamplerDescriptorSize` of `VkPhysicalDeviceDescriptorBufferPropertiesEXT`.
+    pub combined_image_sampler_descriptor_size: usize,
+}
+
+impl PartialEq for DescriptorBufferOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_device_proc_addr as usize == other.get_device_proc_addr as usize
+            && self.descriptor_buffer_offset_alignment == other.descriptor_buffer_offset_alignment
+            && self.combined_image_sampler_descriptor_size == other.combined_image_sampler_descriptor_size
+    }
+}
+
+/// Options for [`IntegrationOptions::descriptor_set_layout`].
+///
+/// Makes the egui pipeline layout compatible with the engine's global descriptor sets, so
+/// they stay bound while UI and engine draws are interleaved. Pipeline layouts are only
+/// compatible with the same push constant range, see
+/// [`Renderer::push_constant_range`](crate::Renderer::push_constant_range).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DescriptorSetLayoutOptions {
+    /// Set layouts of the egui pipeline layout, owned by the application and kept alive until
+    /// the integration is destroyed.
+    pub set_layouts: Vec<vk::DescriptorSetLayout>,
+    /// Index of the layout the descriptor sets of textures are allocated with. It must hold
+    /// only one combined image sampler, at [`binding`](Self::binding), visible to the
+    /// fragment stage, and be created with the descriptor buffer flag when
+    /// [`IntegrationOptions::descriptor_buffer`] is set.
+    pub set: u32,
+    /// Binding of the combined image sampler.
+    pub binding: u32,
+}
+
+/// Options for [`IntegrationOptions::memory_locations`].
+///
+/// The defaults suit discrete GPUs. On unified memory architectures (integrated GPUs) all
+/// memory is both device local and host visible, so e.g. textures may go in `CpuToGpu` too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLocations {
+    /// Vertex buffers. Written through a mapping, so the memory must be host visible.
+    pub vertex: MemoryLocation,
+    /// Index buffers. Written through a mapping, so the memory must be host visible.
+    pub index: MemoryLocation,
+    /// Staging buffers texture data is copied to textures from. Written through a mapping, so
+    /// the memory must be host visible.
+    pub staging: MemoryLocation,
+    /// Images of egui textures and of textures created by the integration for user textures.
+    pub texture: MemoryLocation,
+}
+
+impl Default for MemoryLocations {
+    fn default() -> Self {
+        Self {
+            vertex: MemoryLocation::CpuToGpu,
+            index: MemoryLocation::CpuToGpu,
+            staging: MemoryLocation::CpuToGpu,
+            texture: MemoryLocation::GpuOnly,
+        }
+    }
+}
+
+/// Options for [`IntegrationOptions::target_layouts`].
+///
+/// The egui render pass transitions the render target from `initial_layout` to
+/// `final_layout`. Without these options it is expected in `COLOR_ATTACHMENT_OPTIMAL`
+/// layout and left in `PRESENT_SRC_KHR` layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetLayouts {
+    /// Layout the render target is in when `paint` is recorded. The writes of the
+    /// application to it are waited for at any stage unless it is `COLOR_ATTACHMENT_OPTIMAL`
+    /// or `PRESENT_SRC_KHR`.
+    pub initial_layout: vk::ImageLayout,
+    /// Layout the render target is left in, made available to any later stage unless it is
+    /// `PRESENT_SRC_KHR`.
+    pub final_layout: vk::ImageLayout,
+}
+
+/// Sampler of a user texture registered with
+/// [`Integration::register_user_texture_with_sampler`](crate::Integration::register_user_texture_with_sampler),
+/// or got with [`Integration::get_or_create_sampler`](crate::Integration::get_or_create_sampler).
+///
+/// The defaults match the sampler of egui's own textures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOptions {
+    /// Maximum anisotropy, e.g. `Some(16.0)` for mipmapped images drawn at an angle in a
+    /// rotated or zoomed canvas. Clamped to
+    /// [`IntegrationOptions::max_sampler_anisotropy`].
+    pub anisotropy: Option<f32>,
+    /// Magnification and minification filter, and mipmap mode where there is a matching one.
+    pub filter: vk::Filter,
+    /// Most detailed mip level that is sampled.
+    pub min_lod: f32,
+    /// Least detailed mip level that is sampled.
+    pub max_lod: f32,
+    /// Added to the mip level the GPU picks, e.g. `-0.5` to keep a minified sprite sharper.
+    /// Clamped to `maxSamplerLodBias`.
+    pub lod_bias: f32,
+    /// Wrap mode of all coordinates, e.g. `REPEAT` for tiled patterns.
+    pub address_mode: vk::SamplerAddressMode,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        Self {
+            anisotropy: None,
+            filter: vk::Filter::LINEAR,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            lod_bias: 0.0,
+            address_mode: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// Options for the intermediate UI layer, see [`IntegrationOptions::ui_layer`].
+///
+/// Decouples the UI from the render target format (e.g. 10-bit or HDR swapchains) and
+/// resolution. The layer is cleared to transparent every frame and composited with
+/// premultiplied alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiLayerOptions {
+    /// Format of the layer images. Must support color attachment, blending and sampling.
+    pub format: vk::Format,
+    /// Resolution of the layer relative to the render target.
+    pub resolution_scale: f32,
+}
+
+impl Default for UiLayerOptions {
+    fn default() -> Self {
+        Self {
+            format: vk::Format::R8G8B8A8_SRGB,
+            resolution_scale: 1.0,
+        }
+    }
+}
@@ -0,0 +1,1348 @@
+#![warn(missing_docs)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use egui::{
+    emath::{pos2, vec2},
+    Context, Key, PlatformOutput, TexturesDelta};
+use winit::event::{DeviceEvent, Event, ModifiersState, TouchPhase, VirtualKeyCode, WindowEvent};
+use winit::window::{Window, WindowId};
+
+use crate::font_atlas::glyph_uv;
+use crate::layers::{default_tracked_layers, mark_layers};
+use crate::touch::TouchEmulation;
+use crate::viewport::ViewportInfo;
+use crate::{Clipboard, ErrorHook, GlyphUv, LayeredShapes, MemoryClipboard, RuntimeError, SystemClipboard};
+
+/// Clock of the egui frames, returning seconds since an arbitrary start, see
+/// [`Platform::set_time_source`].
+pub type TimeSource = dyn FnMut() -> f64 + Send;
+
+/// The event handling half of [`Integration`](crate::Integration): owns the
+/// [`egui::Context`] and turns winit events into egui input.
+///
+/// Runs on the thread running the winit event loop, independently of the
+/// [`Renderer`](crate::Renderer).
+pub struct Platform {
+    start_time: Option<Instant>,
+    time_source: Option<Box<TimeSource>>,
+    // handle_event ignores the window events of other windows
+    window_id: Option<WindowId>,
+    // begin_frame was called and end_frame was not, yet
+    in_frame: bool,
+    // texture updates of frames ended by recover, sent with the next end_frame
+    recovered_textures_delta: TexturesDelta,
+
+    scale_factor: f64,
+    context: Context,
+    raw_input: egui::RawInput,
+    mouse_pos: egui::Pos2,
+    modifiers_state: ModifiersState,
+    clipboard: Box<dyn Clipboard>,
+    pasted_image: Option<egui::ColorImage>,
+    file_drag: FileDrag,
+    current_cursor_icon: egui::CursorIcon,
+    ime_position: Option<egui::Pos2>,
+    pointer_buttons: Vec<egui::PointerButton>,
+    pointer_in_window: bool,
+    wants_pointer_input: bool,
+    wants_keyboard_input: bool,
+    // raw mouse motion egui did not want, see take_mouse_motion
+    mouse_motion: egui::Vec2,
+    occluded: bool,
+    focused: bool,
+    viewport_info: Option<ViewportInfo>,
+    touch: TouchEmulation,
+    long_press_duration: Option<Duration>,
+    max_animation_rate: Option<f32>,
+    frame_start: Option<Instant>,
+    // the frame begun last had input events, so its follow-up repaint is not an animation
+    frame_had_input: bool,
+    window_events: u64,
+    frame_input_events: usize,
+    tracked_layers: Vec<egui::LayerId>,
+    scroll_momentum: ScrollMomentum,
+    font_atlas_fit: FontAtlasFit,
+    error_hook: Option<Box<ErrorHook>>,
+}
+
+// Momentum scrolling: macOS keeps sending scroll events after the fingers leave the trackpad.
+#[derive(Default)]
+struct ScrollMomentum {
+    // the fingers left the trackpad, later events are momentum
+    lifted: bool,
+    // the momentum was stopped by a click or a key press
+    stopped: bool,
+}
+
+impl ScrollMomentum {
+    // Whether a pixel scroll event in `phase` is passed on to egui.
+    fn accept(&mut self, phase: TouchPhase) -> bool {
+        match phase {
+            TouchPhase::Started => {
+                *self = Self::default();
+                true
+            }
+            TouchPhase::Moved => !self.stopped,
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.lifted = true;
+                true
+            }
+        }
+    }
+
+    // Stop the momentum like native scroll views do.
+    fn stop(&mut self) {
+        if self.lifted {
+            self.stopped = true;
+        }
+    }
+}
+
+// Text is scaled down by this factor each time the font atlas overflows, down to
+// MIN_FONT_SCALE.
+const FONT_SCALE_STEP: f32 = 0.8;
+const MIN_FONT_SCALE: f32 = 0.5;
+
+// Negotiates the font atlas size: scales the text styles down while the glyphs of a frame
+// do not fit into an atlas of the max texture side.
+struct FontAtlasFit {
+    // consecutive frames ending with an overflowed atlas
+    overflows: u32,
+    font_scale: f32,
+    reported: bool,
+}
+
+impl Default for FontAtlasFit {
+    fn default() -> Self {
+        Self {
+            overflows: 0,
+            font_scale: 1.0,
+            reported: false,
+        }
+    }
+}
+
+impl FontAtlasFit {
+    // Check the atlas at the end of a frame, returning the error to report when the text
+    // cannot be scaled down any further.
+    fn end_frame(&mut self, context: &Context) -> Option<RuntimeError> {
+        let (fill_ratio, max_texture_side) = {
+            let fonts = context.fonts();
+            (fonts.font_atlas_fill_ratio(), fonts.max_texture_side())
+        };
+        if fill_ratio < 1.0 {
+            self.overflows = 0;
+            return None;
+        }
+        // egui recreates an overflowed atlas at the next frame, so only an overflow of the
+        // fresh atlas means the glyphs of a frame do not fit
+        self.overflows += 1;
+        if self.overflows < 2 {
+            return None;
+        }
+        self.overflows = 0;
+        if self.font_scale * FONT_SCALE_STEP < MIN_FONT_SCALE {
+            if self.reported {
+                return None;
+            }
+            self.reported = true;
+            return Some(RuntimeError::FontAtlasOverflow { max_texture_side });
+        }
+        self.font_scale *= FONT_SCALE_STEP;
+        log::warn!(
+            "The font atlas overflowed at {0}x{0} texels, scaling text down to {1:.0}%",
+            max_texture_side,
+            self.font_scale * 100.0
+        );
+        let mut style = (*context.style()).clone();
+        scale_text_styles(&mut style, FONT_SCALE_STEP);
+        context.set_style(style);
+        None
+    }
+}
+
+fn scale_text_styles(style: &mut egui::Style, factor: f32) {
+    for font_id in style.text_styles.values_mut() {
+        font_id.size *= factor;
+    }
+}
+
+// Whether `modifiers` hold the command key of the platform: Cmd on macOS, Ctrl elsewhere.
+fn is_command(modifiers: ModifiersState) -> bool {
+    if cfg!(target_os = "macos") {
+        modifiers.logo()
+    } else {
+        modifiers.ctrl()
+    }
+}
+
+// macOS moves to the start and end of a line with Cmd+Left and Cmd+Right, so these are also
+// reported as Home and End for widgets that only know those.
+fn emulate_home_end(key: egui::Key, modifiers: egui::Modifiers) -> (egui::Key, egui::Modifiers) {
+    let key = match key {
+        egui::Key::ArrowLeft if modifiers.mac_cmd => egui::Key::Home,
+        egui::Key::ArrowRight if modifiers.mac_cmd => egui::Key::End,
+        _ => return (key, modifiers),
+    };
+    let modifiers = egui::Modifiers {
+        mac_cmd: false,
+        command: false,
+        ..modifiers
+    };
+    (key, modifiers)
+}
+
+// Files dragged over the window. winit reports every file of a drag separately.
+#[derive(Default)]
+struct FileDrag {
+    hovered_files: Vec<egui::HoveredFile>,
+}
+
+impl FileDrag {
+    fn hover(&mut self, path: &Path) {
+        self.hovered_files.push(egui::HoveredFile {
+            path: Some(path.to_owned()),
+            ..Default::default()
+        });
+    }
+
+    fn cancel(&mut self) {
+        self.hovered_files.clear();
+    }
+
+    fn drop_file(&mut self, path: &Path) -> egui::DroppedFile {
+        // the drag is over once the first file is dropped
+        self.hovered_files.clear();
+        egui::DroppedFile {
+            path: Some(path.to_owned()),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            last_modified: std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok(),
+            bytes: None,
+        }
+    }
+}
+
+// Events moving a synthetic pointer to `pos` with `buttons` held, given the buttons held before.
+pub(crate) fn pointer_events(
+    pos: egui::Pos2,
+    held: &[egui::PointerButton],
+    buttons: &[egui::PointerButton],
+    modifiers: egui::Modifiers,
+) -> Vec<egui::Event> {
+    let mut events = vec![egui::Event::PointerMoved(pos)];
+    for &button in held.iter().filter(|button| !buttons.contains(button)) {
+        events.push(egui::Event::PointerButton {
+            pos,
+            button,
+            pressed: false,
+            modifiers,
+        });
+    }
+    for &button in buttons.iter().filter(|button| !held.contains(button)) {
+        events.push(egui::Event::PointerButton {
+            pos,
+            button,
+            pressed: true,
+            modifiers,
+        });
+    }
+    events
+}
+
+// Whether characters typed with `modifiers` held are text, rather than part of a shortcut
+// like Ctrl+A. AltGr is reported as Ctrl+Alt on Windows, and types text.
+fn is_text_input(modifiers: ModifiersState) -> bool {
+    let alt_gr = cfg!(windows) && modifiers.ctrl() && modifiers.alt();
+    alt_gr || !(modifiers.ctrl() || modifiers.logo())
+}
+
+impl Platform {
+    /// Create the platform for a window of `physical_width` x `physical_height` pixels.
+    pub fn new(
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: f64,
+        font_definitions: egui::FontDefinitions,
+        style: egui::Style,
+    ) -> Self {
+        Self::with_context_init(physical_width, physical_height, scale_factor, font_definitions, style, |_| {})
+    }
+
+    /// Like [`new`](Self::new), calling `init` with the [`Context`] right after it is created,
+    /// before the first frame.
+    ///
+    /// Install plugins there, e.g. image loaders, extra fonts or memory restored from a
+    /// previous session. The fonts and style passed here are already set.
+    pub fn with_context_init(
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: f64,
+        font_definitions: egui::FontDefinitions,
+        style: egui::Style,
+        init: impl FnOnce(&Context),
+    ) -> Self {
+        // Start time is initialized when first time call render_time
+        let start_time = None;
+
+        // Create context
+        let context = Context::default();
+        context.set_fonts(font_definitions);
+        context.set_style(style);
+        init(&context);
+
+        // Create raw_input
+        let raw_input = egui::RawInput {
+            pixels_per_point: Some(scale_factor as f32),
+            screen_rect: Some(egui::Rect::from_min_size(
+                Default::default(),
+                vec2(physical_width as f32, physical_height as f32) / scale_factor as f32,
+            )),
+            time: Some(0.0),
+            ..Default::default()
+        };
+
+        // Create mouse pos and modifier state (These values are overwritten by handle events)
+        let mouse_pos = pos2(0.0, 0.0);
+        let modifiers_state = winit::event::ModifiersState::default();
+
+        // Create clipboard context, in memory without a window system, e.g. headless
+        let clipboard: Box<dyn Clipboard> = match SystemClipboard::new() {
+            Ok(clipboard) => Box::new(clipboard),
+            Err(err) => {
+                log::warn!("Failed to initialize ClipboardContext, using a clipboard in memory: {}", err);
+                Box::new(MemoryClipboard::default())
+            }
+        };
+
+        Self {
+            start_time,
+            time_source: None,
+            window_id: None,
+            in_frame: false,
+            recovered_textures_delta: Default::default(),
+
+            scale_factor,
+            context,
+            raw_input,
+            mouse_pos,
+            modifiers_state,
+            clipboard,
+            pasted_image: None,
+            file_drag: Default::default(),
+            current_cursor_icon: egui::CursorIcon::None,
+            ime_position: None,
+            pointer_buttons: vec![],
+            pointer_in_window: false,
+            wants_pointer_input: false,
+            wants_keyboard_input: false,
+            mouse_motion: egui::Vec2::ZERO,
+            occluded: false,
+            // winit 0.26 only reports focus changes
+            focused: true,
+            viewport_info: None,
+            touch: Default::default(),
+            // longer than egui's maximum click duration, so the primary release is not a click
+            long_press_duration: Some(Duration::from_millis(800)),
+            max_animation_rate: None,
+            frame_start: None,
+            frame_had_input: false,
+            window_events: 0,
+            frame_input_events: 0,
+            tracked_layers: default_tracked_layers(),
+            scroll_momentum: Default::default(),
+            font_atlas_fit: Default::default(),
+            error_hook: None,
+        }
+    }
+
+    /// Scale factor of the window, as last set by an event or [`Self::set_screen_size`].
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Update the screen rect after the render target was resized.
+    ///
+    /// `None` keeps the current scale factor.
+    pub fn set_screen_size(&mut self, physical_width: u32, physical_height: u32, scale_factor: Option<f64>) {
+        if let Some(scale_factor) = scale_factor {
+            self.scale_factor = scale_factor;
+            self.raw_input.pixels_per_point = Some(scale_factor as f32);
+        }
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            Default::default(),
+            vec2(physical_width as f32, physical_height as f32) / self.scale_factor as f32,
+        ));
+    }
+
+    /// handling winit event.
+    pub fn handle_event<T>(&mut self, winit_event: &Event<T>) {
+        if let Event::WindowEvent { window_id, event } = winit_event {
+            if !self.handles_window(*window_id) {
+                return;
+            }
+            self.window_events += 1;
+            match event {
+                // window size changed
+                WindowEvent::Resized(physical_size) => {
+                    let pixels_per_point = self
+                        .raw_input
+                        .pixels_per_point
+                        .unwrap_or_else(|| self.context.pixels_per_point());
+                    self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+                        Default::default(),
+                        vec2(physical_size.width as f32, physical_size.height as f32)
+                            / pixels_per_point,
+                    ));
+                    // minimized windows are resized to zero on some platforms
+                    self.occluded = physical_size.width == 0 || physical_size.height == 0;
+                }
+                // dpi changed
+                WindowEvent::ScaleFactorChanged {
+                    scale_factor,
+                    new_inner_size,
+                } => {
+                    self.scale_factor = *scale_factor;
+                    self.raw_input.pixels_per_point = Some(*scale_factor as f32);
+                    let pixels_per_point = self
+                        .raw_input
+                        .pixels_per_point
+                        .unwrap_or_else(|| self.context.pixels_per_point());
+                    self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+                        Default::default(),
+                        vec2(new_inner_size.width as f32, new_inner_size.height as f32)
+                            / pixels_per_point,
+                    ));
+                }
+                // mouse click
+                WindowEvent::MouseInput { state, button, .. } => {
+                    if let Some(button) = Self::winit_to_egui_mouse_button(*button) {
+                        let pressed = *state == winit::event::ElementState::Pressed;
+                        if pressed {
+                            self.scroll_momentum.stop();
+                        }
+                        self.raw_input.events.push(egui::Event::PointerButton {
+                            pos: self.mouse_pos,
+                            button,
+                            pressed,
+                            modifiers: Self::winit_to_egui_modifiers(self.modifiers_state),
+                        });
+                        // middle click pastes the primary selection
+                        if pressed && button == egui::PointerButton::Middle {
+                            match self.clipboard.get_primary() {
+                                Ok(Some(contents)) if !contents.is_empty() => {
+                                    self.raw_input.events.push(egui::Event::Text(contents));
+                                }
+                                Ok(_) => {}
+                                Err(err) => {
+                                    log::error!("Paste error: {}", err);
+                                    self.report_error(RuntimeError::Clipboard(err.to_string()));
+                                }
+                            }
+                        }
+                    }
+                }
+                // mouse wheel
+                // the sign is passed on as is, the OS has already applied natural scrolling
+                WindowEvent::MouseWheel { delta, phase, .. } => match delta {
+                    winit::event::MouseScrollDelta::LineDelta(x, y) => {
+                        let line_height = 24.0;
+                        self.raw_input.events.push(egui::Event::Scroll(vec2(*x, *y) * line_height));
+                    }
+                    winit::event::MouseScrollDelta::PixelDelta(delta) => {
+                        if self.scroll_momentum.accept(*phase) {
+                            // physical pixels, e.g. twice the points on Retina displays
+                            let pixels_per_point = self.scale_factor as f32;
+                            self.raw_input.events.push(egui::Event::Scroll(
+                                vec2(delta.x as f32, delta.y as f32) / pixels_per_point,
+                            ));
+                        }
+                    }
+                },
+                // mouse move
+                WindowEvent::CursorMoved { position, .. } => {
+                    let pixels_per_point = self
+                        .raw_input
+                        .pixels_per_point
+                        .unwrap_or_else(|| self.context.pixels_per_point());
+                    let pos = pos2(
+                        position.x as f32 / pixels_per_point,
+                        position.y as f32 / pixels_per_point,
+                    );
+                    self.raw_input.events.push(egui::Event::PointerMoved(pos));
+                    self.mouse_pos = pos;
+                    self.pointer_in_window = true;
+                }
+                // mouse out
+                WindowEvent::CursorLeft { .. } => {
+                    self.raw_input.events.push(egui::Event::PointerGone);
+                    self.pointer_in_window = false;
+                }
+                // touch, also emulating the pointer with the first finger
+                WindowEvent::Touch(touch) => {
+                    let pixels_per_point = self
+                        .raw_input
+                        .pixels_per_point
+                        .unwrap_or_else(|| self.context.pixels_per_point());
+                    let pos = pos2(
+                        touch.location.x as f32 / pixels_per_point,
+                        touch.location.y as f32 / pixels_per_point,
+                    );
+                    let mut hasher = DefaultHasher::new();
+                    touch.device_id.hash(&mut hasher);
+                    let phase = match touch.phase {
+                        TouchPhase::Started => egui::TouchPhase::Start,
+                        TouchPhase::Moved => egui::TouchPhase::Move,
+                        TouchPhase::Ended => egui::TouchPhase::End,
+                        TouchPhase::Cancelled => egui::TouchPhase::Cancel,
+                    };
+                    self.raw_input.events.push(egui::Event::Touch {
+                        device_id: egui::TouchDeviceId(hasher.finish()),
+                        id: egui::TouchId::from(touch.id),
+                        phase,
+                        pos,
+                        force: touch.force.map_or(0.0, |force| force.normalized() as f32),
+                    });
+
+                    let modifiers = Self::winit_to_egui_modifiers(self.modifiers_state);
+                    let events = match touch.phase {
+                        TouchPhase::Started => self.touch.start(touch.id, pos, Instant::now(), modifiers),
+                        TouchPhase::Moved => self.touch.moved(touch.id, pos),
+                        TouchPhase::Ended => self.touch.end(touch.id, pos, false, modifiers),
+                        TouchPhase::Cancelled => self.touch.end(touch.id, pos, true, modifiers),
+                    };
+                    for event in &events {
+                        match event {
+                            egui::Event::PointerMoved(pos) => {
+                                self.mouse_pos = *pos;
+                                self.pointer_in_window = true;
+                            }
+                            egui::Event::PointerGone => self.pointer_in_window = false,
+                            _ => {}
+                        }
+                    }
+                    self.raw_input.events.extend(events);
+                }
+                // file drag and drop
+                WindowEvent::HoveredFile(path) => self.file_drag.hover(path),
+                WindowEvent::HoveredFileCancelled => self.file_drag.cancel(),
+                WindowEvent::DroppedFile(path) => {
+                    let dropped_file = self.file_drag.drop_file(path);
+                    self.raw_input.dropped_files.push(dropped_file);
+                }
+                // modifier keys
+                WindowEvent::ModifiersChanged(input) => self.modifiers_state = *input,
+                WindowEvent::Focused(focused) => {
+                    self.focused = *focused;
+                    if let Some(viewport_info) = self.viewport_info.as_mut() {
+                        viewport_info.focused = *focused;
+                    }
+                }
+                // keyboard inputs
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let Some(virtual_keycode) = input.virtual_keycode {
+                        let pressed = input.state == winit::event::ElementState::Pressed;
+                        if pressed {
+                            self.scroll_momentum.stop();
+                            let is_command = is_command(self.modifiers_state);
+                            if is_command && virtual_keycode == VirtualKeyCode::C {
+                                self.raw_input.events.push(egui::Event::Copy);
+                            } else if is_command && virtual_keycode == VirtualKeyCode::X {
+                                self.raw_input.events.push(egui::Event::Cut);
+                            } else if is_command && virtual_keycode == VirtualKeyCode::V {
+                                if let Ok(contents) = self.clipboard.get_text() {
+                                    if !contents.is_empty() {
+                                        self.raw_input.events.push(egui::Event::Text(contents));
+                                    }
+                                }
+                                match self.clipboard.get_image() {
+                                    Ok(Some(image)) => self.pasted_image = Some(image),
+                                    Ok(None) => {}
+                                    Err(err) => {
+                                        log::error!("Paste error: {}", err);
+                                        self.report_error(RuntimeError::Clipboard(err.to_string()));
+                                    }
+                                }
+                            } else if let Some(key) = Self::winit_to_egui_key_code(virtual_keycode)
+                            {
+                                let (key, modifiers) =
+                                    emulate_home_end(key, Self::winit_to_egui_modifiers(self.modifiers_state));
+                                self.raw_input.events.push(egui::Event::Key {
+                                    key,
+                                    pressed: input.state == winit::event::ElementState::Pressed,
+                                    modifiers,
+                                })
+                            }
+                        }
+                    }
+                }
+                // receive character
+                WindowEvent::ReceivedCharacter(ch) => {
+                    // remove control character and shortcuts
+                    if ch.is_ascii_control() || !is_text_input(self.modifiers_state) {
+                        return;
+                    }
+                    self.raw_input
+                        .events
+                        .push(egui::Event::Text(ch.to_string()));
+                }
+                _ => (),
+            }
+        } else if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } = winit_event
+        {
+            // device events arrive for every window and also when none is focused
+            if self.focused && !self.wants_pointer_input {
+                self.mouse_motion += vec2(delta.0 as f32, delta.1 as f32);
+            }
+        }
+    }
+
+    fn winit_to_egui_key_code(key: VirtualKeyCode) -> Option<egui::Key> {
+        Some(match key {
+            VirtualKeyCode::Down => Key::ArrowDown,
+            VirtualKeyCode::Left => Key::ArrowLeft,
+            VirtualKeyCode::Right => Key::ArrowRight,
+            VirtualKeyCode::Up => Key::ArrowUp,
+            VirtualKeyCode::Escape => Key::Escape,
+            VirtualKeyCode::Tab => Key::Tab,
+            VirtualKeyCode::Back => Key::Backspace,
+            VirtualKeyCode::Return => Key::Enter,
+            VirtualKeyCode::Space => Key::Space,
+            VirtualKeyCode::Insert => Key::Insert,
+            VirtualKeyCode::Delete => Key::Delete,
+            VirtualKeyCode::Home => Key::Home,
+            VirtualKeyCode::End => Key::End,
+            VirtualKeyCode::PageUp => Key::PageUp,
+            VirtualKeyCode::PageDown => Key::PageDown,
+            VirtualKeyCode::Key0 => Key::Num0,
+            VirtualKeyCode::Key1 => Key::Num1,
+            VirtualKeyCode::Key2 => Key::Num2,
+            VirtualKeyCode::Key3 => Key::Num3,
+            VirtualKeyCode::Key4 => Key::Num4,
+            VirtualKeyCode::Key5 => Key::Num5,
+            VirtualKeyCode::Key6 => Key::Num6,
+            VirtualKeyCode::Key7 => Key::Num7,
+            VirtualKeyCode::Key8 => Key::Num8,
+            VirtualKeyCode::Key9 => Key::Num9,
+            VirtualKeyCode::A => Key::A,
+            VirtualKeyCode::B => Key::B,
+            VirtualKeyCode::C => Key::C,
+            VirtualKeyCode::D => Key::D,
+            VirtualKeyCode::E => Key::E,
+            VirtualKeyCode::F => Key::F,
+            VirtualKeyCode::G => Key::G,
+            VirtualKeyCode::H => Key::H,
+            VirtualKeyCode::I => Key::I,
+            VirtualKeyCode::J => Key::J,
+            VirtualKeyCode::K => Key::K,
+            VirtualKeyCode::L => Key::L,
+            VirtualKeyCode::M => Key::M,
+            VirtualKeyCode::N => Key::N,
+            VirtualKeyCode::O => Key::O,
+            VirtualKeyCode::P => Key::P,
+            VirtualKeyCode::Q => Key::Q,
+            VirtualKeyCode::R => Key::R,
+            VirtualKeyCode::S => Key::S,
+            VirtualKeyCode::T => Key::T,
+            VirtualKeyCode::U => Key::U,
+            VirtualKeyCode::V => Key::V,
+            VirtualKeyCode::W => Key::W,
+            VirtualKeyCode::X => Key::X,
+            VirtualKeyCode::Y => Key::Y,
+            VirtualKeyCode::Z => Key::Z,
+            _ => return None,
+        })
+    }
+
+    fn winit_to_egui_modifiers(modifiers: ModifiersState) -> egui::Modifiers {
+        egui::Modifiers {
+            alt: modifiers.alt(),
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            #[cfg(not(target_os = "macos"))]
+            mac_cmd: false,
+            #[cfg(not(target_os = "macos"))]
+            command: modifiers.ctrl(),
+            #[cfg(target_os = "macos")]
+            mac_cmd: modifiers.logo(),
+            #[cfg(target_os = "macos")]
+            command: modifiers.logo(),
+        }
+    }
+
+    fn winit_to_egui_mouse_button(
+        button: winit::event::MouseButton,
+    ) -> Option<egui::PointerButton> {
+        Some(match button {
+            winit::event::MouseButton::Left => egui::PointerButton::Primary,
+            winit::event::MouseButton::Right => egui::PointerButton::Secondary,
+            winit::event::MouseButton::Middle => egui::PointerButton::Middle,
+            _ => return None,
+        })
+    }
+
+    /// Convert from [`egui::CursorIcon`] to [`winit::window::CursorIcon`].
+    fn egui_to_winit_cursor_icon(
+        cursor_icon: egui::CursorIcon,
+    ) -> Option<winit::window::CursorIcon> {
+        Some(match cursor_icon {
+            egui::CursorIcon::Default => winit::window::CursorIcon::Default,
+            egui::CursorIcon::PointingHand => winit::window::CursorIcon::Hand,
+            egui::CursorIcon::ResizeHorizontal => winit::window::CursorIcon::ColResize,
+            egui::CursorIcon::ResizeNeSw => winit::window::CursorIcon::NeResize,
+            egui::CursorIcon::ResizeNwSe => winit::window::CursorIcon::NwResize,
+            egui::CursorIcon::ResizeVertical => winit::window::CursorIcon::RowResize,
+            egui::CursorIcon::Text => winit::window::CursorIcon::Text,
+            egui::CursorIcon::Grab => winit::window::CursorIcon::Grab,
+            egui::CursorIcon::Grabbing => winit::window::CursorIcon::Grabbing,
+            egui::CursorIcon::None => return None,
+            egui::CursorIcon::ContextMenu => winit::window::CursorIcon::ContextMenu,
+            egui::CursorIcon::Help => winit::window::CursorIcon::Help,
+            egui::CursorIcon::Progress => winit::window::CursorIcon::Progress,
+            egui::CursorIcon::Wait => winit::window::CursorIcon::Wait,
+            egui::CursorIcon::Cell => winit::window::CursorIcon::Cell,
+            egui::CursorIcon::Crosshair => winit::window::CursorIcon::Crosshair,
+            egui::CursorIcon::VerticalText => winit::window::CursorIcon::VerticalText,
+            egui::CursorIcon::Alias => winit::window::CursorIcon::Alias,
+            egui::CursorIcon::Copy => winit::window::CursorIcon::Copy,
+            egui::CursorIcon::Move => winit::window::CursorIcon::Move,
+            egui::CursorIcon::NoDrop => winit::window::CursorIcon::NoDrop,
+            egui::CursorIcon::NotAllowed => winit::window::CursorIcon::NotAllowed,
+            egui::CursorIcon::AllScroll => winit::window::CursorIcon::AllScroll,
+            egui::CursorIcon::ZoomIn => winit::window::CursorIcon::ZoomIn,
+            egui::CursorIcon::ZoomOut => winit::window::CursorIcon::ZoomOut,
+            egui::CursorIcon::ResizeEast => winit::window::CursorIcon::EResize,
+            egui::CursorIcon::ResizeSouthEast => winit::window::CursorIcon::SeResize,
+            egui::CursorIcon::ResizeSouth => winit::window::CursorIcon::SResize,
+            egui::CursorIcon::ResizeSouthWest => winit::window::CursorIcon::SwResize,
+            egui::CursorIcon::ResizeWest => winit::window::CursorIcon::WResize,
+            egui::CursorIcon::ResizeNorthWest => winit::window::CursorIcon::NwResize,
+            egui::CursorIcon::ResizeNorth => winit::window::CursorIcon::NResize,
+            egui::CursorIcon::ResizeNorthEast => winit::window::CursorIcon::NeResize,
+            egui::CursorIcon::ResizeColumn => winit::window::CursorIcon::ColResize,
+            egui::CursorIcon::ResizeRow => winit::window::CursorIcon::RowResize,
+        })
+    }
+    
+    /// Move a pointer that winit does not see, e.g. a VR controller or an eye tracker.
+    ///
+    /// `buttons` are the buttons held now; press and release events are generated for the
+    /// changes since the previous call.
+    pub fn push_pointer(&mut self, pos: egui::Pos2, buttons: &[egui::PointerButton], modifiers: egui::Modifiers) {
+        self.raw_input
+            .events
+            .extend(pointer_events(pos, &self.pointer_buttons, buttons, modifiers));
+        self.raw_input.modifiers = modifiers;
+        self.pointer_buttons = buttons.to_vec();
+        self.mouse_pos = pos;
+        self.pointer_in_window = true;
+    }
+
+    /// Enter text that winit does not see, e.g. from a virtual keyboard or a remote control.
+    pub fn push_text(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.raw_input.events.push(egui::Event::Text(text.to_owned()));
+        }
+    }
+
+    /// Number of window events handled so far.
+    pub fn window_event_count(&self) -> u64 {
+        self.window_events
+    }
+
+    /// Number of egui input events of the frame begun last.
+    pub fn frame_input_event_count(&self) -> usize {
+        self.frame_input_events
+    }
+
+    /// The [`ViewportInfo`] last read from the window, stored in the context at every
+    /// [`begin_frame`](Self::begin_frame).
+    pub fn viewport_info(&self) -> Option<&ViewportInfo> {
+        self.viewport_info.as_ref()
+    }
+
+    /// Whether the window is known to be invisible, e.g. minimized.
+    ///
+    /// Skip rendering while this is true; there is nothing to present to and a zero sized
+    /// swapchain cannot be created. winit 0.26 has no occlusion event, so this is derived from
+    /// zero sized resizes; use [`Self::set_occluded`] to report occlusion detected otherwise.
+    pub fn is_occluded(&self) -> bool {
+        self.occluded
+    }
+
+    /// Report that the window became occluded or visible again.
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+    }
+
+    /// Hold time after which a still touch becomes a secondary click, e.g. to open context
+    /// menus without a mouse. `None` disables the emulation.
+    ///
+    /// Defaults to 800 ms. Durations shorter than egui's maximum click duration make the
+    /// long press a primary click too.
+    pub fn set_long_press_duration(&mut self, duration: Option<Duration>) {
+        self.long_press_duration = duration;
+    }
+
+    /// Battery saver: repaint at most `max_rate` times a second while only animations, e.g.
+    /// the cursor blink or spinners, request repaints. `None` repaints them at full rate.
+    ///
+    /// Frames with input are never delayed. Applies to [`next_repaint`](Self::next_repaint).
+    pub fn set_max_animation_rate(&mut self, max_rate: Option<f32>) {
+        self.max_animation_rate = max_rate;
+    }
+
+    /// When to paint the next frame, given [`egui::FullOutput::needs_repaint`] of the last
+    /// one; `None` to wait for the next event.
+    ///
+    /// Pass it to `ControlFlow::WaitUntil` instead of repainting continuously. Input
+    /// received since the last [`begin_frame`](Self::begin_frame) is painted right away;
+    /// repaints of animations are limited by
+    /// [`set_max_animation_rate`](Self::set_max_animation_rate).
+    pub fn next_repaint(&self, needs_repaint: bool) -> Option<Instant> {
+        let now = Instant::now();
+        if !self.raw_input.events.is_empty() || !self.raw_input.dropped_files.is_empty() {
+            return Some(now);
+        }
+        if !needs_repaint {
+            return None;
+        }
+        match self.frame_start {
+            Some(frame_start) if !self.frame_had_input => {
+                Some(animation_repaint_time(frame_start, self.max_animation_rate, now))
+            }
+            _ => Some(now),
+        }
+    }
+
+    /// Set a hook called with clipboard and link failures, see [`RuntimeError`].
+    ///
+    /// They are logged too.
+    pub fn set_error_hook(&mut self, hook: impl FnMut(&RuntimeError) + Send + 'static) {
+        self.error_hook = Some(Box::new(hook));
+    }
+
+    /// Remove the hook set with [`set_error_hook`](Self::set_error_hook).
+    pub fn clear_error_hook(&mut self) {
+        self.error_hook = None;
+    }
+
+    fn report_error(&mut self, error: RuntimeError) {
+        if let Some(hook) = self.error_hook.as_mut() {
+            hook(&error);
+        }
+    }
+
+    /// Only handle the window events of `window_id` in [`Self::handle_event`]; `None` handles
+    /// those of every window.
+    ///
+    /// egui 0.18 has no viewports, so an application with several windows creates an
+    /// [`Integration`](crate::Integration) with its own swapchain per window, all on the same
+    /// device, and passes every event to each of them.
+    pub fn set_window_id(&mut self, window_id: Option<WindowId>) {
+        self.window_id = window_id;
+    }
+
+    /// Whether [`Self::handle_event`] handles the events of `window_id`, see
+    /// [`set_window_id`](Self::set_window_id).
+    pub fn handles_window(&self, window_id: WindowId) -> bool {
+        self.window_id.is_none_or(|own_window_id| own_window_id == window_id)
+    }
+
+    /// Take the time of the egui frames from `time_source` instead of the system clock, e.g.
+    /// a fixed step per frame so golden image tests and input replays animate identically.
+    pub fn set_time_source(&mut self, time_source: impl FnMut() -> f64 + Send + 'static) {
+        self.time_source = Some(Box::new(time_source));
+    }
+
+    /// Go back to the system clock after [`set_time_source`](Self::set_time_source).
+    pub fn clear_time_source(&mut self) {
+        self.time_source = None;
+        self.start_time = None;
+    }
+
+    /// Replace the clipboard, e.g. with one that supports images.
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = clipboard;
+    }
+
+    /// Take the image pasted with Ctrl+V since the last call, if the clipboard had one.
+    ///
+    /// egui has no image paste event, so load it as a texture with
+    /// [`egui::Context::load_texture`] and show it where it belongs.
+    pub fn take_pasted_image(&mut self) -> Option<egui::ColorImage> {
+        self.pasted_image.take()
+    }
+
+    /// Report selected text, which is pasted with the middle mouse button on X11.
+    ///
+    /// egui does not report text selections, so only copied text becomes the primary
+    /// selection automatically. Call this with the selection of e.g. a
+    /// [`egui::widgets::text_edit::TextEditState`] to follow the platform convention closely.
+    pub fn set_primary_selection(&mut self, text: &str) {
+        if let Err(err) = self.clipboard.set_primary(text.to_owned()) {
+            log::error!("Copy error: {}", err);
+            self.report_error(RuntimeError::Clipboard(err.to_string()));
+        }
+    }
+
+    /// Put an image on the clipboard, e.g. an exported plot.
+    pub fn copy_image(&mut self, image: &egui::ColorImage) {
+        if let Err(err) = self.clipboard.set_image(image) {
+            log::error!("Copy error: {}", err);
+            self.report_error(RuntimeError::Clipboard(err.to_string()));
+        }
+    }
+
+    /// begin frame.
+    ///
+    /// A frame left unfinished by a panic in UI code is ended first, see
+    /// [`recover`](Self::recover).
+    pub fn begin_frame(&mut self) {
+        if self.recover() {
+            log::warn!("The previous frame was not ended, e.g. because UI code panicked; it is dropped.");
+        }
+        self.in_frame = true;
+        // update time
+        if let Some(time_source) = self.time_source.as_mut() {
+            self.raw_input.time = Some(time_source());
+        } else if let Some(time) = self.start_time {
+            self.raw_input.time = Some(time.elapsed().as_secs_f64());
+        } else {
+            self.start_time = Some(Instant::now());
+        }
+        self.frame_start = Some(Instant::now());
+        self.frame_had_input = !self.raw_input.events.is_empty() || !self.raw_input.dropped_files.is_empty();
+        self.frame_input_events = self.raw_input.events.len();
+        if let Some(duration) = self.long_press_duration {
+            let modifiers = Self::winit_to_egui_modifiers(self.modifiers_state);
+            let events = self.touch.poll(Instant::now(), duration, modifiers);
+            self.raw_input.events.extend(events);
+        }
+        // `take` clears the hovered files, but they stay hovered until dropped or cancelled
+        self.raw_input.hovered_files = self.file_drag.hovered_files.clone();
+        if let Some(viewport_info) = &self.viewport_info {
+            viewport_info.store(&self.context);
+        }
+        self.context.begin_frame(self.raw_input.take());
+        if self.long_press_duration.is_some() && self.touch.long_press_pending() {
+            self.context.request_repaint();
+        }
+    }
+
+    /// end frame.
+    ///
+    /// Platform side effects are not applied here; pass `platform_output` of the
+    /// returned [`egui::FullOutput`] to [`Self::handle_platform_output`].
+    pub fn end_frame(&mut self) -> egui::FullOutput {
+        let mut output = self.context.end_frame();
+        self.in_frame = false;
+        if !self.recovered_textures_delta.is_empty() {
+            let mut textures_delta = std::mem::take(&mut self.recovered_textures_delta);
+            textures_delta.append(output.textures_delta);
+            output.textures_delta = textures_delta;
+        }
+        if let Some(error) = self.font_atlas_fit.end_frame(&self.context) {
+            log::error!("{}", error);
+            self.report_error(error);
+        }
+        self.wants_pointer_input = self.context.wants_pointer_input();
+        self.wants_keyboard_input = self.context.wants_keyboard_input();
+        output
+    }
+
+    /// Whether a frame has begun and not ended yet, e.g. because UI code panicked.
+    pub fn is_in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    /// End a frame left unfinished by a panic in UI code caught by the application, so the
+    /// next frame starts from a consistent state. Returns whether there was such a frame.
+    ///
+    /// Its shapes and platform output are dropped, and the input of the frame is lost. Its
+    /// texture updates, e.g. glyphs added to the font atlas, are returned by the next
+    /// [`end_frame`](Self::end_frame). Long-running editors can show an error dialog in the
+    /// next frame and carry on. [`begin_frame`](Self::begin_frame) recovers by itself.
+    pub fn recover(&mut self) -> bool {
+        if !self.in_frame {
+            return false;
+        }
+        let output = self.context.end_frame();
+        self.recovered_textures_delta.append(output.textures_delta);
+        self.in_frame = false;
+        true
+    }
+
+    /// Run a whole frame: [`begin_frame`](Self::begin_frame), `run_ui` and
+    /// [`end_frame`](Self::end_frame).
+    ///
+    /// A panic in `run_ui` is caught and the frame [`recover`](Self::recover)ed; the panic
+    /// payload is returned so the application can report it. The panic hook still prints the
+    /// panic as usual.
+    pub fn run_frame(&mut self, run_ui: impl FnOnce(&Context)) -> std::thread::Result<egui::FullOutput> {
+        self.begin_frame();
+        let context = self.context.clone();
+        match panic::catch_unwind(AssertUnwindSafe(|| run_ui(&context))) {
+            Ok(()) => Ok(self.end_frame()),
+            Err(payload) => {
+                self.recover();
+                Err(payload)
+            }
+        }
+    }
+
+    /// Like [`Self::end_frame`], but with the shapes grouped by layer in [`LayeredShapes`]
+    /// instead of in the returned [`egui::FullOutput`], to paint layers in separate passes.
+    pub fn end_frame_layered(&mut self) -> (egui::FullOutput, LayeredShapes) {
+        let mut layer_ids = self.context.memory().layer_ids().collect::<Vec<_>>();
+        for layer_id in &self.tracked_layers {
+            if !layer_ids.contains(layer_id) {
+                layer_ids.push(*layer_id);
+            }
+        }
+        mark_layers(&self.context, &layer_ids);
+        let mut output = self.end_frame();
+        let shapes = std::mem::take(&mut output.shapes);
+        (output, LayeredShapes::new(&layer_ids, shapes))
+    }
+
+    /// Tell [`Self::end_frame_layered`] about a layer that is painted with
+    /// [`egui::Context::layer_painter`] but is not an area.
+    pub fn track_layer(&mut self, layer_id: egui::LayerId) {
+        if !self.tracked_layers.contains(&layer_id) {
+            self.tracked_layers.push(layer_id);
+        }
+    }
+
+    /// Latest pointer position in points, or `None` when the pointer is outside the window.
+    pub fn pointer_pos(&self) -> Option<egui::Pos2> {
+        self.pointer_in_window.then_some(self.mouse_pos)
+    }
+
+    /// Keyboard modifiers currently held, as reported by winit.
+    pub fn modifiers(&self) -> egui::Modifiers {
+        Self::winit_to_egui_modifiers(self.modifiers_state)
+    }
+
+    /// Whether egui wanted pointer input in the last frame, e.g. the pointer was over a window.
+    ///
+    /// Cached by [`Self::end_frame`], so game code can check it without locking the context.
+    pub fn wants_pointer_input(&self) -> bool {
+        self.wants_pointer_input
+    }
+
+    /// Raw mouse motion since the last call, in device units, e.g. to turn a camera.
+    ///
+    /// Sums the `DeviceEvent::MouseMotion` deltas passed to [`Self::handle_event`] while the
+    /// window was focused and egui did not want pointer input, so the application need not
+    /// handle device events itself and motion over egui windows does not also turn the
+    /// camera. Unlike `CursorMoved`, the deltas keep coming while the cursor is grabbed.
+    pub fn take_mouse_motion(&mut self) -> egui::Vec2 {
+        std::mem::take(&mut self.mouse_motion)
+    }
+
+    /// Whether egui wanted keyboard input in the last frame, e.g. a text field had focus.
+    ///
+    /// Cached by [`Self::end_frame`], so game code can check it without locking the context.
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.wants_keyboard_input
+    }
+
+    /// end frame, apply the platform output to `window` and tessellate the shapes.
+    ///
+    /// Shortcut for [`Self::end_frame`], [`Self::handle_platform_output`] and
+    /// [`egui::Context::tessellate`]. The shapes are tessellated with the same
+    /// pixels_per_point egui laid out the frame with, so text stays sharp.
+    pub fn end_frame_and_tessellate(
+        &mut self,
+        window: &Window,
+    ) -> (TexturesDelta, Vec<egui::ClippedPrimitive>) {
+        let output = self.end_frame();
+        self.handle_platform_output(window, output.platform_output);
+        let clipped_primitives = self.context.tessellate(output.shapes);
+        (output.textures_delta, clipped_primitives)
+    }
+
+    /// Apply the platform side effects of a frame (open urls, clipboard, cursor icon, IME
+    /// position) to `window`, and read the [`ViewportInfo`] of the next frame from it.
+    pub fn handle_platform_output(&mut self, window: &Window, output: PlatformOutput) {
+        self.viewport_info = Some(ViewportInfo::from_window(window, self.focused));
+
+        // handle links
+        if let Some(egui::output::OpenUrl { url, .. }) = &output.open_url {
+            if let Err(err) = webbrowser::open(url) {
+                log::error!("Failed to open url: {}", err);
+                self.report_error(RuntimeError::OpenUrl(err.to_string()));
+            }
+        }
+
+        // handle clipboard
+        if !output.copied_text.is_empty() {
+            // copied text was selected, so it is the primary selection too
+            if let Err(err) = self.clipboard.set_primary(output.copied_text.clone()) {
+                log::error!("Copy/Cut error: {}", err);
+                self.report_error(RuntimeError::Clipboard(err.to_string()));
+            }
+            if let Err(err) = self.clipboard.set_text(output.copied_text) {
+                log::error!("Copy/Cut error: {}", err);
+                self.report_error(RuntimeError::Clipboard(err.to_string()));
+            }
+        }
+
+        // handle cursor icon
+        if self.current_cursor_icon != output.cursor_icon {
+            if let Some(cursor_icon) =
+                Self::egui_to_winit_cursor_icon(output.cursor_icon)
+            {
+                window.set_cursor_visible(true);
+                window.set_cursor_icon(cursor_icon);
+            } else {
+                window.set_cursor_visible(false);
+            }
+            self.current_cursor_icon = output.cursor_icon;
+        }
+
+        // move the IME candidate window next to the text cursor
+        if let Some(pos) = output.text_cursor_pos {
+            if self.ime_position != Some(pos) {
+                let pixels_per_point = self.context.pixels_per_point();
+                window.set_ime_position(winit::dpi::PhysicalPosition::new(
+                    pos.x * pixels_per_point,
+                    pos.y * pixels_per_point,
+                ));
+            }
+        }
+        self.ime_position = output.text_cursor_pos;
+    }
+
+    /// Get [`egui::Context`].
+    pub fn context(&self) -> Context {
+        self.context.clone()
+    }
+
+    /// Replace the font definitions.
+    ///
+    /// egui rebuilds the font atlas at the start of the next frame. The resulting
+    /// full texture delta is handled by `paint()`, which frees the old atlas texture.
+    pub fn set_fonts(&self, font_definitions: egui::FontDefinitions) {
+        self.context.set_fonts(font_definitions);
+    }
+
+    /// Size of the font atlas texture, [`FONT_TEXTURE_ID`](crate::FONT_TEXTURE_ID), in texels.
+    ///
+    /// # Panics
+    ///
+    /// Before the first [`begin_frame`](Self::begin_frame).
+    pub fn font_atlas_size(&self) -> [usize; 2] {
+        self.context.fonts().font_image_size()
+    }
+
+    /// Where the glyph of `c` lies in the font atlas, rasterizing it if it is not there yet.
+    ///
+    /// Newly rasterized glyphs are uploaded with the textures delta of the current frame, so
+    /// draw them once that frame has been painted.
+    ///
+    /// # Panics
+    ///
+    /// Before the first [`begin_frame`](Self::begin_frame).
+    pub fn glyph_uv(&self, font_id: &egui::FontId, c: char) -> Option<GlyphUv> {
+        glyph_uv(&self.context.fonts(), font_id, c)
+    }
+
+    /// Replace the style.
+    ///
+    /// Style changes that affect text (e.g. text styles) can also trigger a font atlas rebuild.
+    /// The text styles are scaled by [`font_scale`](Self::font_scale).
+    pub fn set_style(&self, mut style: egui::Style) {
+        if self.font_atlas_fit.font_scale < 1.0 {
+            scale_text_styles(&mut style, self.font_atlas_fit.font_scale);
+        }
+        self.context.set_style(style);
+    }
+
+    /// Largest width or height of the font atlas, e.g. `maxImageDimension2D` of the device
+    /// limits. egui defaults to 2048 and needs at least 1024, which every Vulkan device
+    /// supports; smaller values are raised to it.
+    ///
+    /// Should the glyphs of a frame still not fit into the atlas, e.g. with large CJK fonts,
+    /// the text styles are scaled down step by step to half their size. Beyond that a
+    /// [`RuntimeError::FontAtlasOverflow`] is reported; egui 0.18 cannot split the atlas
+    /// into several textures.
+    pub fn set_max_texture_side(&mut self, max_texture_side: usize) {
+        self.raw_input.max_texture_side = Some(max_texture_side.max(1024));
+    }
+
+    /// Factor the text styles have been scaled down by to fit the font atlas, see
+    /// [`set_max_texture_side`](Self::set_max_texture_side).
+    pub fn font_scale(&self) -> f32 {
+        self.font_atlas_fit.font_scale
+    }
+}
+
+// When to repaint an animation of the frame begun at `frame_start`, at most `max_rate` times
+// a second.
+fn animation_repaint_time(frame_start: Instant, max_rate: Option<f32>, now: Instant) -> Instant {
+    match max_rate {
+        Some(max_rate) if max_rate > 0.0 => (frame_start + Duration::from_secs_f64(1.0 / max_rate as f64)).max(now),
+        _ => now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::PointerButton;
+
+    #[test]
+    fn a_panicking_frame_is_recovered() {
+        let mut platform = Platform::new(640, 480, 1.0, egui::FontDefinitions::default(), egui::Style::default());
+        let result = platform.run_frame(|context| {
+            egui::CentralPanel::default().show(context, |ui| ui.label("before the panic"));
+            panic!("UI code panicked");
+        });
+        assert!(result.is_err());
+        assert!(!platform.is_in_frame());
+        assert!(!platform.recover());
+
+        // the font atlas uploaded by the dropped frame reaches the renderer with the next one
+        let output = platform.run_frame(|_| {}).unwrap();
+        assert!(output.textures_delta.set.iter().any(|(id, _)| *id == egui::TextureId::default()));
+        assert!(platform.run_frame(|_| {}).unwrap().textures_delta.is_empty());
+    }
+
+    #[test]
+    fn animation_repaints_are_capped() {
+        let frame_start = Instant::now();
+        let now = frame_start + Duration::from_millis(10);
+        assert_eq!(animation_repaint_time(frame_start, None, now), now);
+        assert_eq!(
+            animation_repaint_time(frame_start, Some(20.0), now),
+            frame_start + Duration::from_millis(50)
+        );
+        let late = frame_start + Duration::from_millis(80);
+        assert_eq!(animation_repaint_time(frame_start, Some(20.0), late), late);
+    }
+
+    #[test]
+    fn text_shrinks_until_the_font_atlas_fits() {
+        let context = Context::default();
+        let mut style = (*context.style()).clone();
+        scale_text_styles(&mut style, 10.0);
+        context.set_style(style);
+        let mut fit = FontAtlasFit::default();
+        let text = (0..400u32).filter_map(|c| char::from_u32(0x21 + c)).collect::<String>();
+        let mut errors = vec![];
+        for _ in 0..20 {
+            context.begin_frame(egui::RawInput {
+                max_texture_side: Some(1024),
+                ..Default::default()
+            });
+            egui::CentralPanel::default().show(&context, |ui| ui.heading(&text));
+            let _ = context.end_frame();
+            errors.extend(fit.end_frame(&context));
+        }
+        assert!(fit.font_scale < 1.0);
+        assert!(context.style().text_styles[&egui::TextStyle::Heading].size < 200.0);
+        assert_eq!(errors, vec![RuntimeError::FontAtlasOverflow { max_texture_side: 1024 }]);
+    }
+
+    #[test]
+    fn file_drag_lifecycle() {
+        let mut file_drag = FileDrag::default();
+        file_drag.hover(Path::new("a.png"));
+        file_drag.hover(Path::new("b.png"));
+        assert_eq!(file_drag.hovered_files.len(), 2);
+        file_drag.cancel();
+        assert!(file_drag.hovered_files.is_empty());
+
+        file_drag.hover(Path::new("dir/a.png"));
+        let dropped_file = file_drag.drop_file(Path::new("dir/a.png"));
+        assert_eq!(dropped_file.name, "a.png");
+        assert!(file_drag.hovered_files.is_empty());
+    }
+
+    #[test]
+    fn pointer_events_report_button_changes() {
+        let pos = pos2(1.0, 2.0);
+        let modifiers = egui::Modifiers::default();
+        let events = pointer_events(pos, &[PointerButton::Secondary], &[PointerButton::Primary], modifiers);
+        assert_eq!(
+            events,
+            vec![
+                egui::Event::PointerMoved(pos),
+                egui::Event::PointerButton {
+                    pos,
+                    button: PointerButton::Secondary,
+                    pressed: false,
+                    modifiers,
+                },
+                egui::Event::PointerButton {
+                    pos,
+                    button: PointerButton::Primary,
+                    pressed: true,
+                    modifiers,
+                },
+            ]
+        );
+
+        let events = pointer_events(pos, &[PointerButton::Primary], &[PointerButton::Primary], modifiers);
+        assert_eq!(events, vec![egui::Event::PointerMoved(pos)]);
+    }
+
+    #[test]
+    fn clicks_stop_scroll_momentum() {
+        let mut momentum = ScrollMomentum::default();
+        assert!(momentum.accept(TouchPhase::Started));
+        momentum.stop();
+        assert!(momentum.accept(TouchPhase::Moved), "the fingers are still on the trackpad");
+        assert!(momentum.accept(TouchPhase::Ended));
+        assert!(momentum.accept(TouchPhase::Moved));
+        momentum.stop();
+        assert!(!momentum.accept(TouchPhase::Moved));
+        assert!(momentum.accept(TouchPhase::Started));
+    }
+
+    #[test]
+    fn cmd_arrows_are_home_and_end() {
+        let cmd_shift = egui::Modifiers {
+            shift: true,
+            mac_cmd: true,
+            command: true,
+            ..Default::default()
+        };
+        let shift = egui::Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        assert_eq!(emulate_home_end(egui::Key::ArrowLeft, cmd_shift), (egui::Key::Home, shift));
+        assert_eq!(emulate_home_end(egui::Key::ArrowRight, cmd_shift), (egui::Key::End, shift));
+        assert_eq!(emulate_home_end(egui::Key::ArrowUp, cmd_shift), (egui::Key::ArrowUp, cmd_shift));
+        assert_eq!(emulate_home_end(egui::Key::ArrowLeft, shift), (egui::Key::ArrowLeft, shift));
+    }
+
+    #[test]
+    fn clipboard_shortcuts_use_the_command_key() {
+        assert_eq!(is_command(ModifiersState::LOGO), cfg!(target_os = "macos"));
+        assert_eq!(is_command(ModifiersState::CTRL), !cfg!(target_os = "macos"));
+    }
+
+    #[test]
+    fn command_modifiers_suppress_text() {
+        assert!(is_text_input(ModifiersState::empty()));
+        assert!(is_text_input(ModifiersState::SHIFT));
+        assert!(is_text_input(ModifiersState::ALT));
+        assert!(!is_text_input(ModifiersState::CTRL));
+        assert!(!is_text_input(ModifiersState::LOGO));
+        assert_eq!(is_text_input(ModifiersState::CTRL | ModifiersState::ALT), cfg!(windows));
+    }
+}
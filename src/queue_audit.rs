@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use ash::vk;
+
+// Something recorded into a command buffer that the audit checks at submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuditEvent {
+    // the image is read or written
+    Use(vk::Image),
+    // ownership of the image is acquired from `src_queue_family`
+    Acquire { image: vk::Image, src_queue_family: u32 },
+    // ownership of the image is released to `dst_queue_family`
+    Release { image: vk::Image, dst_queue_family: u32 },
+}
+
+// A use of an image on a queue family that does not own it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QueueViolation {
+    pub(crate) image: vk::Image,
+    pub(crate) owner: u32,
+    pub(crate) queue_family: u32,
+    pub(crate) acquire: bool,
+}
+
+impl fmt::Display for QueueViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.acquire {
+            write!(
+                f,
+                "{:?} is acquired by queue family {} from the wrong queue family; it was last used on queue family {}",
+                self.image, self.queue_family, self.owner
+            )
+        } else {
+            write!(
+                f,
+                "{:?} is used on queue family {} without an ownership transfer; it was last used on queue family {}",
+                self.image, self.queue_family, self.owner
+            )
+        }
+    }
+}
+
+// Tracks the queue family each image of the integration was last used on, see
+// IntegrationOptions::queue_audit.
+#[derive(Default)]
+pub(crate) struct QueueAudit {
+    // events of the command buffers recorded into since their last submission
+    recorded: HashMap<vk::CommandBuffer, Vec<AuditEvent>>,
+    owners: HashMap<vk::Image, u32>,
+}
+
+impl QueueAudit {
+    pub(crate) fn record(&mut self, command_buffer: vk::CommandBuffer, event: AuditEvent) {
+        self.recorded.entry(command_buffer).or_default().push(event);
+    }
+
+    // Replay the events of `command_buffer` on `queue_family` in order.
+    pub(crate) fn submit(&mut self, command_buffer: vk::CommandBuffer, queue_family: u32) -> Vec<QueueViolation> {
+        let mut violations = vec![];
+        for event in self.recorded.remove(&command_buffer).unwrap_or_default() {
+            match event {
+                AuditEvent::Use(image) => {
+                    let owner = *self.owners.entry(image).or_insert(queue_family);
+                    if owner != queue_family {
+                        violations.push(QueueViolation {
+                            image,
+                            owner,
+                            queue_family,
+                            acquire: false,
+                        });
+                        // reported once
+                        self.owners.insert(image, queue_family);
+                    }
+                }
+                AuditEvent::Acquire {
+                    image,
+                    src_queue_family,
+                } => {
+                    let owner = self.owners.insert(image, queue_family).unwrap_or(src_queue_family);
+                    if owner != src_queue_family {
+                        violations.push(QueueViolation {
+                            image,
+                            owner,
+                            queue_family,
+                            acquire: true,
+                        });
+                    }
+                }
+                AuditEvent::Release {
+                    image,
+                    dst_queue_family,
+                } => {
+                    self.owners.insert(image, dst_queue_family);
+                }
+            }
+        }
+        violations
+    }
+
+    // Stop tracking a destroyed image, whose handle may be reused.
+    pub(crate) fn forget(&mut self, image: vk::Image) {
+        self.owners.remove(&image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn uploads_on_a_transfer_queue_need_an_ownership_transfer() {
+        let (upload, paint) = (vk::CommandBuffer::from_raw(1), vk::CommandBuffer::from_raw(2));
+        let texture = vk::Image::from_raw(3);
+        let mut audit = QueueAudit::default();
+        audit.record(upload, AuditEvent::Use(texture));
+        audit.record(paint, AuditEvent::Use(texture));
+        assert!(audit.submit(upload, 1).is_empty());
+        let violations = audit.submit(paint, 0);
+        assert_eq!(
+            violations,
+            vec![QueueViolation {
+                image: texture,
+                owner: 1,
+                queue_family: 0,
+                acquire: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn paired_transfers_are_not_reported() {
+        let paint = vk::CommandBuffer::from_raw(1);
+        let texture = vk::Image::from_raw(2);
+        let mut audit = QueueAudit::default();
+        for _ in 0..2 {
+            audit.record(
+                paint,
+                AuditEvent::Acquire {
+                    image: texture,
+                    src_queue_family: 2,
+                },
+            );
+            audit.record(paint, AuditEvent::Use(texture));
+            audit.record(
+                paint,
+                AuditEvent::Release {
+                    image: texture,
+                    dst_queue_family: 2,
+                },
+            );
+            assert!(audit.submit(paint, 0).is_empty());
+        }
+    }
+}
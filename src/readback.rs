@@ -0,0 +1,58 @@
+#![warn(missing_docs)]
+
+use ash::vk;
+
+/// What a readback requested with
+/// [`Renderer::request_readback`](crate::Renderer::request_readback) copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadbackSource {
+    /// A texture owned by the integration: a managed texture, e.g. the font atlas, one
+    /// created with [`TextureQueue::create_texture`](crate::TextureQueue::create_texture),
+    /// or the front slot of a [`StreamingTexture`](crate::StreamingTexture). Read as
+    /// premultiplied sRGB `R8G8B8A8` texels.
+    Texture(egui::TextureId),
+    /// The render target the frame was painted to, after the paint and the
+    /// [post paint hook](crate::Renderer::set_post_paint_hook). Swapchain images must have
+    /// been created with `TRANSFER_SRC` usage.
+    Frame,
+}
+
+/// Handle of a readback, polled with
+/// [`Renderer::poll_readback`](crate::Renderer::poll_readback).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadbackHandle(pub(crate) u64);
+
+/// The texels of a completed readback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Readback {
+    /// Width in texels.
+    pub width: u32,
+    /// Height in texels.
+    pub height: u32,
+    /// Format of the texels, e.g. the surface format for [`ReadbackSource::Frame`].
+    pub format: vk::Format,
+    /// Tightly packed rows, top to bottom.
+    pub data: Vec<u8>,
+}
+
+// Bytes per texel of the formats textures and render targets are created with.
+pub(crate) fn texel_size(format: vk::Format) -> u64 {
+    match format {
+        vk::Format::R8_UNORM | vk::Format::R8_SRGB => 1,
+        vk::Format::R16G16B16A16_SFLOAT | vk::Format::R16G16B16A16_UNORM => 8,
+        vk::Format::R32G32B32A32_SFLOAT => 16,
+        _ => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hdr_targets_are_read_back_with_wider_texels() {
+        assert_eq!(texel_size(vk::Format::B8G8R8A8_SRGB), 4);
+        assert_eq!(texel_size(vk::Format::A2B10G10R10_UNORM_PACK32), 4);
+        assert_eq!(texel_size(vk::Format::R16G16B16A16_SFLOAT), 8);
+    }
+}
@@ -0,0 +1,7240 @@
+#![warn(missing_docs)]
+
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::include_bytes;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use ash::{extensions::khr::Swapchain, vk, vk::Handle, Device};
+use ash::vk::ImageMemoryBarrier2;
+use bytemuck::bytes_of;
+use egui::{
+    emath::{pos2, vec2},
+    TexturesDelta};
+
+use crate::queue_audit::{AuditEvent, QueueAudit};
+use crate::slow_path::{above_headroom, SlowPath, SlowPathWarnings, MANY_TEXTURE_UPDATES, SLOW_PATH_HEADROOM};
+use crate::*;
+
+struct VkStagingBuffer<A: AllocatorTrait> {
+    buffer: vk::Buffer,
+    allocation: Option<A::Allocation>,
+}
+
+impl<A: AllocatorTrait> VkStagingBuffer<A> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Default::default(),
+            allocation: None,
+        }
+    }
+    
+    pub fn create(
+        &mut self,
+        device: &Device,
+        allocator: &A,
+        size: u64,
+        location: MemoryLocation,
+        usage: vk::BufferUsageFlags,
+    ) {
+        self.buffer = unsafe {
+            device
+                .create_buffer(
+                    &vk::BufferCreateInfo::builder()
+                        .usage(usage)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .size(size),
+                    None,
+                )
+                .expect("Failed to create buffer.")
+        };
+        
+        self.allocation = {
+            let buffer_requirements = unsafe {
+                device.get_buffer_memory_requirements(self.buffer)
+            };
+            let allocation = allocator
+                .allocate(A::AllocationCreateInfo::new(
+                    buffer_requirements,
+                    location,
+                    true,
+                ))
+                .expect("Failed to create buffer.");
+            
+            unsafe {
+                device.bind_buffer_memory(
+                    self.buffer,
+                    allocation.memory(),
+                    allocation.offset(),
+                )
+                    .expect("Failed to create buffer.")
+            }
+            Some(allocation)
+        };
+    }
+    
+    pub fn upload_data(&mut self, offset: usize, data: &[u8]) {
+        // TODO: gpu-allocator seems not supporting manually unmap memory
+        if let Some(allocation) = &self.allocation {
+            let ptr = allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            unsafe {
+                ptr.add(offset).copy_from_nonoverlapping(data.as_ptr(), data.len());
+            }
+        }
+    }
+    
+    // Copy the first `len` bytes out of a host visible buffer, e.g. after a readback.
+    pub fn read_data(&self, len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        if let Some(allocation) = &self.allocation {
+            let ptr = allocation.mapped_ptr().unwrap().as_ptr() as *const u8;
+            unsafe {
+                ptr.copy_to_nonoverlapping(data.as_mut_ptr(), len);
+            }
+        }
+        data
+    }
+
+    pub fn destroy(&mut self, device: &Device, allocator: &A) {
+        // TODO: Note gpu-allocator example free the allocation first and then the buffer/image, investigate the difference
+        unsafe {
+            device.destroy_buffer(self.buffer, None);
+        }
+        if let Some(allocation) = self.allocation.take() {
+            allocator.free(allocation).expect("Failed to free allocation");
+        }
+    }
+}
+
+struct VkTexture2D<A: AllocatorTrait> {
+    image: vk::Image,
+    allocation: Option<A::Allocation>,
+    view: vk::ImageView,
+    size: (u64, u64),
+    layers: u32,
+    // R8G8B8A8_UNORM, or R8_UNORM for font coverage
+    format: vk::Format,
+    layout: vk::ImageLayout,
+    staging_buffer: VkStagingBuffer<A>,
+    // layout of the texels of a host visible linear image, written without a staging buffer
+    linear: Option<vk::SubresourceLayout>,
+    // frame_count of the paint that last recorded an upload
+    updated_frame: Option<u64>,
+}
+
+impl<A: AllocatorTrait> VkTexture2D<A> {
+    pub fn new() -> Self {
+        Self {
+            image: Default::default(),
+            allocation: None,
+            view: Default::default(),
+            size: (0, 0),
+            layers: 1,
+            format: vk::Format::R8G8B8A8_UNORM,
+            layout: vk::ImageLayout::UNDEFINED,
+            staging_buffer: VkStagingBuffer::<A>::new(),
+            linear: None,
+            updated_frame: None,
+        }
+    }
+    
+    // `view` shows layer 0; the staging buffer holds all `layers` back to back.
+    pub fn create(
+        &mut self,
+        device: &Device,
+        allocator: &A,
+        size: (u32, u32),
+        layers: u32,
+        format: vk::Format,
+        memory_locations: MemoryLocations,
+    ) {
+        self.image = unsafe {
+            device.create_image(
+                    &vk::ImageCreateInfo::builder()
+                        .format(format)
+                        .initial_layout(vk::ImageLayout::UNDEFINED)
+                        .samples(vk::SampleCountFlags::TYPE_1)
+                        .tiling(vk::ImageTiling::OPTIMAL)
+                        .usage(
+                            vk::ImageUsageFlags::SAMPLED
+                                | vk::ImageUsageFlags::TRANSFER_DST
+                                | vk::ImageUsageFlags::TRANSFER_SRC,
+                        )
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .image_type(vk::ImageType::TYPE_2D)
+                        .mip_levels(1)
+                        .array_layers(layers)
+                        .extent(vk::Extent3D {
+                            width: size.0,
+                            height: size.1,
+                            depth: 1,
+                        }),
+                    None,
+                )
+                .expect("Failed to create image.")
+        };
+        self.allocation = {
+            let allocation = allocator
+                .allocate(image_allocation_create_info::<A>(device, self.image, memory_locations.texture))
+                .expect("Failed to create image.");
+            unsafe {
+                device.bind_image_memory(
+                    self.image,
+                    allocation.memory(),
+                    allocation.offset(),
+                )
+                    .expect("Failed to create image.")
+            }
+            Some(allocation)
+        };
+
+        self.view = create_texture_view(device, self.image, format);
+        self.size = (size.0 as u64, size.1 as u64);
+        self.layers = layers;
+        self.format = format;
+        
+        self.staging_buffer.create(
+            device,
+            allocator,
+            size.0 as u64 * size.1 as u64 * texel_size(format) * layers as u64,
+            memory_locations.staging,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+        );
+    }
+    
+    // Host visible linear image with a single layer, sampled in GENERAL layout, whose texels
+    // upload_data writes directly.
+    pub fn create_linear(&mut self, device: &Device, allocator: &A, size: (u32, u32), format: vk::Format) {
+        self.image = unsafe {
+            device.create_image(
+                &vk::ImageCreateInfo::builder()
+                    .format(format)
+                    .initial_layout(vk::ImageLayout::PREINITIALIZED)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::LINEAR)
+                    .usage(
+                        vk::ImageUsageFlags::SAMPLED
+                            | vk::ImageUsageFlags::TRANSFER_SRC
+                            | vk::ImageUsageFlags::TRANSFER_DST,
+                    )
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .extent(vk::Extent3D {
+                        width: size.0,
+                        height: size.1,
+                        depth: 1,
+                    }),
+                None,
+            )
+        }
+        .expect("Failed to create image.");
+        let requirements = unsafe { device.get_image_memory_requirements(self.image) };
+        let allocation = allocator
+            .allocate(A::AllocationCreateInfo::new(requirements, MemoryLocation::CpuToGpu, true))
+            .expect("Failed to create image.");
+        unsafe {
+            device
+                .bind_image_memory(self.image, allocation.memory(), allocation.offset())
+                .expect("Failed to create image.");
+        }
+        self.allocation = Some(allocation);
+        self.linear = Some(unsafe {
+            device.get_image_subresource_layout(
+                self.image,
+                vk::ImageSubresource {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    array_layer: 0,
+                },
+            )
+        });
+        self.layout = vk::ImageLayout::PREINITIALIZED;
+        self.view = create_texture_view(device, self.image, format);
+        self.size = (size.0 as u64, size.1 as u64);
+        self.layers = 1;
+        self.format = format;
+    }
+
+    // Layout the image is in while it is sampled.
+    fn sampled_layout(&self) -> vk::ImageLayout {
+        if self.linear.is_some() {
+            vk::ImageLayout::GENERAL
+        } else {
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        }
+    }
+
+    pub fn upload_data(
+        &mut self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        data: &[u8],
+        offset: (i32, i32),
+        extent: (u32, u32),
+        frame: u64,
+    ) {
+        if let Some(subresource_layout) = self.linear {
+            let allocation = self.allocation.as_ref().unwrap();
+            let texels = unsafe {
+                std::slice::from_raw_parts_mut(
+                    (allocation.mapped_ptr().unwrap().as_ptr() as *mut u8).add(subresource_layout.offset as usize),
+                    subresource_layout.size as usize,
+                )
+            };
+            let texel_size = texel_size(self.format) as usize;
+            copy_rows(texels, subresource_layout.row_pitch as usize, data, offset, extent, texel_size);
+            // host writes are made visible by the submission; the barrier moves a fresh image
+            // out of PREINITIALIZED and orders the reads after earlier ones
+            unsafe {
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &vk::DependencyInfo::builder().image_memory_barriers(&[ImageMemoryBarrier2::builder()
+                        .image(self.image)
+                        .src_stage_mask(vk::PipelineStageFlags2::HOST)
+                        .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                        .src_access_mask(vk::AccessFlags2::HOST_WRITE)
+                        .dst_access_mask(vk::AccessFlags2::SHADER_READ)
+                        .old_layout(self.layout)
+                        .new_layout(vk::ImageLayout::GENERAL)
+                        .subresource_range(
+                            vk::ImageSubresourceRange::builder()
+                                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                .level_count(1)
+                                .layer_count(1)
+                                .build(),
+                        )
+                        .build()]),
+                );
+            }
+            self.layout = vk::ImageLayout::GENERAL;
+            self.updated_frame = Some(frame);
+            return;
+        }
+        self.staging_buffer.upload_data(0, data);
+        self.record_upload(device, command_buffer, offset, extent, frame);
+    }
+
+    // record commands copying the staging buffer contents into the image (all layers)
+    pub fn record_upload(
+        &mut self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        offset: (i32, i32),
+        extent: (u32, u32),
+        frame: u64,
+    ) {
+        self.record_upload_from(device, command_buffer, self.staging_buffer.buffer, offset, extent, frame);
+    }
+
+    // Like record_upload, copying from `buffer` instead of the staging buffer of the texture.
+    pub fn record_upload_from(
+        &mut self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        offset: (i32, i32),
+        extent: (u32, u32),
+        frame: u64,
+    ) {
+        // record buffer staging commands to command buffer; linear images stay in GENERAL
+        // layout, in which the host may write them
+        let transfer_layout = match self.linear {
+            Some(_) => vk::ImageLayout::GENERAL,
+            None => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        };
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(self.layers)
+            .base_mip_level(0)
+            .base_array_layer(0)
+            .build();
+
+        unsafe {
+            // update image layout to transfer dst optimal
+            // (partial updates must keep the existing contents, so only a fresh image starts from UNDEFINED)
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &ash::vk::DependencyInfo::builder()
+                    .image_memory_barriers(&[ImageMemoryBarrier2::builder()
+                        .image(self.image)
+                        .src_stage_mask(vk::PipelineStageFlags2::HOST | vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                        .dst_stage_mask(vk::PipelineStageFlags2::TRANSFER)
+                        .src_access_mask(vk::AccessFlags2::default())
+                        .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                        .old_layout(self.layout)
+                        .new_layout(transfer_layout)
+                        .subresource_range(subresource_range)
+                        .build()
+                    ])
+            );
+
+            // copy staging buffer to image
+            device.cmd_copy_buffer_to_image2(
+                command_buffer,
+                &vk::CopyBufferToImageInfo2::builder()
+                    .src_buffer(buffer)
+                    .dst_image(self.image)
+                    .dst_image_layout(transfer_layout)
+                    .regions(&[vk::BufferImageCopy2::builder()
+                        .image_subresource(vk::ImageSubresourceLayers::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_array_layer(0)
+                            .layer_count(self.layers)
+                            .mip_level(0)
+                            .build())
+                        .image_offset(vk::Offset3D {x: offset.0, y: offset.1, z: 0})
+                        .image_extent(
+                            vk::Extent3D::builder()
+                                .width(extent.0)
+                                .height(extent.1)
+                                .depth(1)
+                                .build(),
+                        )
+                        .build()
+                    ])
+            );
+
+            // update image layout to shader read only optimal
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &ash::vk::DependencyInfo::builder()
+                    .image_memory_barriers(&[ImageMemoryBarrier2::builder()
+                        .image(self.image)
+                        .src_stage_mask(vk::PipelineStageFlags2::TRANSFER)
+                        // textures are only sampled by fragment shaders, so vertex work of tilers
+                        // is not held up by uploads
+                        .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                        .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags2::SHADER_READ)
+                        .old_layout(transfer_layout)
+                        .new_layout(self.sampled_layout())
+                        .subresource_range(subresource_range)
+                        .build()
+                    ])
+            );
+        }
+        self.layout = self.sampled_layout();
+        self.updated_frame = Some(frame);
+    }
+
+    // Device memory of the image and its staging buffer.
+    fn memory_bytes(&self) -> u64 {
+        let image = self.allocation.as_ref().map_or(0, |allocation| allocation.size());
+        let staging = self.staging_buffer.allocation.as_ref().map_or(0, |allocation| allocation.size());
+        image + staging
+    }
+
+    pub fn destroy(&mut self, device: &Device, allocator: &A) {
+        // free font image
+        unsafe {
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+        }
+        if let Some(allocation) = self.allocation.take() {
+            allocator.free(allocation).expect("Failed to free allocation");
+        }
+        self.staging_buffer.destroy(device, allocator);
+    }
+
+    // Free the staging buffer of a texture that is only updated from the staging buffers of
+    // the renderer.
+    pub fn destroy_staging_buffer(&mut self, device: &Device, allocator: &A) {
+        std::mem::replace(&mut self.staging_buffer, VkStagingBuffer::new()).destroy(device, allocator);
+    }
+}
+
+// Copy the tightly packed rows of `texel_size` byte texels of `data` into the `extent` region
+// at `offset` of `texels`, rows of which are `row_pitch` bytes apart.
+fn copy_rows(
+    texels: &mut [u8],
+    row_pitch: usize,
+    data: &[u8],
+    offset: (i32, i32),
+    extent: (u32, u32),
+    texel_size: usize,
+) {
+    let row_bytes = extent.0 as usize * texel_size;
+    for (y, row) in data.chunks_exact(row_bytes).take(extent.1 as usize).enumerate() {
+        let start = (offset.1 as usize + y) * row_pitch + offset.0 as usize * texel_size;
+        texels[start..start + row_bytes].copy_from_slice(row);
+    }
+}
+
+// Size of a `size` texture scaled down to fit within `max_dimension`, keeping its aspect
+// ratio.
+fn fit_texture_size(size: (u32, u32), max_dimension: Option<u32>) -> (u32, u32) {
+    match max_dimension {
+        Some(max_dimension) if size.0.max(size.1) > max_dimension => {
+            let scale = max_dimension as f64 / size.0.max(size.1) as f64;
+            let scaled = |length: u32| ((length as f64 * scale).round() as u32).clamp(1, max_dimension);
+            (scaled(size.0), scaled(size.1))
+        }
+        _ => size,
+    }
+}
+
+// Box filter the tightly packed texels of `channels` bytes of a `size` image down to `scaled`.
+fn downscale_texels(data: &[u8], size: (u32, u32), scaled: (u32, u32), channels: usize) -> Vec<u8> {
+    // texels of the source covered by texel `i` of `scaled_length`
+    let span = |i: u32, length: u32, scaled_length: u32| {
+        let start = (i as u64 * length as u64 / scaled_length as u64) as u32;
+        let end = ((i as u64 + 1) * length as u64 / scaled_length as u64) as u32;
+        start..end.max(start + 1).min(length)
+    };
+    let mut texels = Vec::with_capacity(scaled.0 as usize * scaled.1 as usize * channels);
+    for y in 0..scaled.1 {
+        let rows = span(y, size.1, scaled.1);
+        for x in 0..scaled.0 {
+            let columns = span(x, size.0, scaled.0);
+            let mut sum = [0u32; 4];
+            for row in rows.clone() {
+                for column in columns.clone() {
+                    let start = (row as usize * size.0 as usize + column as usize) * channels;
+                    for (sum, &channel) in sum.iter_mut().zip(&data[start..start + channels]) {
+                        *sum += channel as u32;
+                    }
+                }
+            }
+            let count = rows.len() as u32 * columns.len() as u32;
+            texels.extend(sum[..channels].iter().map(|sum| ((sum + count / 2) / count) as u8));
+        }
+    }
+    texels
+}
+
+// Images at least this large get a dedicated allocation.
+const DEDICATED_ALLOCATION_SIZE: u64 = 16 * 1024 * 1024;
+
+fn wants_dedicated_allocation(size: u64, dedicated_requirements: &vk::MemoryDedicatedRequirements) -> bool {
+    size >= DEDICATED_ALLOCATION_SIZE
+        || dedicated_requirements.prefers_dedicated_allocation == vk::TRUE
+        || dedicated_requirements.requires_dedicated_allocation == vk::TRUE
+}
+
+// Create info for the memory of an optimal tiling image.
+fn image_allocation_create_info<A: AllocatorTrait>(
+    device: &Device,
+    image: vk::Image,
+    location: MemoryLocation,
+) -> A::AllocationCreateInfo {
+    let mut dedicated_requirements = vk::MemoryDedicatedRequirements::default();
+    let mut requirements = vk::MemoryRequirements2::builder().push_next(&mut dedicated_requirements);
+    unsafe {
+        device.get_image_memory_requirements2(
+            &vk::ImageMemoryRequirementsInfo2::builder().image(image),
+            &mut requirements,
+        );
+    }
+    let requirements = requirements.memory_requirements;
+    let create_info = A::AllocationCreateInfo::new(requirements, location, false);
+    if wants_dedicated_allocation(requirements.size, &dedicated_requirements) {
+        create_info.dedicated(DedicatedAllocation::Image(image))
+    } else {
+        create_info
+    }
+}
+
+// 2D view of a single layer of `image`.
+fn create_layer_view(device: &Device, image: vk::Image, format: vk::Format, layer: u32) -> vk::ImageView {
+    create_swizzled_layer_view(device, image, format, layer, vk::ComponentMapping::default())
+}
+
+// View of a texture of the integration. The font coverage of R8_UNORM textures is read from
+// all channels, so the shaders sample it like the premultiplied white texels egui expands it
+// to.
+fn create_texture_view(device: &Device, image: vk::Image, format: vk::Format) -> vk::ImageView {
+    let components = if format == vk::Format::R8_UNORM {
+        vk::ComponentMapping {
+            r: vk::ComponentSwizzle::R,
+            g: vk::ComponentSwizzle::R,
+            b: vk::ComponentSwizzle::R,
+            a: vk::ComponentSwizzle::R,
+        }
+    } else {
+        vk::ComponentMapping::default()
+    };
+    create_swizzled_layer_view(device, image, format, 0, components)
+}
+
+fn create_swizzled_layer_view(
+    device: &Device,
+    image: vk::Image,
+    format: vk::Format,
+    layer: u32,
+    components: vk::ComponentMapping,
+) -> vk::ImageView {
+    unsafe {
+        device.create_image_view(
+            &vk::ImageViewCreateInfo::builder()
+                .image(image)
+                .format(format)
+                .components(components)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .subresource_range(
+                    vk::ImageSubresourceRange::builder()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .base_array_layer(layer)
+                        .base_mip_level(0)
+                        .layer_count(1)
+                        .level_count(1)
+                        .build(),
+                ),
+            None,
+        )
+    }
+    .expect("Failed to create image view.")
+}
+
+/// A user texture whose whole contents are replaced every frame.
+///
+/// Intended for video, camera or emulator framebuffer display. The texture owns two images
+/// and a copy of the texels of the last update, so [`update`](Self::update) is a plain memcpy
+/// and nothing is re-allocated. The next [`Integration::paint`] copies them to the GPU
+/// through a staging buffer of the renderer, reused once the frame has completed, and then
+/// swaps the displayed image.
+///
+/// Created with [`Integration::create_streaming_texture`].
+pub struct StreamingTexture<A: AllocatorTrait> {
+    size: (u32, u32),
+    slots: [(VkTexture2D<A>, vk::DescriptorSet); 2],
+    front: Option<usize>,
+    pending: Option<usize>,
+    // texels of the pending update
+    texels: Vec<u8>,
+}
+
+impl<A: AllocatorTrait> StreamingTexture<A> {
+    /// Size of the texture in texels.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Replace the contents of the texture with tightly packed RGBA8 (sRGB, premultiplied) texels.
+    ///
+    /// Safe to call every frame; only the last update before `paint` is shown.
+    pub fn update(&mut self, data: &[u8]) {
+        assert_eq!(
+            data.len(),
+            (self.size.0 * self.size.1 * 4) as usize,
+            "Mismatch between streaming texture size and data length"
+        );
+        let back = match self.front {
+            Some(front) => 1 - front,
+            None => 0,
+        };
+        // frames in flight may still read the staging buffer of an earlier update
+        self.texels.clear();
+        self.texels.extend_from_slice(data);
+        self.pending = Some(back);
+    }
+}
+
+// SPIR-V of the bundled shaders, compiled by build.rs with the `compile-shaders` feature.
+#[cfg(not(feature = "compile-shaders"))]
+const VERT_SPV: &[u8] = include_bytes!("shaders/spv/vert.spv");
+#[cfg(not(feature = "compile-shaders"))]
+const FRAG_SPV: &[u8] = include_bytes!("shaders/spv/frag.spv");
+#[cfg(feature = "compile-shaders")]
+const VERT_SPV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/vert.vert.spv"));
+#[cfg(feature = "compile-shaders")]
+const FRAG_SPV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/frag.frag.spv"));
+#[cfg(not(feature = "compile-shaders"))]
+const FRAG_SUBPIXEL_SPV: &[u8] = include_bytes!("shaders/spv/frag_subpixel.spv");
+#[cfg(feature = "compile-shaders")]
+const FRAG_SUBPIXEL_SPV: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/frag_subpixel.frag.spv"));
+
+// Set and binding of the texture descriptor the shaders sample.
+fn texture_descriptor(options: &IntegrationOptions) -> (u32, u32) {
+    options
+        .descriptor_set_layout
+        .as_ref()
+        .map_or((0, 0), |layout_options| (layout_options.set, layout_options.binding))
+}
+
+const SPIRV_OP_DECORATE: u32 = 71;
+const SPIRV_DECORATION_BINDING: u32 = 33;
+const SPIRV_DECORATION_DESCRIPTOR_SET: u32 = 34;
+
+// `spirv` with its descriptor set and binding decorations replaced. The egui shaders sample a
+// single texture, so every decoration is the one of the texture.
+fn relocate_descriptor(spirv: &[u8], set: u32, binding: u32) -> Vec<u8> {
+    let mut words = spirv
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    // instructions follow the 5 word header
+    let mut i = 5;
+    while i < words.len() {
+        let (word_count, opcode) = ((words[i] >> 16) as usize, words[i] & 0xffff);
+        if opcode == SPIRV_OP_DECORATE && word_count == 4 {
+            match words[i + 2] {
+                SPIRV_DECORATION_DESCRIPTOR_SET => words[i + 3] = set,
+                SPIRV_DECORATION_BINDING => words[i + 3] = binding,
+                _ => {}
+            }
+        }
+        i += word_count.max(1);
+    }
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+// Mipmap mode matching a filter; filters without one (e.g. CUBIC_EXT) blend mip levels linearly.
+fn sampler_mipmap_mode(filter: vk::Filter) -> vk::SamplerMipmapMode {
+    match filter {
+        vk::Filter::NEAREST => vk::SamplerMipmapMode::NEAREST,
+        _ => vk::SamplerMipmapMode::LINEAR,
+    }
+}
+
+// Sampler options of a texture of the integration: those set with
+// Renderer::set_texture_sampler, else the font sampler for the font atlas and the default.
+fn texture_sampler_options(
+    texture_id: egui::TextureId,
+    texture_samplers: &HashMap<egui::TextureId, SamplerOptions>,
+    font_sampler: SamplerOptions,
+) -> SamplerOptions {
+    match texture_samplers.get(&texture_id) {
+        Some(&sampler_options) => sampler_options,
+        None if texture_id == FONT_TEXTURE_ID => font_sampler,
+        None => SamplerOptions::default(),
+    }
+}
+
+// Anisotropy a sampler is created with: `requested` within what the device supports.
+fn sampler_anisotropy(requested: Option<f32>, max_sampler_anisotropy: Option<f32>) -> Option<f32> {
+    let requested = requested?;
+    match max_sampler_anisotropy {
+        Some(max) => Some(requested.clamp(1.0, max)),
+        None => {
+            log::warn!("samplerAnisotropy is not enabled; the sampler is created without anisotropic filtering.");
+            None
+        }
+    }
+}
+
+// Layout the render target is in when a paint is recorded.
+fn target_initial_layout(options: &IntegrationOptions) -> vk::ImageLayout {
+    if let Some(target_layouts) = options.target_layouts {
+        target_layouts.initial_layout
+    } else if options.tile_based_gpu {
+        vk::ImageLayout::PRESENT_SRC_KHR
+    } else {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+}
+
+// Layout the render target is left in by a paint.
+fn target_final_layout(options: &IntegrationOptions) -> vk::ImageLayout {
+    options
+        .target_layouts
+        .map_or(vk::ImageLayout::PRESENT_SRC_KHR, |target_layouts| target_layouts.final_layout)
+}
+
+// Frames whose vertex and index data add up to at most this many bytes, e.g. a lone tooltip,
+// are recorded with cmd_update_buffer into device local buffers instead of mapped memory.
+const TINY_FRAME_BYTES: usize = 2048;
+
+// Vertex and index buffers that meshes are copied into for drawing. Host visible buffers are
+// written through mapped memory, device local ones with cmd_update_buffer.
+struct MeshBuffers<A: AllocatorTrait> {
+    vertex_buffer: vk::Buffer,
+    vertex_allocation: A::Allocation,
+    index_buffer: vk::Buffer,
+    index_allocation: A::Allocation,
+    vertex_capacity: usize,
+    index_capacity: usize,
+}
+
+impl<A: AllocatorTrait> MeshBuffers<A> {
+    fn new(
+        device: &Device,
+        allocator: &A,
+        vertex_capacity: u64,
+        index_capacity: u64,
+        vertex_location: MemoryLocation,
+        index_location: MemoryLocation,
+    ) -> Self {
+        // device local buffers are written with cmd_update_buffer
+        let transfer = |location| match location {
+            MemoryLocation::GpuOnly => vk::BufferUsageFlags::TRANSFER_DST,
+            _ => vk::BufferUsageFlags::empty(),
+        };
+        let (vertex_buffer, vertex_allocation) = Self::create_buffer(
+            device,
+            allocator,
+            vk::BufferUsageFlags::VERTEX_BUFFER | transfer(vertex_location),
+            vertex_capacity,
+            vertex_location,
+            "Failed to create vertex buffer.",
+        );
+        let (index_buffer, index_allocation) = Self::create_buffer(
+            device,
+            allocator,
+            vk::BufferUsageFlags::INDEX_BUFFER | transfer(index_location),
+            index_capacity,
+            index_location,
+            "Failed to create index buffer.",
+        );
+        Self {
+            vertex_buffer,
+            vertex_allocation,
+            index_buffer,
+            index_allocation,
+            vertex_capacity: vertex_capacity as usize,
+            index_capacity: index_capacity as usize,
+        }
+    }
+
+    fn create_buffer(
+        device: &Device,
+        allocator: &A,
+        usage: vk::BufferUsageFlags,
+        size: u64,
+        location: MemoryLocation,
+        error: &str,
+    ) -> (vk::Buffer, A::Allocation) {
+        let buffer = unsafe {
+            device
+                .create_buffer(
+                    &vk::BufferCreateInfo::builder()
+                        .usage(usage)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .size(size),
+                    None,
+                )
+                .expect(error)
+        };
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation = allocator
+            .allocate(A::AllocationCreateInfo::new(
+                requirements,
+                location,
+                true,
+            ))
+            .expect(error);
+        unsafe {
+            device
+                .bind_buffer_memory(buffer, allocation.memory(), allocation.offset())
+                .expect(error)
+        }
+        (buffer, allocation)
+    }
+
+    fn destroy(self, device: &Device, allocator: &A) {
+        unsafe {
+            device.destroy_buffer(self.vertex_buffer, None);
+            device.destroy_buffer(self.index_buffer, None);
+        }
+        allocator
+            .free(self.vertex_allocation)
+            .expect("Failed to free allocation");
+        allocator
+            .free(self.index_allocation)
+            .expect("Failed to free allocation");
+    }
+}
+
+// Descriptor buffer holding the descriptor sets of textures with
+// IntegrationOptions::descriptor_buffer. Descriptor sets are then slots of the buffer, handed
+// out as handles holding the slot index plus one.
+struct DescriptorBuffer<A: AllocatorTrait> {
+    fns: vk::ExtDescriptorBufferFn,
+    buffer: vk::Buffer,
+    allocation: A::Allocation,
+    address: vk::DeviceAddress,
+    slot_size: u64,
+    binding_offset: u64,
+    descriptor_size: usize,
+    next_slot: u64,
+    slot_count: u64,
+}
+
+// Size of a descriptor set of `layout_size` bytes in a descriptor buffer.
+fn descriptor_slot_size(layout_size: u64, offset_alignment: u64) -> u64 {
+    layout_size.div_ceil(offset_alignment) * offset_alignment
+}
+
+impl<A: AllocatorTrait> DescriptorBuffer<A> {
+    fn new(
+        device: &Device,
+        allocator: &A,
+        descriptor_set_layout: vk::DescriptorSetLayout,
+        binding: u32,
+        options: DescriptorBufferOptions,
+        slot_count: u32,
+    ) -> Self {
+        let get_device_proc_addr = options.get_device_proc_addr;
+        let handle = device.handle();
+        let fns = vk::ExtDescriptorBufferFn::load(|name| unsafe {
+            std::mem::transmute(get_device_proc_addr(handle, name.as_ptr()))
+        });
+        let (mut layout_size, mut binding_offset) = (0, 0);
+        unsafe {
+            (fns.get_descriptor_set_layout_size_ext)(handle, descriptor_set_layout, &mut layout_size);
+            (fns.get_descriptor_set_layout_binding_offset_ext)(handle, descriptor_set_layout, binding, &mut binding_offset);
+        }
+        let slot_size = descriptor_slot_size(layout_size, options.descriptor_buffer_offset_alignment);
+
+        let buffer = unsafe {
+            device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .usage(
+                        vk::BufferUsageFlags::SAMPLER_DESCRIPTOR_BUFFER_EXT
+                            | vk::BufferUsageFlags::RESOURCE_DESCRIPTOR_BUFFER_EXT
+                            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+                    )
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .size(slot_size * slot_count as u64),
+                None,
+            )
+        }
+        .expect("Failed to create descriptor buffer.");
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation = allocator
+            .allocate(A::AllocationCreateInfo::new(requirements, MemoryLocation::CpuToGpu, true))
+            .expect("Failed to create descriptor buffer.");
+        let address = unsafe {
+            device
+                .bind_buffer_memory(buffer, allocation.memory(), allocation.offset())
+                .expect("Failed to create descriptor buffer.");
+            device.get_buffer_device_address(&vk::BufferDeviceAddressInfo::builder().buffer(buffer))
+        };
+        Self {
+            fns,
+            buffer,
+            allocation,
+            address,
+            slot_size,
+            binding_offset,
+            descriptor_size: options.combined_image_sampler_descriptor_size,
+            next_slot: 0,
+            slot_count: slot_count as u64,
+        }
+    }
+
+    fn allocate(&mut self) -> vk::DescriptorSet {
+        assert!(
+            self.next_slot < self.slot_count,
+            "Failed to create descriptor set for texture: the descriptor buffer is full"
+        );
+        self.next_slot += 1;
+        vk::DescriptorSet::from_raw(self.next_slot)
+    }
+
+    fn offset(&self, descriptor_set: vk::DescriptorSet) -> u64 {
+        (descriptor_set.as_raw() - 1) * self.slot_size
+    }
+
+    fn write(&self, device: &Device, descriptor_set: vk::DescriptorSet, image_info: &vk::DescriptorImageInfo) {
+        let get_info = vk::DescriptorGetInfoEXT::builder()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .data(vk::DescriptorDataEXT {
+                p_combined_image_sampler: image_info,
+            });
+        let offset = (self.offset(descriptor_set) + self.binding_offset) as usize;
+        unsafe {
+            let ptr = self.allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            (self.fns.get_descriptor_ext)(
+                device.handle(),
+                &*get_info,
+                self.descriptor_size,
+                ptr.add(offset) as *mut std::ffi::c_void,
+            );
+        }
+    }
+
+    fn destroy(self, device: &Device, allocator: &A) {
+        unsafe {
+            device.destroy_buffer(self.buffer, None);
+        }
+        allocator.free(self.allocation).expect("Failed to free allocation");
+    }
+}
+
+// Secondary command buffer holding the draws last recorded for a swapchain image, with what
+// they were recorded from.
+struct RecordedFrame {
+    command_buffer: vk::CommandBuffer,
+    // None once the textures it references may have changed
+    key: Option<RecordedFrameKey>,
+}
+
+struct RecordedFrameKey {
+    // the frame in flight whose mesh buffers the draws read
+    frame_index: usize,
+    clipped_primitives: Vec<egui::ClippedPrimitive>,
+    pixels_per_point: f32,
+    transform: Option<[[f32; 4]; 4]>,
+    ui_tint: egui::Color32,
+    display_calibration: DisplayCalibration,
+    custom_push_constants: Vec<u8>,
+}
+
+// Whether two frames draw the same meshes. Callbacks are never considered the same.
+fn same_primitives(a: &[egui::ClippedPrimitive], b: &[egui::ClippedPrimitive]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.clip_rect == b.clip_rect
+                && match (&a.primitive, &b.primitive) {
+                    (egui::epaint::Primitive::Mesh(a), egui::epaint::Primitive::Mesh(b)) => a == b,
+                    _ => false,
+                }
+        })
+}
+
+// Intermediate image (one per render target image) the UI is rendered into before it is
+// composited over the render target.
+struct UiLayer<A: AllocatorTrait> {
+    render_pass: vk::RenderPass,
+    composite_pipeline: vk::Pipeline,
+    extent: vk::Extent2D,
+    images: Vec<UiLayerImage<A>>,
+}
+
+struct UiLayerImage<A: AllocatorTrait> {
+    image: vk::Image,
+    allocation: A::Allocation,
+    view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    descriptor_set: vk::DescriptorSet,
+}
+
+// A resource released while frames in flight may still use it, destroyed by
+// Renderer::on_frame_complete.
+enum DeferredDestruction<A: AllocatorTrait> {
+    Texture(VkTexture2D<A>),
+    DescriptorSet(vk::DescriptorSet),
+    ImageView(vk::ImageView),
+    MeshBuffers(MeshBuffers<A>),
+    OffscreenPanel(OffscreenPanel<A>),
+    // returned to the staging buffers of texture updates, with its size
+    StagingBuffer(u64, VkStagingBuffer<A>),
+}
+
+// A readback requested with Renderer::request_readback.
+enum PendingReadback<A: AllocatorTrait> {
+    // recorded by the next paint
+    Requested(ReadbackSource),
+    // copied into `buffer` by frame in flight `frame_index`
+    InFlight {
+        frame_index: usize,
+        buffer: VkStagingBuffer<A>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    },
+    Complete(Readback),
+}
+
+// Copy layer `layer` of `image` into `buffer` for the host to read, leaving the image in
+// `layout`.
+fn record_image_readback(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    layout: vk::ImageLayout,
+    layer: u32,
+    extent: vk::Extent2D,
+    buffer: vk::Buffer,
+) {
+    let subresource_range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: layer,
+        layer_count: 1,
+    };
+    unsafe {
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::builder().image_memory_barriers(&[ImageMemoryBarrier2::builder()
+                .src_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                .src_access_mask(vk::AccessFlags2::MEMORY_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                .old_layout(layout)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .image(image)
+                .subresource_range(subresource_range)
+                .build()]),
+        );
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            buffer,
+            &[vk::BufferImageCopy::builder()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: layer,
+                    layer_count: 1,
+                })
+                .image_extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .build()],
+        );
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::builder()
+                .memory_barriers(&[vk::MemoryBarrier2::builder()
+                    .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+                    .dst_access_mask(vk::AccessFlags2::HOST_READ)
+                    .build()])
+                .image_memory_barriers(&[ImageMemoryBarrier2::builder()
+                    .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                    .dst_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                    .dst_access_mask(vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE)
+                    .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .new_layout(layout)
+                    .image(image)
+                    .subresource_range(subresource_range)
+                    .build()]),
+        );
+    }
+}
+
+// Offscreen image a UiInstance is painted into by Renderer::paint_offscreen_panel, drawn
+// like a user texture.
+struct OffscreenPanel<A: AllocatorTrait> {
+    ui_instance: u64,
+    extent: vk::Extent2D,
+    image: vk::Image,
+    allocation: A::Allocation,
+    // rendered to as sRGB, sampled as the premultiplied sRGB bytes egui textures hold
+    attachment_view: vk::ImageView,
+    sampled_view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    descriptor_set: vk::DescriptorSet,
+    // per frame in flight, grown to the largest frame painted
+    mesh_buffers: Vec<Option<MeshBuffers<A>>>,
+}
+
+// What a pipeline is created to draw into: a render pass, or with
+// IntegrationOptions::dynamic_rendering attachments of these formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PipelineTarget {
+    RenderPass(vk::RenderPass),
+    Dynamic {
+        color_format: vk::Format,
+        depth_format: Option<vk::Format>,
+    },
+}
+
+impl PipelineTarget {
+    // The render target, drawn into with dynamic rendering where there is no render pass.
+    fn render_target(render_pass: vk::RenderPass, color_format: vk::Format, depth_format: Option<vk::Format>) -> Self {
+        if render_pass == vk::RenderPass::null() {
+            PipelineTarget::Dynamic {
+                color_format,
+                depth_format,
+            }
+        } else {
+            PipelineTarget::RenderPass(render_pass)
+        }
+    }
+}
+
+// Depth and stencil attachment formats of dynamic rendering with a depth attachment of
+// `depth_format`.
+fn depth_stencil_formats(depth_format: Option<vk::Format>) -> (vk::Format, vk::Format) {
+    match depth_format {
+        Some(depth_format) if format_has_stencil(depth_format) => (depth_format, depth_format),
+        Some(depth_format) => (depth_format, vk::Format::UNDEFINED),
+        None => (vk::Format::UNDEFINED, vk::Format::UNDEFINED),
+    }
+}
+
+// Barrier taking the place of the layout transitions and external dependencies of the target
+// render pass with IntegrationOptions::dynamic_rendering: into COLOR_ATTACHMENT_OPTIMAL before
+// the draws with `begin`, into the final layout after them otherwise.
+fn dynamic_rendering_barrier(
+    options: &IntegrationOptions,
+    image: vk::Image,
+    layer: u32,
+    begin: bool,
+) -> ImageMemoryBarrier2 {
+    let barrier = ImageMemoryBarrier2::builder().image(image).subresource_range(
+        vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .base_array_layer(layer)
+            .layer_count(1)
+            .build(),
+    );
+    let barrier = if begin {
+        let initial_layout = target_initial_layout(options);
+        let (src_stage_mask, src_access_mask) = match initial_layout {
+            vk::ImageLayout::UNDEFINED
+            | vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            | vk::ImageLayout::PRESENT_SRC_KHR => (
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            ),
+            // written by other kinds of commands, e.g. a compute pass or a copy
+            _ => (vk::PipelineStageFlags2::ALL_COMMANDS, vk::AccessFlags2::MEMORY_WRITE),
+        };
+        barrier
+            .old_layout(initial_layout)
+            .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .src_stage_mask(src_stage_mask)
+            .src_access_mask(src_access_mask)
+            .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+    } else {
+        let final_layout = target_final_layout(options);
+        let (dst_stage_mask, dst_access_mask) = match final_layout {
+            // presentation waits for a semaphore
+            vk::ImageLayout::PRESENT_SRC_KHR => (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE),
+            // the attachment is sampled afterwards
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => {
+                (vk::PipelineStageFlags2::FRAGMENT_SHADER, vk::AccessFlags2::SHADER_READ)
+            }
+            // more passes of the application follow
+            _ => (
+                vk::PipelineStageFlags2::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+            ),
+        };
+        barrier
+            .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .new_layout(final_layout)
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(dst_stage_mask)
+            .dst_access_mask(dst_access_mask)
+    };
+    barrier.build()
+}
+
+// Specialization constants of the egui shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ShaderVariant {
+    // texels are premultiplied sRGB and converted to linear, IntegrationOptions::premultiplied_alpha
+    premultiplied_alpha: bool,
+    // the target stores sRGB encoded values without converting them, e.g. B8G8R8A8_UNORM
+    encode_srgb: bool,
+}
+
+impl ShaderVariant {
+    // The variant writing linear colors to a target of `format`.
+    fn new(premultiplied_alpha: bool, format: vk::Format) -> Self {
+        Self {
+            premultiplied_alpha,
+            encode_srgb: !stores_linear_colors(format),
+        }
+    }
+}
+
+// Whether render targets of `format` take linear colors: sRGB formats encode them on write and
+// float formats keep them linear. Other formats are presented as they are, so the shaders must
+// encode to sRGB.
+fn stores_linear_colors(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::R8_SRGB
+            | vk::Format::R8G8_SRGB
+            | vk::Format::R8G8B8_SRGB
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+            | vk::Format::B10G11R11_UFLOAT_PACK32
+            | vk::Format::R16G16B16A16_SFLOAT
+            | vk::Format::R32G32B32A32_SFLOAT
+    )
+}
+
+// The variant compositing a UI layer of `layer_format` onto a target of `target_format`:
+// encoded layer texels are decoded like premultiplied sRGB texels for a linear target, and
+// linear ones are encoded for an encoding target.
+fn composite_variant(layer_format: vk::Format, target_format: vk::Format) -> ShaderVariant {
+    let (layer_linear, target_linear) = (stores_linear_colors(layer_format), stores_linear_colors(target_format));
+    ShaderVariant {
+        premultiplied_alpha: target_linear && !layer_linear,
+        encode_srgb: layer_linear && !target_linear,
+    }
+}
+
+// Renderer::create_pipeline.
+type CreatePipelineFn = fn(
+    &Device,
+    PipelineTarget,
+    vk::PipelineLayout,
+    vk::PipelineCreateFlags,
+    ShaderVariant,
+    vk::PipelineColorBlendAttachmentState,
+    Option<&DepthAttachment>,
+    &[u8],
+) -> vk::Pipeline;
+
+// Format offscreen panels are rendered in.
+const OFFSCREEN_PANEL_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+// An array texture created by the integration, shown as one user texture per layer.
+struct ArrayTexture<A: AllocatorTrait> {
+    texture: VkTexture2D<A>,
+    layer_views: Vec<vk::ImageView>,
+    ids: Vec<u64>,
+    // texels of all layers back to back, uploaded through a staging buffer of the renderer
+    texels: Vec<u8>,
+    pending_upload: bool,
+}
+
+fn color_blend_attachment_state(premultiplied_alpha: bool) -> vk::PipelineColorBlendAttachmentState {
+    let builder = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(
+            vk::ColorComponentFlags::R
+                | vk::ColorComponentFlags::G
+                | vk::ColorComponentFlags::B
+                | vk::ColorComponentFlags::A,
+        )
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::ONE)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA);
+    if premultiplied_alpha {
+        // composite alpha "over" the target as well, so transparent targets keep coverage
+        builder
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .build()
+    } else {
+        builder.build()
+    }
+}
+
+// Blend state of the subpixel text pipeline: every color channel is blended with its own
+// coverage, output as the second source.
+fn subpixel_blend_attachment_state(premultiplied_alpha: bool) -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC1_COLOR,
+        ..color_blend_attachment_state(premultiplied_alpha)
+    }
+}
+
+// Whether scissor_rect clamps `clip_rect` by more than its rounding.
+fn scissor_is_clamped(clip_rect: egui::Rect, scale_factor: f32, physical_width: u32, physical_height: u32) -> bool {
+    clip_rect.min.x * scale_factor < -0.5
+        || clip_rect.min.y * scale_factor < -0.5
+        || clip_rect.max.x * scale_factor > physical_width as f32 + 0.5
+        || clip_rect.max.y * scale_factor > physical_height as f32 + 0.5
+}
+
+// Union of the scissor rects of `clip_rects`, or all of `extent` if they cover nothing.
+fn render_area(clip_rects: impl Iterator<Item = egui::Rect>, scale_factor: f32, extent: vk::Extent2D) -> vk::Rect2D {
+    clip_rects
+        .map(|clip_rect| scissor_rect(clip_rect, scale_factor, extent.width, extent.height))
+        .filter(|scissor| scissor.extent.width > 0 && scissor.extent.height > 0)
+        .map(|scissor| {
+            let min = (scissor.offset.x, scissor.offset.y);
+            (min, (min.0 + scissor.extent.width as i32, min.1 + scissor.extent.height as i32))
+        })
+        .reduce(|(a_min, a_max), (b_min, b_max)| {
+            ((a_min.0.min(b_min.0), a_min.1.min(b_min.1)), (a_max.0.max(b_max.0), a_max.1.max(b_max.1)))
+        })
+        .map(|(min, max)| vk::Rect2D {
+            offset: vk::Offset2D { x: min.0, y: min.1 },
+            extent: vk::Extent2D {
+                width: (max.0 - min.0) as u32,
+                height: (max.1 - min.1) as u32,
+            },
+        })
+        .unwrap_or_else(|| vk::Rect2D::builder().extent(extent).build())
+}
+
+// Remember `scissor` as the bound one; false if it already was, so setting it can be skipped.
+// Consecutive meshes mostly share a clip rect, e.g. the glyph runs of a text-heavy window.
+fn replace_scissor(bound: &mut Option<vk::Rect2D>, scissor: vk::Rect2D) -> bool {
+    bound.replace(scissor) != Some(scissor)
+}
+
+// Scissor rect in physical pixels for an egui clip rect in points, clamped to the framebuffer.
+fn scissor_rect(clip_rect: egui::Rect, scale_factor: f32, physical_width: u32, physical_height: u32) -> vk::Rect2D {
+    let min_x = (clip_rect.min.x * scale_factor).clamp(0.0, physical_width as f32).round();
+    let min_y = (clip_rect.min.y * scale_factor).clamp(0.0, physical_height as f32).round();
+    let max_x = (clip_rect.max.x * scale_factor).clamp(min_x, physical_width as f32).round();
+    let max_y = (clip_rect.max.y * scale_factor).clamp(min_y, physical_height as f32).round();
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: min_x as i32,
+            y: min_y as i32,
+        },
+        extent: vk::Extent2D {
+            width: (max_x - min_x) as u32,
+            height: (max_y - min_y) as u32,
+        },
+    }
+}
+
+// How much of the per-frame vertex and index buffers has been filled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct MeshBufferCursor {
+    vertex_offset: usize,
+    index_offset: usize,
+    // indices of the frame are stored as u16 instead of u32
+    short_indices: bool,
+    // the frame was uploaded up front by upload_tiny_frame
+    tiny: bool,
+}
+
+// Fraction of the descriptor pool or the mesh buffers in use above which strict mode warns.
+#[cfg(feature = "strict")]
+const STRICT_HEADROOM: f32 = 0.9;
+
+// Whether using `after` instead of `before` bytes (or sets) of `capacity` crosses the headroom.
+#[cfg(feature = "strict")]
+fn crosses_headroom(before: usize, after: usize, capacity: usize) -> bool {
+    let threshold = capacity as f32 * STRICT_HEADROOM;
+    (before as f32) <= threshold && (after as f32) > threshold
+}
+
+impl MeshBufferCursor {
+    // Start a frame drawing `passes`. UI meshes are small, so their indices usually fit in u16,
+    // halving the index data uploaded and read by the GPU.
+    fn for_frame(passes: &[&[egui::ClippedPrimitive]]) -> Self {
+        let short_indices = passes.iter().flat_map(|pass| pass.iter()).all(|clipped_primitive| {
+            match &clipped_primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) => mesh.vertices.len() <= u16::MAX as usize + 1,
+                _ => true,
+            }
+        });
+        Self {
+            short_indices,
+            ..Default::default()
+        }
+    }
+
+    // Size of the vertex and index data of `passes`.
+    fn frame_bytes(&self, passes: &[&[egui::ClippedPrimitive]]) -> usize {
+        passes
+            .iter()
+            .flat_map(|pass| pass.iter())
+            .map(|clipped_primitive| match &clipped_primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) => {
+                    std::mem::size_of_val(mesh.vertices.as_slice()) + mesh.indices.len() * self.index_size()
+                }
+                _ => 0,
+            })
+            .sum()
+    }
+
+    // Continue in empty buffers with the same index type.
+    fn restart(&mut self) {
+        *self = Self {
+            short_indices: self.short_indices,
+            ..Default::default()
+        };
+    }
+
+    fn index_type(&self) -> vk::IndexType {
+        if self.short_indices {
+            vk::IndexType::UINT16
+        } else {
+            vk::IndexType::UINT32
+        }
+    }
+
+    fn index_size(&self) -> usize {
+        if self.short_indices {
+            std::mem::size_of::<u16>()
+        } else {
+            std::mem::size_of::<u32>()
+        }
+    }
+
+    // Reserve room for a mesh and return the byte offsets to copy its vertices and indices to,
+    // or None if either buffer would overflow.
+    fn reserve(
+        &mut self,
+        vertices: &[egui::epaint::Vertex],
+        indices: &[u32],
+        vertex_capacity: usize,
+        index_capacity: usize,
+    ) -> Option<(usize, usize)> {
+        let vertex_end = self.vertex_offset + std::mem::size_of_val(vertices);
+        let index_end = self.index_offset + indices.len() * self.index_size();
+        if vertex_end > vertex_capacity || index_end > index_capacity {
+            return None;
+        }
+        let offsets = (self.vertex_offset, self.index_offset);
+        self.vertex_offset = vertex_end;
+        self.index_offset = index_end;
+        Some(offsets)
+    }
+}
+
+// What an image delta does to the textures the integration holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextureUpdate {
+    // upload into a region of the existing texture
+    Partial,
+    // create a texture reusing the descriptor set of the one it replaces
+    Replace,
+    // create a texture with a new descriptor set
+    Create,
+    // a region of a texture that does not exist, which is skipped
+    Unknown,
+}
+
+impl TextureUpdate {
+    fn plan(exists: bool, image_delta: &egui::epaint::ImageDelta) -> Self {
+        match (image_delta.pos, exists) {
+            (Some(_), true) => TextureUpdate::Partial,
+            (Some(_), false) => TextureUpdate::Unknown,
+            (None, true) => TextureUpdate::Replace,
+            (None, false) => TextureUpdate::Create,
+        }
+    }
+}
+
+// Frames a user texture that is no longer registered keeps its descriptor set for, so
+// immediate-mode code registering it again every frame does not allocate a new one.
+const USER_TEXTURE_CACHE_FRAMES: u64 = 2;
+
+// What UserTextureRegistry::release did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Release {
+    // other registrations of the id are left
+    Registered,
+    // the descriptor set is kept until evicted
+    Cached,
+    // the descriptor set is to be recycled
+    Removed(vk::DescriptorSet),
+}
+
+// Descriptor sets of registered user textures, keyed by TextureId::User id and by (view, sampler).
+#[derive(Default)]
+struct UserTextureRegistry {
+    descriptor_sets: HashMap<u64, ((vk::ImageView, vk::Sampler), vk::DescriptorSet)>,
+    ids: HashMap<(vk::ImageView, vk::Sampler), u64>,
+    // registrations of each id, each released by one unregister
+    ref_counts: HashMap<u64, usize>,
+    // pairs no longer registered, with their id, descriptor set and the frame count they were
+    // released at; registering them again revives them until they are evicted
+    released: HashMap<(vk::ImageView, vk::Sampler), (u64, vk::DescriptorSet, u64)>,
+    // shared with TextureQueue, which hands out ids on other threads
+    next_id: Arc<AtomicU64>,
+}
+
+impl UserTextureRegistry {
+    // Reserve a TextureId::User id, also used by textures that are not registered here.
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn find(&self, image_view: vk::ImageView, sampler: vk::Sampler) -> Option<u64> {
+        self.ids.get(&(image_view, sampler)).copied()
+    }
+
+    // Register a pair that is registered or was released recently once more, returning its id.
+    fn acquire(&mut self, image_view: vk::ImageView, sampler: vk::Sampler) -> Option<u64> {
+        if let Some(id) = self.find(image_view, sampler) {
+            *self.ref_counts.entry(id).or_insert(0) += 1;
+            return Some(id);
+        }
+        let (id, descriptor_set, _) = self.released.remove(&(image_view, sampler))?;
+        self.insert_with_id(id, image_view, sampler, descriptor_set);
+        Some(id)
+    }
+
+    fn descriptor_set(&self, id: u64) -> Option<vk::DescriptorSet> {
+        self.descriptor_sets.get(&id).map(|&(_, descriptor_set)| descriptor_set)
+    }
+
+    fn insert(&mut self, image_view: vk::ImageView, sampler: vk::Sampler, descriptor_set: vk::DescriptorSet) -> u64 {
+        let id = self.next_id();
+        self.insert_with_id(id, image_view, sampler, descriptor_set);
+        id
+    }
+
+    // Register under a new id that `find` never returns, so it is not shared with other
+    // registrations of the pair.
+    fn insert_unshared(&mut self, image_view: vk::ImageView, sampler: vk::Sampler, descriptor_set: vk::DescriptorSet) -> u64 {
+        let id = self.next_id();
+        self.descriptor_sets.insert(id, ((image_view, sampler), descriptor_set));
+        self.ref_counts.insert(id, 1);
+        id
+    }
+
+    // Register under an id reserved with next_id. The first id of a (view, sampler) pair is the one `find` returns.
+    fn insert_with_id(&mut self, id: u64, image_view: vk::ImageView, sampler: vk::Sampler, descriptor_set: vk::DescriptorSet) {
+        self.descriptor_sets.insert(id, ((image_view, sampler), descriptor_set));
+        self.ids.entry((image_view, sampler)).or_insert(id);
+        *self.ref_counts.entry(id).or_insert(0) += 1;
+    }
+
+    // Remove `id` whatever its registrations. Returns the descriptor set to recycle.
+    fn remove(&mut self, id: u64) -> Option<vk::DescriptorSet> {
+        self.ref_counts.remove(&id);
+        let (key, descriptor_set) = self.descriptor_sets.remove(&id)?;
+        if self.ids.get(&key) == Some(&id) {
+            self.ids.remove(&key);
+        }
+        Some(descriptor_set)
+    }
+
+    // Drop one registration of `id`, at frame count `frame`. None if it is not registered.
+    fn release(&mut self, id: u64, frame: u64) -> Option<Release> {
+        let ref_count = self.ref_counts.get_mut(&id)?;
+        *ref_count -= 1;
+        if *ref_count > 0 {
+            return Some(Release::Registered);
+        }
+        let (key, descriptor_set) = self.descriptor_sets[&id];
+        if self.ids.get(&key) != Some(&id) || self.released.contains_key(&key) {
+            return self.remove(id).map(Release::Removed);
+        }
+        self.remove(id);
+        self.released.insert(key, (id, descriptor_set, frame));
+        Some(Release::Cached)
+    }
+
+    // Descriptor sets of the pairs released before frame count `frame`, which are forgotten.
+    fn evict(&mut self, frame: u64) -> Vec<vk::DescriptorSet> {
+        let mut evicted = vec![];
+        self.released.retain(|_, &mut (_, descriptor_set, released)| {
+            if released < frame {
+                evicted.push(descriptor_set);
+            }
+            released >= frame
+        });
+        evicted
+    }
+}
+
+// `passes` with the UVs of the meshes drawing user texture regions mapped into the UV rects of
+// the regions, or None if no mesh draws one.
+fn map_uv_rects(
+    passes: &[&[egui::ClippedPrimitive]],
+    uv_rects: &HashMap<u64, egui::Rect>,
+) -> Option<Vec<Vec<egui::ClippedPrimitive>>> {
+    let uv_rect = |clipped_primitive: &egui::ClippedPrimitive| match &clipped_primitive.primitive {
+        egui::epaint::Primitive::Mesh(egui::Mesh {
+            texture_id: egui::TextureId::User(id),
+            ..
+        }) => uv_rects.get(id).copied(),
+        _ => None,
+    };
+    if uv_rects.is_empty() || !passes.iter().flat_map(|pass| pass.iter()).any(|p| uv_rect(p).is_some()) {
+        return None;
+    }
+    let map = |clipped_primitive: &egui::ClippedPrimitive| {
+        let mut mapped = clipped_primitive.clone();
+        if let (Some(uv_rect), egui::epaint::Primitive::Mesh(mesh)) = (uv_rect(clipped_primitive), &mut mapped.primitive) {
+            for vertex in mesh.vertices.iter_mut() {
+                vertex.uv = uv_rect.min + vertex.uv.to_vec2() * uv_rect.size();
+            }
+        }
+        mapped
+    };
+    Some(passes.iter().map(|pass| pass.iter().map(map).collect()).collect())
+}
+
+// Accesses of images in `layout`.
+fn layout_access_mask(layout: vk::ImageLayout) -> vk::AccessFlags2 {
+    match layout {
+        vk::ImageLayout::UNDEFINED | vk::ImageLayout::PREINITIALIZED | vk::ImageLayout::PRESENT_SRC_KHR => {
+            vk::AccessFlags2::NONE
+        }
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => {
+            vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+        }
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => vk::AccessFlags2::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => vk::AccessFlags2::TRANSFER_WRITE,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL | vk::ImageLayout::READ_ONLY_OPTIMAL => {
+            vk::AccessFlags2::SHADER_READ
+        }
+        _ => vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+    }
+}
+
+// Barrier of Renderer::transition_user_texture. The sampled side is egui's fragment shader,
+// the other the application's `stage_mask`; only writes are made available.
+fn user_texture_barrier(
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    stage_mask: vk::PipelineStageFlags2,
+) -> ImageMemoryBarrier2 {
+    let stage = |layout| match layout {
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL | vk::ImageLayout::READ_ONLY_OPTIMAL => {
+            vk::PipelineStageFlags2::FRAGMENT_SHADER
+        }
+        _ => stage_mask,
+    };
+    let writes = vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+        | vk::AccessFlags2::TRANSFER_WRITE
+        | vk::AccessFlags2::SHADER_WRITE
+        | vk::AccessFlags2::MEMORY_WRITE;
+    ImageMemoryBarrier2::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_stage_mask(stage(old_layout))
+        .src_access_mask(layout_access_mask(old_layout) & writes)
+        .dst_stage_mask(stage(new_layout))
+        .dst_access_mask(layout_access_mask(new_layout))
+        .build()
+}
+
+// Barrier transferring the ownership of `image` from `src_queue_family` to `dst_queue_family`
+// without changing its layout. The same barrier has to be recorded on both queues: as release
+// on the source queue (`acquire == false`) and as acquire on the destination queue, each with
+// only its own side of the dependency. None if no transfer is needed.
+#[allow(clippy::too_many_arguments)]
+fn queue_family_transfer_barrier(
+    image: vk::Image,
+    subresource_range: vk::ImageSubresourceRange,
+    layout: vk::ImageLayout,
+    src_queue_family: u32,
+    dst_queue_family: u32,
+    acquire: bool,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+) -> Option<ImageMemoryBarrier2> {
+    if src_queue_family == dst_queue_family {
+        return None;
+    }
+    let barrier = ImageMemoryBarrier2::builder()
+        .image(image)
+        .subresource_range(subresource_range)
+        .old_layout(layout)
+        .new_layout(layout)
+        .src_queue_family_index(src_queue_family)
+        .dst_queue_family_index(dst_queue_family);
+    let barrier = if acquire {
+        barrier.dst_stage_mask(stage_mask).dst_access_mask(access_mask)
+    } else {
+        barrier.src_stage_mask(stage_mask).src_access_mask(access_mask)
+    };
+    Some(barrier.build())
+}
+
+// Push constants of the egui shaders.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PushConstants {
+    transform: [[f32; 4]; 4],
+    screen_size: [f32; 2],
+    // gamma, brightness
+    calibration: [f32; 2],
+    // min x, min y, max x, max y in points
+    clip_rect: [f32; 4],
+    // linear, premultiplied
+    tint: [f32; 4],
+}
+
+const CLIP_RECT_OFFSET: u32 = 80;
+
+/// Offset of the push constants of [`IntegrationOptions::custom_push_constants`], right after
+/// the ones of egui.
+pub const CUSTOM_PUSH_CONSTANTS_OFFSET: u32 = std::mem::size_of::<PushConstants>() as u32;
+
+// Push constant range of the egui pipeline layout with `options`.
+fn push_constant_range(options: &IntegrationOptions) -> vk::PushConstantRange {
+    vk::PushConstantRange::builder()
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .offset(0)
+        .size(CUSTOM_PUSH_CONSTANTS_OFFSET + options.custom_push_constants)
+        .build()
+}
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// Check that the buffers written through a mapping are put in host visible memory.
+fn validate_memory_locations(memory_locations: &MemoryLocations) {
+    for (kind, location) in [
+        ("Vertex buffers", memory_locations.vertex),
+        ("Index buffers", memory_locations.index),
+        ("Staging buffers", memory_locations.staging),
+    ] {
+        assert_ne!(location, MemoryLocation::GpuOnly, "{} must be host visible.", kind);
+    }
+}
+
+// Check a render target configuration before any resources are recreated for it.
+fn validate_render_targets(physical_width: u32, physical_height: u32, image_count: usize, format: vk::Format) {
+    assert!(
+        physical_width > 0 && physical_height > 0,
+        "Render targets must not be empty: {}x{}",
+        physical_width,
+        physical_height
+    );
+    assert!(image_count > 0, "No render target images.");
+    assert_ne!(format, vk::Format::UNDEFINED, "Render target format is undefined.");
+}
+
+/// The target [`Integration::paint`] rendered into, passed to the post-paint hook.
+///
+/// When the hook runs the egui render pass has ended and the target layer is in its final
+/// layout (`PRESENT_SRC_KHR` unless set with [`IntegrationOptions::target_layouts`]), with
+/// the color attachment writes of the pass complete at `COLOR_ATTACHMENT_OUTPUT`. The hook
+/// must leave it in that layout.
+#[derive(Debug, Clone, Copy)]
+pub struct PaintTarget {
+    /// Command buffer `paint` recorded into.
+    pub command_buffer: vk::CommandBuffer,
+    /// Index of the swapchain image (or render target image) passed to `paint`.
+    pub image_index: usize,
+    /// Image rendered into.
+    pub image: vk::Image,
+    /// View of the rendered layer of `image`.
+    pub image_view: vk::ImageView,
+    /// Array layer of `image` rendered into.
+    pub layer: u32,
+    /// Format of `image`.
+    pub format: vk::Format,
+    /// Size of the target in pixels.
+    pub extent: vk::Extent2D,
+}
+
+/// Depth buffer the UI writes into, set with [`Renderer::set_depth_attachment`].
+///
+/// Every pass of the UI writes a constant depth, so 3D scene elements rendered against the
+/// same depth buffer can appear in front of some UI layers and behind others, e.g. for
+/// diegetic interfaces. Split the UI into passes per layer with
+/// [`Platform::end_frame_layered`](crate::Platform::end_frame_layered) and
+/// [`Renderer::paint_passes`].
+///
+/// With a [`StencilMask`] the UI is also only drawn where the stencil test passes.
+#[derive(Debug, Clone)]
+pub struct DepthAttachment {
+    /// One view per render target image, in `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` layout when
+    /// painting. The layout is kept and the contents are loaded and stored.
+    pub image_views: Vec<vk::ImageView>,
+    /// Format of the depth images.
+    pub format: vk::Format,
+    /// Depth test of the UI; `ALWAYS` only writes depth.
+    pub compare_op: vk::CompareOp,
+    /// Depth in `0.0..=1.0` written by pass `i` of [`Renderer::paint_passes`], e.g. far for
+    /// the background panels and near for tooltips. Later passes, and [`Renderer::paint`],
+    /// use the last value.
+    pub pass_depths: Vec<f32>,
+    /// Stencil test of the UI. `format` must have a stencil aspect.
+    pub stencil: Option<StencilMask>,
+}
+
+impl DepthAttachment {
+    // Depth written by pass `pass` of a frame.
+    fn pass_depth(&self, pass: usize) -> f32 {
+        self.pass_depths[pass.min(self.pass_depths.len() - 1)]
+    }
+}
+
+/// Stencil test masking the UI, see [`DepthAttachment::stencil`].
+///
+/// The stencil buffer is prepared by the application, e.g. with the shape of a round
+/// smartwatch display or a cockpit cutout, and is not written by the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilMask {
+    /// Stencil test, e.g. `EQUAL` to only draw where the stencil buffer holds `reference`.
+    pub compare_op: vk::CompareOp,
+    /// Value the stencil buffer is compared with.
+    pub reference: u32,
+    /// Bits of the stencil buffer and `reference` that are compared.
+    pub compare_mask: u32,
+}
+
+// Stencil state of the UI pipelines, which never write stencil.
+fn stencil_op_state(stencil: Option<StencilMask>) -> vk::StencilOpState {
+    let (compare_op, reference, compare_mask) = match stencil {
+        Some(stencil) => (stencil.compare_op, stencil.reference, stencil.compare_mask),
+        None => (vk::CompareOp::ALWAYS, 0, 0),
+    };
+    vk::StencilOpState::builder()
+        .fail_op(vk::StencilOp::KEEP)
+        .pass_op(vk::StencilOp::KEEP)
+        .depth_fail_op(vk::StencilOp::KEEP)
+        .compare_op(compare_op)
+        .compare_mask(compare_mask)
+        .write_mask(0)
+        .reference(reference)
+        .build()
+}
+
+fn format_has_stencil(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::S8_UINT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// Gamma and brightness of the UI, set with [`Renderer::set_display_calibration`].
+///
+/// Applied to the unpremultiplied colors in the fragment shader as
+/// `brightness * color^(1 / gamma)`. The default leaves the UI unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayCalibration {
+    /// Gamma, greater than 0. Values above 1 brighten the mid tones.
+    pub gamma: f32,
+    /// Factor of the colors.
+    pub brightness: f32,
+}
+
+impl Default for DisplayCalibration {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            brightness: 1.0,
+        }
+    }
+}
+
+/// Who created a texture listed by [`Renderer::registered_textures`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureKind {
+    /// Uploaded from an egui [`TexturesDelta`], e.g. the font atlas.
+    Managed,
+    /// Created and owned by the renderer on request, e.g. streaming and array textures.
+    Owned,
+    /// An image view registered with [`Renderer::register_user_texture`]; its image belongs
+    /// to the application.
+    User,
+}
+
+/// A texture held by the [`Renderer`], listed by [`Renderer::registered_textures`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureInfo {
+    /// Id to draw the texture with.
+    pub id: egui::TextureId,
+    /// Who created the texture.
+    pub kind: TextureKind,
+    /// Size in texels, unknown for [`TextureKind::User`] textures not registered with
+    /// [`Renderer::register_texture`].
+    pub size: Option<(u32, u32)>,
+    /// Format of the image, unknown for [`TextureKind::User`] textures not registered with
+    /// [`Renderer::register_texture`].
+    pub format: Option<vk::Format>,
+    /// Device memory allocated for the texture, including staging buffers.
+    pub memory_bytes: u64,
+    /// [`Renderer::frame_count`] of the frame that last uploaded texels, if any.
+    pub last_update_frame: Option<u64>,
+    /// [`Renderer::frame_count`] of the frame that last drew the texture, if any.
+    pub last_draw_frame: Option<u64>,
+}
+
+/// Hook called by [`Integration::paint`] after the egui render pass.
+pub type PostPaintHook = dyn FnMut(&Device, &PaintTarget) + Send;
+
+/// Callback called by [`Renderer::paint_layered`] between egui layers, see
+/// [`Renderer::add_layer_callback`].
+pub type LayerCallback = dyn FnMut(&Device, &PaintTarget) + Send;
+
+/// The rendering half of [`Integration`]: records egui paint commands with ash.
+///
+/// Holds no window or input state, so it can be moved to a render thread while the
+/// [`Platform`] stays on the thread running the event loop. Frames can be handed over
+/// with a [`FrameQueue`].
+pub struct Renderer<A: AllocatorTrait> {
+    physical_width: u32,
+    physical_height: u32,
+    scale_factor: f64,
+
+    device: Device,
+    allocator: A,
+    swapchain_loader: Swapchain,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_update_template: vk::DescriptorUpdateTemplate,
+    descriptor_buffer: Option<DescriptorBuffer<A>>,
+    free_descriptor_sets: Vec<vk::DescriptorSet>,
+    // staging buffers of partial texture updates no frame in flight reads, with their sizes
+    free_staging_buffers: Vec<(u64, VkStagingBuffer<A>)>,
+    // staging buffers of the texture updates recorded since the last paint started
+    pending_staging_buffers: Vec<(u64, VkStagingBuffer<A>)>,
+    allocated_descriptor_sets: usize,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    // draws the font atlas with IntegrationOptions::subpixel_text
+    text_pipeline: Option<vk::Pipeline>,
+    // format the render pass and pipelines of the render target were created for, unless they
+    // depend on a depth attachment or the UI layer; a resize to the same format keeps them
+    target_pipelines_format: Option<vk::Format>,
+    // pipeline and text pipeline created on a thread with IntegrationOptions::background_pipeline_creation
+    pending_pipelines: Option<JoinHandle<(vk::Pipeline, Option<vk::Pipeline>)>>,
+    sampler: vk::Sampler,
+    // samplers created for SamplerOptions other than the default, which use `sampler`
+    user_samplers: Vec<(SamplerOptions, vk::Sampler)>,
+    render_pass: vk::RenderPass,
+    render_target_images: Vec<vk::Image>,
+    render_target_format: vk::Format,
+    render_target_layer: u32,
+    framebuffer_color_image_views: Vec<vk::ImageView>,
+    framebuffers: Vec<vk::Framebuffer>,
+    // per frame in flight: the regular buffers followed by overflow buffers
+    mesh_buffers: Vec<Vec<MeshBuffers<A>>>,
+    // per frame in flight: device local buffers of tiny frames, created on first use
+    tiny_mesh_buffers: Vec<Option<MeshBuffers<A>>>,
+    // set by set_frame_index; frames follow the swapchain image index otherwise
+    frame_index: Option<usize>,
+    // the frame in flight painted last
+    last_frame_index: usize,
+    // (frame in flight, resource)
+    deferred_destruction: Vec<(usize, DeferredDestruction<A>)>,
+    
+    textures: HashMap<egui::TextureId, (VkTexture2D<A>, vk::DescriptorSet)>,
+    user_textures: UserTextureRegistry,
+    user_texture_views: HashMap<u64, vk::ImageView>,
+    // image, subresource range and queue family of user textures owned by another queue family
+    user_texture_owners: HashMap<u64, (vk::Image, vk::ImageSubresourceRange, u32)>,
+    // format and extent of user textures registered with register_texture
+    user_texture_metadata: HashMap<u64, (vk::Format, vk::Extent2D)>,
+    // images of user textures known to the integration, for transition_user_texture
+    user_texture_images: HashMap<u64, (vk::Image, vk::ImageSubresourceRange)>,
+    // UV rects of user textures registered with register_user_texture_region
+    user_texture_uv_rects: HashMap<u64, egui::Rect>,
+    render_target_queue_family: Option<u32>,
+    streaming_textures: HashMap<u64, StreamingTexture<A>>,
+    array_textures: Vec<ArrayTexture<A>>,
+    texture_queue_sender: Sender<QueuedRegistration>,
+    texture_queue_receiver: Receiver<QueuedRegistration>,
+    // managed textures of UiInstances, stored as user textures: (instance, managed id) -> user id
+    ui_instance_textures: HashMap<(u64, u64), u64>,
+    // keyed by TextureId::User id
+    offscreen_panels: HashMap<u64, OffscreenPanel<A>>,
+    // render pass and pipeline of the offscreen panels, created with the first panel
+    offscreen_panel_pipeline: Option<(vk::RenderPass, vk::Pipeline)>,
+    frame_queue_sender: Sender<QueuedFrame>,
+    frame_queue_receiver: Receiver<QueuedFrame>,
+    last_clipped_primitives: Vec<egui::ClippedPrimitive>,
+    frame_count: u64,
+    frame_stats: FrameStats,
+    texture_names: HashMap<egui::TextureId, String>,
+    // size egui sent for textures downscaled to IntegrationOptions::max_texture_dimension
+    downscaled_textures: HashMap<egui::TextureId, (u32, u32)>,
+    // set with Renderer::set_texture_sampler
+    texture_samplers: HashMap<egui::TextureId, SamplerOptions>,
+    last_draw_frames: HashMap<egui::TextureId, u64>,
+    // with IntegrationOptions::reuse_static_frames, null otherwise
+    command_pool: vk::CommandPool,
+    recorded_frames: Vec<RecordedFrame>,
+    // reused every frame instead of allocating
+    scratch_texels: Vec<u8>,
+    scratch_tiny_vertices: Vec<u8>,
+    scratch_tiny_indices: Vec<u8>,
+    scratch_composite_mesh: egui::Mesh,
+
+    #[cfg(feature = "software-renderer")]
+    software_renderer: Option<(SoftwareRenderer, Option<egui::TextureId>)>,
+
+    post_paint_hook: Option<Box<PostPaintHook>>,
+    // sorted by order
+    layer_callbacks: Vec<(egui::Order, Box<LayerCallback>)>,
+    error_hook: Option<Box<ErrorHook>>,
+
+    ui_layer: Option<UiLayer<A>>,
+    transform: Option<[[f32; 4]; 4]>,
+    ui_tint: egui::Color32,
+    display_calibration: DisplayCalibration,
+    // IntegrationOptions::custom_push_constants
+    custom_push_constants: Vec<u8>,
+    // the render targets are the swapchain images rather than application images
+    swapchain_targets: bool,
+    needs_swapchain_update: bool,
+    // texture updates of frames not painted because of NeedsSwapchainUpdate, and textures
+    // freed by update_textures
+    pending_textures_delta: TexturesDelta,
+    depth_attachment: Option<DepthAttachment>,
+    // keyed by ReadbackHandle
+    readbacks: HashMap<u64, PendingReadback<A>>,
+    next_readback: u64,
+    // with IntegrationOptions::queue_audit
+    queue_audit: Option<QueueAudit>,
+    slow_paths: SlowPathWarnings,
+    // of Renderer::paint_owned, created with its first frame; indexed by frame in flight
+    frame_command_pool: vk::CommandPool,
+    frame_command_buffers: Vec<vk::CommandBuffer>,
+
+    options: IntegrationOptions,
+}
+
+impl<A: AllocatorTrait> Renderer<A> {
+    /// Create a renderer drawing into the images of `swapchain`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: f64,
+        device: Device,
+        allocator: A,
+        swapchain_loader: Swapchain,
+        swapchain: vk::SwapchainKHR,
+        surface_format: vk::SurfaceFormatKHR,
+        options: IntegrationOptions,
+    ) -> Self {
+        // Get swap_images to get len of swapchain images and to create framebuffers
+        let swap_images = unsafe {
+            swapchain_loader
+                .get_swapchain_images(swapchain)
+                .expect("Failed to get swapchain images.")
+        };
+
+        // Create DescriptorPool, unless descriptors are placed in a descriptor buffer
+        let descriptor_pool = if options.descriptor_buffer.is_some() {
+            vk::DescriptorPool::null()
+        } else {
+            unsafe {
+                device.create_descriptor_pool(
+                    &vk::DescriptorPoolCreateInfo::builder()
+                        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+                        .max_sets(options.descriptor_pool.max_sets)
+                        .pool_sizes(&[vk::DescriptorPoolSize::builder()
+                            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .descriptor_count(options.descriptor_pool.combined_image_samplers)
+                            .build()]),
+                    None,
+                )
+            }
+            .expect("Failed to create descriptor pool.")
+        };
+
+        let (descriptor_set_layout_flags, pipeline_flags) = if options.descriptor_buffer.is_some() {
+            (
+                vk::DescriptorSetLayoutCreateFlags::DESCRIPTOR_BUFFER_EXT,
+                vk::PipelineCreateFlags::DESCRIPTOR_BUFFER_EXT,
+            )
+        } else {
+            Default::default()
+        };
+        validate_memory_locations(&options.memory_locations);
+        assert!(
+            options.custom_push_constants.is_multiple_of(4),
+            "The size of the custom push constants must be a multiple of 4."
+        );
+        let (_, texture_binding) = texture_descriptor(&options);
+        let descriptor_set_layout = match &options.descriptor_set_layout {
+            Some(layout_options) => {
+                assert!(
+                    (layout_options.set as usize) < layout_options.set_layouts.len(),
+                    "The texture set is not one of the set layouts."
+                );
+                layout_options.set_layouts[layout_options.set as usize]
+            }
+            None => unsafe {
+                device.create_descriptor_set_layout(
+                    &vk::DescriptorSetLayoutCreateInfo::builder().flags(descriptor_set_layout_flags).bindings(&[
+                        vk::DescriptorSetLayoutBinding::builder()
+                            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .descriptor_count(1)
+                            .binding(0)
+                            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                            .build(),
+                    ]),
+                    None,
+                )
+            }
+            .expect("Failed to create descriptor set layout."),
+        };
+
+        // Create RenderPass
+        let render_pass = Self::create_target_render_pass(&device, surface_format, &options, None);
+
+        // Create PipelineLayout
+        let descriptor_set_layouts = match &options.descriptor_set_layout {
+            Some(layout_options) => layout_options.set_layouts.clone(),
+            None => (0..swap_images.len()).map(|_| descriptor_set_layout).collect::<Vec<_>>(),
+        };
+        
+        let pipeline_layout = unsafe {
+            device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&descriptor_set_layouts)
+                    .push_constant_ranges(&[push_constant_range(&options)]),
+                None,
+            )
+        }
+        .expect("Failed to create pipeline layout.");
+
+        // Create Pipeline
+        let pipeline = Self::create_pipeline(
+            &device,
+            PipelineTarget::render_target(render_pass, surface_format.format, None),
+            pipeline_layout,
+            pipeline_flags,
+            ShaderVariant::new(options.premultiplied_alpha, surface_format.format),
+            color_blend_attachment_state(options.premultiplied_alpha),
+            None,
+            &Self::fragment_shader(&options, FRAG_SPV),
+        );
+
+        // Create Sampler
+        let sampler = unsafe {
+            device.create_sampler(
+                &vk::SamplerCreateInfo::builder()
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .anisotropy_enable(false)
+                    .min_filter(vk::Filter::LINEAR)
+                    .mag_filter(vk::Filter::LINEAR)
+                    .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+                    .min_lod(0.0)
+                    .max_lod(vk::LOD_CLAMP_NONE),
+                None,
+            )
+        }
+        .expect("Failed to create sampler.");
+
+        // Create Framebuffers
+        let (framebuffer_color_image_views, framebuffers) = Self::create_framebuffers(
+            &device,
+            &swap_images,
+            &[],
+            render_pass,
+            surface_format,
+            0,
+            physical_width,
+            physical_height,
+        );
+
+        // Create vertex buffer and index buffer
+        let mesh_buffers = (0..framebuffer_color_image_views.len())
+            .map(|_| {
+                vec![MeshBuffers::new(
+                    &device,
+                    &allocator,
+                    Self::vertex_buffer_size(),
+                    Self::index_buffer_size(),
+                    options.memory_locations.vertex,
+                    options.memory_locations.index,
+                )]
+            })
+            .collect();
+
+        // Create font image and anything related to it
+        // These values will be uploaded at rendering time
+        // let font_texture = VkTexture2D::<A>::new();
+        // let font_descriptor_sets = unsafe {
+        //     device.allocate_descriptor_sets(
+        //         &vk::DescriptorSetAllocateInfo::builder()
+        //             .descriptor_pool(descriptor_pool)
+        //             .set_layouts(&descriptor_set_layouts),
+        //     )
+        // }
+        // .expect("Failed to create descriptor sets.");
+
+        // User Textures
+        // let user_texture_layout = unsafe {
+        //     device.create_descriptor_set_layout(
+        //         &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+        //             vk::DescriptorSetLayoutBinding::builder()
+        //                 .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+        //                 .descriptor_count(1)
+        //                 .binding(0)
+        //                 .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        //                 .build(),
+        //         ]),
+        //         None,
+        //     )
+        // }
+        // .expect("Failed to create descriptor set layout.");
+        // let user_textures = vec![];
+
+        let descriptor_update_template = if options.descriptor_buffer.is_some() {
+            vk::DescriptorUpdateTemplate::null()
+        } else {
+            unsafe {
+                device.create_descriptor_update_template(
+                    &vk::DescriptorUpdateTemplateCreateInfo::builder()
+                        .template_type(vk::DescriptorUpdateTemplateType::DESCRIPTOR_SET)
+                        .descriptor_set_layout(descriptor_set_layout)
+                        .descriptor_update_entries(&[vk::DescriptorUpdateTemplateEntry::builder()
+                            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .descriptor_count(1)
+                            .dst_binding(texture_binding)
+                            .build()
+                        ]),
+                    None,
+                ).expect("Failed to create DescriptorUpdateTemplate")
+            }
+        };
+        let descriptor_buffer = options.descriptor_buffer.map(|descriptor_buffer_options| {
+            DescriptorBuffer::new(
+                &device,
+                &allocator,
+                descriptor_set_layout,
+                texture_binding,
+                descriptor_buffer_options,
+                options.descriptor_pool.max_sets,
+            )
+        });
+
+        let (texture_queue_sender, texture_queue_receiver) = mpsc::channel();
+        let (frame_queue_sender, frame_queue_receiver) = mpsc::channel();
+
+        let command_pool = if options.reuse_static_frames {
+            unsafe {
+                device.create_command_pool(
+                    &vk::CommandPoolCreateInfo::builder()
+                        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                        .queue_family_index(options.queue_family_index),
+                    None,
+                )
+            }
+            .expect("Failed to create command pool.")
+        } else {
+            vk::CommandPool::null()
+        };
+
+        let mut renderer = Self {
+            physical_width,
+            physical_height,
+            scale_factor,
+
+            device,
+            allocator,
+            swapchain_loader,
+            descriptor_pool,
+            descriptor_set_layout,
+            descriptor_update_template,
+            descriptor_buffer,
+            free_descriptor_sets: Default::default(),
+            free_staging_buffers: vec![],
+            pending_staging_buffers: vec![],
+            allocated_descriptor_sets: 0,
+            pipeline_layout,
+            pipeline,
+            text_pipeline: None,
+            sampler,
+            user_samplers: vec![],
+            render_pass,
+            render_target_images: swap_images,
+            render_target_format: surface_format.format,
+            render_target_layer: 0,
+            framebuffer_color_image_views,
+            framebuffers,
+            mesh_buffers,
+            tiny_mesh_buffers: vec![],
+            
+            textures: Default::default(),
+            user_textures: Default::default(),
+            user_texture_views: Default::default(),
+            user_texture_owners: Default::default(),
+            user_texture_metadata: Default::default(),
+            user_texture_images: Default::default(),
+            user_texture_uv_rects: Default::default(),
+            render_target_queue_family: None,
+            streaming_textures: Default::default(),
+            array_textures: Default::default(),
+            texture_queue_sender,
+            texture_queue_receiver,
+            ui_instance_textures: Default::default(),
+            offscreen_panels: Default::default(),
+            offscreen_panel_pipeline: None,
+            frame_index: None,
+            last_frame_index: 0,
+            deferred_destruction: vec![],
+            frame_queue_sender,
+            frame_queue_receiver,
+            last_clipped_primitives: vec![],
+            frame_count: 0,
+            frame_stats: Default::default(),
+            texture_names: Default::default(),
+            downscaled_textures: Default::default(),
+            texture_samplers: Default::default(),
+            last_draw_frames: Default::default(),
+            command_pool,
+            recorded_frames: vec![],
+            scratch_texels: vec![],
+            scratch_tiny_vertices: vec![],
+            scratch_tiny_indices: vec![],
+            scratch_composite_mesh: Default::default(),
+
+            #[cfg(feature = "software-renderer")]
+            software_renderer: options
+                .software_renderer
+                .then(|| (SoftwareRenderer::new(options.premultiplied_alpha), None)),
+
+            post_paint_hook: None,
+            layer_callbacks: vec![],
+            error_hook: None,
+
+            ui_layer: None,
+            target_pipelines_format: options.ui_layer.is_none().then_some(surface_format.format),
+            pending_pipelines: None,
+            transform: None,
+            ui_tint: egui::Color32::WHITE,
+            display_calibration: Default::default(),
+            custom_push_constants: vec![0; options.custom_push_constants as usize],
+            swapchain_targets: true,
+            needs_swapchain_update: false,
+            pending_textures_delta: Default::default(),
+            depth_attachment: None,
+            readbacks: HashMap::new(),
+            next_readback: 0,
+            queue_audit: options.queue_audit.then(QueueAudit::default),
+            slow_paths: Default::default(),
+            frame_command_pool: vk::CommandPool::null(),
+            frame_command_buffers: vec![],
+
+            options,
+        };
+        if renderer.options.subpixel_text && renderer.options.ui_layer.is_some() {
+            log::warn!("Subpixel text cannot be composited from the UI layer; text is drawn with grayscale antialiasing.");
+        }
+        renderer.text_pipeline = renderer.create_text_pipeline();
+        renderer.create_ui_layer();
+        renderer
+    }
+
+    // vertex buffer size
+    fn vertex_buffer_size() -> u64 {
+        1024 * 1024 * 4
+    }
+
+    // index buffer size
+    fn index_buffer_size() -> u64 {
+        1024 * 1024 * 2
+    }
+
+    fn create_render_pass(
+        device: &Device,
+        format: vk::Format,
+        load_op: vk::AttachmentLoadOp,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+        depth_format: Option<vk::Format>,
+    ) -> vk::RenderPass {
+        let (mut access_mask, mut stage_mask) = (
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        );
+        if depth_format.is_some() {
+            access_mask |= vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
+            stage_mask |= vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+        }
+        let (src_access_mask, mut src_stage_mask) = match initial_layout {
+            vk::ImageLayout::UNDEFINED
+            | vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            | vk::ImageLayout::PRESENT_SRC_KHR => (access_mask, stage_mask),
+            // written by other kinds of commands, e.g. a compute pass or a copy
+            _ => (vk::AccessFlags::MEMORY_WRITE, vk::PipelineStageFlags::ALL_COMMANDS),
+        };
+        if final_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL {
+            // an earlier frame may still sample the attachment
+            src_stage_mask |= vk::PipelineStageFlags::FRAGMENT_SHADER;
+        }
+        let mut dependencies = vec![vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(access_mask)
+            .src_stage_mask(src_stage_mask)
+            .dst_stage_mask(stage_mask)
+            .build()];
+        if final_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL {
+            // the attachment is sampled afterwards
+            dependencies.push(
+                vk::SubpassDependency::builder()
+                    .src_subpass(0)
+                    .dst_subpass(vk::SUBPASS_EXTERNAL)
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                    .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                    .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                    .build(),
+            );
+        } else if final_layout != vk::ImageLayout::PRESENT_SRC_KHR {
+            // more passes of the application follow
+            dependencies.push(
+                vk::SubpassDependency::builder()
+                    .src_subpass(0)
+                    .dst_subpass(vk::SUBPASS_EXTERNAL)
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                    .dst_stage_mask(vk::PipelineStageFlags::ALL_COMMANDS)
+                    .build(),
+            );
+        }
+        let mut attachments = vec![vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(load_op)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(initial_layout)
+            .final_layout(final_layout)
+            .build()];
+        let depth_attachment_reference = vk::AttachmentReference::builder()
+            .attachment(1)
+            .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build();
+        let color_attachment_references = [vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+        let mut subpass = vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_references);
+        if let Some(depth_format) = depth_format {
+            // the depth buffer belongs to the application, so it is kept as it is
+            attachments.push(
+                vk::AttachmentDescription::builder()
+                    .format(depth_format)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::LOAD)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::LOAD)
+                    .stencil_store_op(vk::AttachmentStoreOp::STORE)
+                    .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                    .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                    .build(),
+            );
+            subpass = subpass.depth_stencil_attachment(&depth_attachment_reference);
+        }
+        unsafe {
+            device.create_render_pass(
+                &vk::RenderPassCreateInfo::builder()
+                    .attachments(&attachments)
+                    .subpasses(&[subpass.build()])
+                    .dependencies(&dependencies),
+                None,
+            )
+        }
+        .expect("Failed to create render pass.")
+    }
+
+    // Render pass drawing over the render target, null with
+    // IntegrationOptions::dynamic_rendering.
+    fn create_target_render_pass(
+        device: &Device,
+        surface_format: vk::SurfaceFormatKHR,
+        options: &IntegrationOptions,
+        depth_format: Option<vk::Format>,
+    ) -> vk::RenderPass {
+        if options.dynamic_rendering {
+            return vk::RenderPass::null();
+        }
+        Self::create_render_pass(
+            device,
+            surface_format.format,
+            vk::AttachmentLoadOp::LOAD,
+            target_initial_layout(options),
+            target_final_layout(options),
+            depth_format,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_pipeline(
+        device: &Device,
+        target: PipelineTarget,
+        pipeline_layout: vk::PipelineLayout,
+        flags: vk::PipelineCreateFlags,
+        variant: ShaderVariant,
+        color_blend_attachment: vk::PipelineColorBlendAttachmentState,
+        depth_attachment: Option<&DepthAttachment>,
+        fragment_shader: &[u8],
+    ) -> vk::Pipeline {
+        let bindings = [vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .stride(
+                4 * std::mem::size_of::<f32>() as u32 + 4 * std::mem::size_of::<u8>() as u32,
+            )
+            .build()];
+
+        let attributes = [
+            // position
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .offset(0)
+                .location(0)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            // uv
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .offset(8)
+                .location(1)
+                .format(vk::Format::R32G32_SFLOAT)
+                .build(),
+            // color
+            vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .offset(16)
+                .location(2)
+                .format(vk::Format::R8G8B8A8_UNORM)
+                .build(),
+        ];
+
+        let vertex_shader_module = {
+            let bytes_code = VERT_SPV;
+            let shader_module_create_info = vk::ShaderModuleCreateInfo {
+                code_size: bytes_code.len(),
+                p_code: bytes_code.as_ptr() as *const u32,
+                ..Default::default()
+            };
+            unsafe { device.create_shader_module(&shader_module_create_info, None) }
+                .expect("Failed to create vertex shader module.")
+        };
+        let fragment_shader_module = {
+            let bytes_code = fragment_shader;
+            let shader_module_create_info = vk::ShaderModuleCreateInfo {
+                code_size: bytes_code.len(),
+                p_code: bytes_code.as_ptr() as *const u32,
+                ..Default::default()
+            };
+            unsafe { device.create_shader_module(&shader_module_create_info, None) }
+                .expect("Failed to create fragment shader module.")
+        };
+        let main_function_name = CString::new("main").unwrap();
+        // Specialization constants shared by both shader stages
+        let specialization_data = [variant.premultiplied_alpha as u32, variant.encode_srgb as u32];
+        let specialization_entries = [0, 1].map(|constant_id| {
+            vk::SpecializationMapEntry::builder()
+                .constant_id(constant_id)
+                .offset(constant_id * std::mem::size_of::<u32>() as u32)
+                .size(std::mem::size_of::<u32>())
+                .build()
+        });
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&specialization_entries)
+            .data(bytemuck::cast_slice(&specialization_data));
+        let pipeline_shader_stages = [
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vertex_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+            vk::PipelineShaderStageCreateInfo::builder()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(fragment_shader_module)
+                .name(&main_function_name)
+                .specialization_info(&specialization_info)
+                .build(),
+        ];
+
+        let input_assembly_info = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport_info = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterization_info = vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .depth_bias_enable(false)
+            .line_width(1.0);
+        let depth_compare_op = depth_attachment.map(|depth_attachment| depth_attachment.compare_op);
+        let stencil = depth_attachment.and_then(|depth_attachment| depth_attachment.stencil);
+        let stencil_op = stencil_op_state(stencil);
+        let depth_stencil_info = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(depth_compare_op.is_some())
+            .depth_write_enable(depth_compare_op.is_some())
+            .depth_compare_op(depth_compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(stencil.is_some())
+            .front(stencil_op)
+            .back(stencil_op);
+        let color_blend_attachments = [color_blend_attachment];
+        let color_blend_info = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_attachments);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state_info =
+            vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&attributes)
+            .vertex_binding_descriptions(&bindings);
+        let multisample_info = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        let (render_pass, color_attachment_formats, depth_format) = match target {
+            PipelineTarget::RenderPass(render_pass) => (render_pass, vec![], None),
+            PipelineTarget::Dynamic {
+                color_format,
+                depth_format,
+            } => (vk::RenderPass::null(), vec![color_format], depth_format),
+        };
+        let (depth_attachment_format, stencil_attachment_format) = depth_stencil_formats(depth_format);
+        let mut rendering_info = vk::PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(&color_attachment_formats)
+            .depth_attachment_format(depth_attachment_format)
+            .stencil_attachment_format(stencil_attachment_format);
+
+        let mut pipeline_create_info = vk::GraphicsPipelineCreateInfo::builder()
+            .flags(flags)
+            .stages(&pipeline_shader_stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_info)
+            .viewport_state(&viewport_info)
+            .rasterization_state(&rasterization_info)
+            .multisample_state(&multisample_info)
+            .depth_stencil_state(&depth_stencil_info)
+            .color_blend_state(&color_blend_info)
+            .dynamic_state(&dynamic_state_info)
+            .layout(pipeline_layout)
+            .render_pass(render_pass)
+            .subpass(0);
+        if render_pass == vk::RenderPass::null() {
+            pipeline_create_info = pipeline_create_info.push_next(&mut rendering_info);
+        }
+        let pipeline_create_info = [pipeline_create_info.build()];
+
+        let pipeline = unsafe {
+            device.create_graphics_pipelines(
+                vk::PipelineCache::null(),
+                &pipeline_create_info,
+                None,
+            )
+        }
+        .expect("Failed to create graphics pipeline.")[0];
+        unsafe {
+            device.destroy_shader_module(vertex_shader_module, None);
+            device.destroy_shader_module(fragment_shader_module, None);
+        }
+        pipeline
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_framebuffers(
+        device: &Device,
+        swap_images: &[vk::Image],
+        depth_image_views: &[vk::ImageView],
+        render_pass: vk::RenderPass,
+        surface_format: vk::SurfaceFormatKHR,
+        layer: u32,
+        physical_width: u32,
+        physical_height: u32,
+    ) -> (Vec<vk::ImageView>, Vec<vk::Framebuffer>) {
+        let framebuffer_color_image_views = swap_images
+            .iter()
+            .map(|swapchain_image| unsafe {
+                device
+                    .create_image_view(
+                        &vk::ImageViewCreateInfo::builder()
+                            .image(*swapchain_image)
+                            .view_type(vk::ImageViewType::TYPE_2D)
+                            .format(surface_format.format)
+                            .subresource_range(
+                                vk::ImageSubresourceRange::builder()
+                                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                    .base_mip_level(0)
+                                    .level_count(1)
+                                    .base_array_layer(layer)
+                                    .layer_count(1)
+                                    .build(),
+                            ),
+                        None,
+                    )
+                    .expect("Failed to create image view.")
+            })
+            .collect::<Vec<_>>();
+        // dynamic rendering draws into the image views
+        if render_pass == vk::RenderPass::null() {
+            return (framebuffer_color_image_views, vec![]);
+        }
+        let framebuffers = framebuffer_color_image_views
+            .iter()
+            .enumerate()
+            .map(|(i, &image_view)| unsafe {
+                let mut attachments = vec![image_view];
+                attachments.extend(depth_image_views.get(i));
+                device
+                    .create_framebuffer(
+                        &vk::FramebufferCreateInfo::builder()
+                            .render_pass(render_pass)
+                            .attachments(&attachments)
+                            .width(physical_width)
+                            .height(physical_height)
+                            .layers(1),
+                        None,
+                    )
+                    .expect("Failed to create framebuffer.")
+            })
+            .collect::<Vec<_>>();
+        (framebuffer_color_image_views, framebuffers)
+    }
+
+    /// Number of frames painted so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// What the last frame painted drew.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// The images the renderer paints into.
+    pub fn render_target_info(&self) -> RenderTargetInfo {
+        RenderTargetInfo {
+            extent: vk::Extent2D {
+                width: self.physical_width,
+                height: self.physical_height,
+            },
+            format: self.render_target_format,
+            image_count: self.render_target_images.len(),
+            scale_factor: self.scale_factor,
+            swapchain: self.swapchain_targets,
+            needs_swapchain_update: self.needs_swapchain_update,
+        }
+    }
+
+    /// The textures the renderer currently holds, for texture debuggers and leak hunting.
+    ///
+    /// Layers of array textures are listed separately, each with its share of the memory.
+    pub fn registered_textures(&self) -> impl Iterator<Item = TextureInfo> + '_ {
+        let owned_info = |id: egui::TextureId, texture: &VkTexture2D<A>, share: u64| TextureInfo {
+            id,
+            kind: match id {
+                egui::TextureId::Managed(_) => TextureKind::Managed,
+                egui::TextureId::User(_) => TextureKind::Owned,
+            },
+            size: Some((texture.size.0 as u32, texture.size.1 as u32)),
+            format: Some(texture.format),
+            memory_bytes: texture.memory_bytes() / share,
+            last_update_frame: texture.updated_frame,
+            last_draw_frame: self.last_draw_frame(id),
+        };
+
+        let textures = self
+            .textures
+            .iter()
+            .map(move |(&id, (texture, _))| owned_info(id, texture, 1));
+        let streaming_textures = self.streaming_textures.iter().map(move |(&id, streaming_texture)| {
+            let [(a, _), (b, _)] = &streaming_texture.slots;
+            TextureInfo {
+                memory_bytes: a.memory_bytes() + b.memory_bytes(),
+                last_update_frame: a.updated_frame.max(b.updated_frame),
+                ..owned_info(egui::TextureId::User(id), a, 1)
+            }
+        });
+        let array_textures = self.array_textures.iter().flat_map(move |array_texture| {
+            let layers = array_texture.ids.len() as u64;
+            array_texture
+                .ids
+                .iter()
+                .map(move |&id| owned_info(egui::TextureId::User(id), &array_texture.texture, layers))
+        });
+        let user_textures = self
+            .user_textures
+            .descriptor_sets
+            .keys()
+            .filter(move |&&id| self.find_array_texture(egui::TextureId::User(id)).is_none())
+            .map(move |&id| {
+                let metadata = self.user_texture_metadata.get(&id);
+                TextureInfo {
+                    id: egui::TextureId::User(id),
+                    kind: TextureKind::User,
+                    size: metadata.map(|(_, extent)| (extent.width, extent.height)),
+                    format: metadata.map(|&(format, _)| format),
+                    memory_bytes: 0,
+                    last_update_frame: None,
+                    last_draw_frame: self.last_draw_frame(egui::TextureId::User(id)),
+                }
+            });
+
+        textures
+            .chain(streaming_textures)
+            .chain(array_textures)
+            .chain(user_textures)
+    }
+
+    /// [`frame_count`](Self::frame_count) of the frame that last drew `texture_id`, if any.
+    ///
+    /// Lets caches of e.g. thumbnails evict the textures egui has not drawn recently.
+    pub fn last_draw_frame(&self, texture_id: egui::TextureId) -> Option<u64> {
+        self.last_draw_frames.get(&texture_id).copied()
+    }
+
+    /// Name a texture for the leak report of [`destroy`](Self::destroy) and texture debuggers.
+    pub fn set_texture_name(&mut self, texture_id: egui::TextureId, name: &str) {
+        self.texture_names.insert(texture_id, name.to_owned());
+    }
+
+    /// Name given to a texture with [`set_texture_name`](Self::set_texture_name).
+    pub fn texture_name(&self, texture_id: egui::TextureId) -> Option<&str> {
+        self.texture_names.get(&texture_id).map(String::as_str)
+    }
+
+    /// Set the scale factor used to convert points to pixels, e.g. on `ScaleFactorChanged`.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Render the UI with a custom transform instead of covering the render target.
+    ///
+    /// The UI is laid out on the `z = 0` plane from (-1, -1) at its top left corner to
+    /// (1, 1) at its bottom right corner, and `transform` (column-major) maps that plane to
+    /// clip space, e.g. the model-view-projection matrix of a quad for in-world terminals or
+    /// VR menus. Use [`ui_pos_from_ray`] to map pointer rays to UI coordinates.
+    /// `None` restores the screen mapping.
+    pub fn set_transform(&mut self, transform: Option<[[f32; 4]; 4]>) {
+        self.transform = transform;
+    }
+
+    /// Multiply the colors of the whole UI by `tint` (premultiplied sRGB), e.g. to fade it in
+    /// and out or flash it without tessellating again.
+    ///
+    /// Applies to the frames painted afterwards until it is set again; `Color32::WHITE`
+    /// draws the UI as it is.
+    pub fn set_ui_tint(&mut self, tint: egui::Color32) {
+        self.ui_tint = tint;
+    }
+
+    /// The tint set with [`set_ui_tint`](Self::set_ui_tint).
+    pub fn ui_tint(&self) -> egui::Color32 {
+        self.ui_tint
+    }
+
+    /// Adjust the gamma and brightness of the UI, e.g. from the display calibration screen
+    /// of a game. Applies to the frames painted afterwards.
+    pub fn set_display_calibration(&mut self, display_calibration: DisplayCalibration) {
+        assert!(display_calibration.gamma > 0.0, "The gamma must be positive.");
+        self.display_calibration = display_calibration;
+    }
+
+    /// The calibration set with [`set_display_calibration`](Self::set_display_calibration).
+    pub fn display_calibration(&self) -> DisplayCalibration {
+        self.display_calibration
+    }
+
+    /// Set the [custom push constants](IntegrationOptions::custom_push_constants), pushed at
+    /// [`CUSTOM_PUSH_CONSTANTS_OFFSET`] for the frames painted afterwards. They are zero until
+    /// set.
+    pub fn set_custom_push_constants(&mut self, data: &[u8]) {
+        assert_eq!(
+            data.len(),
+            self.custom_push_constants.len(),
+            "The data must have the size of the custom push constants."
+        );
+        self.custom_push_constants.copy_from_slice(data);
+    }
+
+    /// The custom push constants set with
+    /// [`set_custom_push_constants`](Self::set_custom_push_constants).
+    pub fn custom_push_constants(&self) -> &[u8] {
+        &self.custom_push_constants
+    }
+
+    /// Get a [`FrameQueue`] for sending frames to this renderer from another thread.
+    pub fn frame_queue(&self) -> FrameQueue {
+        FrameQueue {
+            sender: self.frame_queue_sender.clone(),
+        }
+    }
+
+    /// Record paint commands for the frames sent through [`FrameQueue`]s.
+    ///
+    /// The texture updates of all frames queued since the last call are applied in order
+    /// and the latest frame is drawn. If no frame was queued, the previous one is drawn again.
+    pub fn paint_queued(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        let mut textures_delta = TexturesDelta::default();
+        for frame in self.frame_queue_receiver.try_iter() {
+            textures_delta.append(frame.textures_delta);
+            self.last_clipped_primitives = frame.clipped_primitives;
+        }
+        let clipped_primitives = std::mem::take(&mut self.last_clipped_primitives);
+        let result = self.paint_frame(
+            command_buffer,
+            swapchain_image_index,
+            textures_delta,
+            &[&clipped_primitives],
+            self.scale_factor as f32,
+            |_, _, _| {},
+        );
+        self.last_clipped_primitives = clipped_primitives;
+        result
+    }
+
+    /// Record paint commands for a frame of a [`UiInstance`].
+    ///
+    /// Like [`paint`](Self::paint), but at the instance's pixels per point and with the
+    /// instance's textures, which are stored separately from those of other contexts.
+    pub fn paint_ui_instance(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        ui_instance: &UiInstance,
+        textures_delta: TexturesDelta,
+        mut clipped_meshes: Vec<egui::ClippedPrimitive>,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        let textures_delta = self.translate_ui_instance_textures(ui_instance, textures_delta, &mut clipped_meshes);
+        self.paint_frame(
+            command_buffer,
+            swapchain_image_index,
+            textures_delta,
+            &[&clipped_meshes],
+            ui_instance.pixels_per_point(),
+            |_, _, _| {},
+        )
+    }
+
+    // Every context numbers its managed textures from 0, so the textures of `ui_instance` are
+    // renamed to user ids.
+    fn translate_ui_instance_textures(
+        &mut self,
+        ui_instance: &UiInstance,
+        textures_delta: TexturesDelta,
+        clipped_meshes: &mut [egui::ClippedPrimitive],
+    ) -> TexturesDelta {
+        let mut translate = |texture_id: egui::TextureId| match texture_id {
+            egui::TextureId::Managed(id) => egui::TextureId::User(
+                *self
+                    .ui_instance_textures
+                    .entry((ui_instance.id, id))
+                    .or_insert_with(|| self.user_textures.next_id()),
+            ),
+            egui::TextureId::User(_) => texture_id,
+        };
+        let textures_delta = TexturesDelta {
+            set: textures_delta
+                .set
+                .into_iter()
+                .map(|(id, image_delta)| (translate(id), image_delta))
+                .collect(),
+            free: textures_delta.free.iter().map(|&id| translate(id)).collect(),
+        };
+        for clipped_mesh in clipped_meshes.iter_mut() {
+            if let egui::epaint::Primitive::Mesh(mesh) = &mut clipped_mesh.primitive {
+                mesh.texture_id = translate(mesh.texture_id);
+            }
+        }
+        self.ui_instance_textures
+            .retain(|_, id| !textures_delta.free.contains(&egui::TextureId::User(*id)));
+        textures_delta
+    }
+
+    /// Destroy the textures of a [`UiInstance`] that is no longer painted.
+    pub fn release_ui_instance(&mut self, ui_instance: &UiInstance) {
+        let ids = self
+            .ui_instance_textures
+            .iter()
+            .filter(|&(&(instance, _), _)| instance == ui_instance.id)
+            .map(|(_, &id)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.unregister_user_texture(egui::TextureId::User(id));
+        }
+        self.ui_instance_textures
+            .retain(|&(instance, _), _| instance != ui_instance.id);
+        let panels = self
+            .offscreen_panels
+            .iter()
+            .filter(|(_, panel)| panel.ui_instance == ui_instance.id)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in panels {
+            let panel = self.offscreen_panels.remove(&id).unwrap();
+            self.defer_destruction(DeferredDestruction::OffscreenPanel(panel));
+        }
+    }
+
+    /// Run a frame of `ui_instance` and paint it into an offscreen panel of `physical_width` x
+    /// `physical_height` pixels, e.g. a picture-in-picture view or a live preview of another
+    /// screen. Returns the TextureId to show the panel with in another UI.
+    ///
+    /// `run_ui` builds the frame; its platform output is dropped. The panel is recorded into
+    /// `command_buffer` outside of a render pass, so call this before [`paint`](Self::paint)
+    /// draws the UI showing it, with the same `swapchain_image_index`, and at most once per
+    /// frame for each instance. Like `paint`, it does not wait for the device: panels
+    /// replaced by a resize are destroyed once the frames drawing them have completed. The
+    /// TextureId stays the same while the instance is painted at the same size and until
+    /// [`release_ui_instance`](Self::release_ui_instance) destroys the panel.
+    pub fn paint_offscreen_panel(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        ui_instance: &mut UiInstance,
+        physical_width: u32,
+        physical_height: u32,
+        run_ui: impl FnOnce(&egui::Context),
+    ) -> egui::TextureId {
+        let pixels_per_point = ui_instance.pixels_per_point();
+        ui_instance.set_screen_size(physical_width, physical_height, pixels_per_point);
+        ui_instance.begin_frame();
+        run_ui(&ui_instance.context());
+        let (textures_delta, mut clipped_meshes) = ui_instance.end_frame_and_tessellate();
+        let textures_delta = self.translate_ui_instance_textures(ui_instance, textures_delta, &mut clipped_meshes);
+
+        let frame_index = self.frame_index.unwrap_or(swapchain_image_index);
+        let extent = vk::Extent2D {
+            width: physical_width.max(1),
+            height: physical_height.max(1),
+        };
+        let id = match self
+            .offscreen_panels
+            .iter()
+            .find(|(_, panel)| panel.ui_instance == ui_instance.id)
+            .map(|(&id, panel)| (id, panel.extent))
+        {
+            Some((id, panel_extent)) if panel_extent == extent => id,
+            resized => {
+                // the old panel may still be drawn by the frames in flight
+                if let Some((id, _)) = resized {
+                    let panel = self.offscreen_panels.remove(&id).unwrap();
+                    self.defer_destruction(DeferredDestruction::OffscreenPanel(panel));
+                }
+                let panel = self.create_offscreen_panel(ui_instance.id, extent);
+                let id = self.user_textures.next_id();
+                self.offscreen_panels.insert(id, panel);
+                id
+            }
+        };
+
+        for (texture_id, image_delta) in textures_delta.set {
+            self.update_texture(command_buffer, texture_id, &image_delta);
+        }
+        self.record_offscreen_panel(command_buffer, id, frame_index, pixels_per_point, &clipped_meshes);
+        for texture_id in textures_delta.free {
+            self.free_texture(texture_id);
+        }
+        egui::TextureId::User(id)
+    }
+
+    fn create_offscreen_panel(&mut self, ui_instance: u64, extent: vk::Extent2D) -> OffscreenPanel<A> {
+        let (render_pass, _) = self.offscreen_panel_pipeline();
+        let image = unsafe {
+            self.device.create_image(
+                &vk::ImageCreateInfo::builder()
+                    .flags(vk::ImageCreateFlags::MUTABLE_FORMAT)
+                    .format(vk::Format::R8G8B8A8_UNORM)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .extent(vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    }),
+                None,
+            )
+        }
+        .expect("Failed to create offscreen panel image.");
+        let allocation = self
+            .allocator
+            .allocate(image_allocation_create_info::<A>(&self.device, image, MemoryLocation::GpuOnly))
+            .expect("Failed to create offscreen panel image.");
+        unsafe {
+            self.device
+                .bind_image_memory(image, allocation.memory(), allocation.offset())
+                .expect("Failed to create offscreen panel image.");
+        }
+        let attachment_view = create_layer_view(&self.device, image, OFFSCREEN_PANEL_FORMAT, 0);
+        let sampled_view = create_layer_view(&self.device, image, vk::Format::R8G8B8A8_UNORM, 0);
+        let framebuffer = unsafe {
+            self.device.create_framebuffer(
+                &vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .attachments(&[attachment_view])
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1),
+                None,
+            )
+        }
+        .expect("Failed to create framebuffer.");
+        let descriptor_set = self.allocate_descriptor_set();
+        self.write_descriptor_set(descriptor_set, sampled_view, self.sampler);
+        OffscreenPanel {
+            ui_instance,
+            extent,
+            image,
+            allocation,
+            attachment_view,
+            sampled_view,
+            framebuffer,
+            descriptor_set,
+            mesh_buffers: vec![],
+        }
+    }
+
+    fn destroy_offscreen_panel(&mut self, panel: OffscreenPanel<A>) {
+        unsafe {
+            self.device.destroy_framebuffer(panel.framebuffer, None);
+            self.device.destroy_image_view(panel.attachment_view, None);
+            self.device.destroy_image_view(panel.sampled_view, None);
+            self.device.destroy_image(panel.image, None);
+        }
+        self.allocator
+            .free(panel.allocation)
+            .expect("Failed to free allocation");
+        for mesh_buffers in panel.mesh_buffers.into_iter().flatten() {
+            mesh_buffers.destroy(&self.device, &self.allocator);
+        }
+        self.free_descriptor_sets.push(panel.descriptor_set);
+    }
+
+    // Render pass and pipeline of the offscreen panels. Like the UI layer, panels keep
+    // coverage in alpha and only get grayscale text.
+    fn offscreen_panel_pipeline(&mut self) -> (vk::RenderPass, vk::Pipeline) {
+        if let Some(offscreen_panel_pipeline) = self.offscreen_panel_pipeline {
+            return offscreen_panel_pipeline;
+        }
+        let render_pass = Self::create_render_pass(
+            &self.device,
+            OFFSCREEN_PANEL_FORMAT,
+            vk::AttachmentLoadOp::CLEAR,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            None,
+        );
+        let pipeline = Self::create_pipeline(
+            &self.device,
+            PipelineTarget::RenderPass(render_pass),
+            self.pipeline_layout,
+            self.pipeline_create_flags(),
+            ShaderVariant::new(self.options.premultiplied_alpha, OFFSCREEN_PANEL_FORMAT),
+            color_blend_attachment_state(true),
+            None,
+            &Self::fragment_shader(&self.options, FRAG_SPV),
+        );
+        *self.offscreen_panel_pipeline.insert((render_pass, pipeline))
+    }
+
+    // Record the render pass drawing `clipped_meshes` into offscreen panel `id`.
+    fn record_offscreen_panel(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        id: u64,
+        frame_index: usize,
+        pixels_per_point: f32,
+        clipped_meshes: &[egui::ClippedPrimitive],
+    ) {
+        let meshes = || {
+            clipped_meshes.iter().filter_map(|clipped_primitive| match &clipped_primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) if !mesh.vertices.is_empty() && !mesh.indices.is_empty() => {
+                    Some((clipped_primitive.clip_rect, mesh))
+                }
+                _ => None,
+            })
+        };
+        let vertex_bytes = meshes().map(|(_, mesh)| std::mem::size_of_val(mesh.vertices.as_slice())).sum::<usize>();
+        let index_bytes = meshes().map(|(_, mesh)| std::mem::size_of_val(mesh.indices.as_slice())).sum::<usize>();
+
+        // all meshes are uploaded up front, with 32 bit indices, into the buffers of the frame,
+        // which the previous frame painted with the same frame index no longer reads
+        let (device, allocator, memory_locations) = (&self.device, &self.allocator, self.options.memory_locations);
+        let panel = self.offscreen_panels.get_mut(&id).unwrap();
+        let (extent, framebuffer) = (panel.extent, panel.framebuffer);
+        if panel.mesh_buffers.len() <= frame_index {
+            panel.mesh_buffers.resize_with(frame_index + 1, || None);
+        }
+        if let Some(mesh_buffers) = panel.mesh_buffers[frame_index].take() {
+            if mesh_buffers.vertex_capacity >= vertex_bytes && mesh_buffers.index_capacity >= index_bytes {
+                panel.mesh_buffers[frame_index] = Some(mesh_buffers);
+            } else {
+                mesh_buffers.destroy(device, allocator);
+            }
+        }
+        let buffers = panel.mesh_buffers[frame_index].get_or_insert_with(|| {
+            MeshBuffers::new(
+                device,
+                allocator,
+                vertex_bytes.next_power_of_two().max(TINY_FRAME_BYTES) as u64,
+                index_bytes.next_power_of_two().max(TINY_FRAME_BYTES) as u64,
+                memory_locations.vertex,
+                memory_locations.index,
+            )
+        });
+        let (mut vertex_offset, mut index_offset) = (0, 0);
+        unsafe {
+            let vertex_buffer_ptr = buffers.vertex_allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            let index_buffer_ptr = buffers.index_allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            for (_, mesh) in meshes() {
+                let vertices = bytemuck::cast_slice::<_, u8>(&mesh.vertices);
+                let indices = bytemuck::cast_slice::<_, u8>(&mesh.indices);
+                vertex_buffer_ptr.add(vertex_offset).copy_from(vertices.as_ptr(), vertices.len());
+                index_buffer_ptr.add(index_offset).copy_from(indices.as_ptr(), indices.len());
+                vertex_offset += vertices.len();
+                index_offset += indices.len();
+            }
+        }
+
+        let (render_pass, pipeline) = self.offscreen_panel_pipeline();
+        self.begin_render_pass(
+            command_buffer,
+            render_pass,
+            framebuffer,
+            vk::Rect2D::builder().extent(extent).build(),
+            vk::SubpassContents::INLINE,
+        );
+        let screen_size = [
+            extent.width as f32 / pixels_per_point,
+            extent.height as f32 / pixels_per_point,
+        ];
+        // the UI showing the panel is tinted and calibrated
+        self.bind_pipeline_state(command_buffer, extent, screen_size, pipeline, IDENTITY, [1.0; 4], [1.0; 2]);
+        self.bind_mesh_buffers(
+            command_buffer,
+            self.offscreen_panels[&id].mesh_buffers[frame_index].as_ref().unwrap(),
+            vk::IndexType::UINT32,
+        );
+        let mut bound_scissor = None;
+        let (mut first_index, mut vertex_offset) = (0, 0);
+        for (clip_rect, mesh) in meshes() {
+            let (mesh_first_index, mesh_vertex_offset) = (first_index, vertex_offset);
+            first_index += mesh.indices.len() as u32;
+            vertex_offset += mesh.vertices.len() as i32;
+            match self.mesh_descriptor_set(mesh.texture_id) {
+                Some(descriptor_set) => self.bind_descriptor_set(command_buffer, descriptor_set),
+                None => continue,
+            }
+            let scissor = scissor_rect(clip_rect, pixels_per_point, extent.width, extent.height);
+            unsafe {
+                if replace_scissor(&mut bound_scissor, scissor) {
+                    self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                }
+                self.device.cmd_draw_indexed(
+                    command_buffer,
+                    mesh.indices.len() as u32,
+                    1,
+                    mesh_first_index,
+                    mesh_vertex_offset,
+                    0,
+                );
+            }
+        }
+        unsafe {
+            self.device.cmd_end_render_pass(command_buffer);
+        }
+    }
+
+    /// Record paint commands.
+    ///
+    /// Does not wait for the device. The previous frame painted with the same frame index,
+    /// the swapchain image index unless set with [`set_frame_index`](Self::set_frame_index),
+    /// must have completed, as its mesh buffers are written again; resources it released,
+    /// e.g. replaced textures and staging buffers of texture updates, are then reused or
+    /// destroyed.
+    ///
+    /// Fails with [`NeedsSwapchainUpdate`], recording nothing, when the swapchain has gone
+    /// out of date since the last [`update_swapchain`](Self::update_swapchain), see
+    /// [`mark_swapchain_out_of_date`](Self::mark_swapchain_out_of_date).
+    pub fn paint(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        clipped_meshes: Vec<egui::ClippedPrimitive>,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        self.paint_frame(
+            command_buffer,
+            swapchain_image_index,
+            textures_delta,
+            &[&clipped_meshes],
+            self.scale_factor as f32,
+            |_, _, _| {},
+        )
+    }
+
+    /// Like [`paint`](Self::paint), but records into a primary command buffer owned by the
+    /// integration and returns it, ready to submit.
+    ///
+    /// For simple applications that draw nothing else. Each frame in flight has its own
+    /// command buffer, reset when the frame is painted again, so the previous submission of
+    /// the frame must have completed, as with command buffers of the application. The render
+    /// target must be in the initial layout of [`IntegrationOptions::target_layouts`], e.g.
+    /// `UNDEFINED` when nothing is drawn before the UI. Nothing is to be submitted on error.
+    pub fn paint_owned(
+        &mut self,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        clipped_meshes: Vec<egui::ClippedPrimitive>,
+    ) -> Result<vk::CommandBuffer, NeedsSwapchainUpdate> {
+        let frame_index = self.frame_index.unwrap_or(swapchain_image_index);
+        let command_buffer = self.frame_command_buffer(frame_index);
+        unsafe {
+            self.device
+                .reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
+                .expect("Failed to reset command buffer.");
+            self.device
+                .begin_command_buffer(
+                    command_buffer,
+                    &vk::CommandBufferBeginInfo::builder().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .expect("Failed to begin command buffer.");
+        }
+        let result = self.paint(command_buffer, swapchain_image_index, textures_delta, clipped_meshes);
+        unsafe {
+            self.device
+                .end_command_buffer(command_buffer)
+                .expect("Failed to end command buffer.");
+        }
+        result.map(|()| command_buffer)
+    }
+
+    // Primary command buffer of frame in flight `frame_index` for paint_owned.
+    fn frame_command_buffer(&mut self, frame_index: usize) -> vk::CommandBuffer {
+        if self.frame_command_pool == vk::CommandPool::null() {
+            self.frame_command_pool = unsafe {
+                self.device.create_command_pool(
+                    &vk::CommandPoolCreateInfo::builder()
+                        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                        .queue_family_index(self.options.queue_family_index),
+                    None,
+                )
+            }
+            .expect("Failed to create command pool.");
+        }
+        while self.frame_command_buffers.len() <= frame_index {
+            let command_buffer = unsafe {
+                self.device.allocate_command_buffers(
+                    &vk::CommandBufferAllocateInfo::builder()
+                        .command_pool(self.frame_command_pool)
+                        .level(vk::CommandBufferLevel::PRIMARY)
+                        .command_buffer_count(1),
+                )
+            }
+            .expect("Failed to allocate command buffers.")[0];
+            self.frame_command_buffers.push(command_buffer);
+        }
+        self.frame_command_buffers[frame_index]
+    }
+
+    /// Record paint commands for primitives tessellated without an [`egui::Context`], e.g. from
+    /// `epaint` shapes generated by another immediate mode framework.
+    ///
+    /// `pixels_per_point` is the scale the primitives were tessellated with; the scale factor
+    /// of the renderer is left as is. Upload the textures they use, e.g. the font atlas delta of
+    /// [`egui::epaint::text::Fonts::font_image_delta`], with
+    /// [`update_textures`](Self::update_textures) beforehand. Callbacks are skipped and reported
+    /// as [`RuntimeError::UnsupportedCallback`].
+    pub fn paint_primitives(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        self.paint_frame(
+            command_buffer,
+            swapchain_image_index,
+            Default::default(),
+            &[clipped_primitives],
+            pixels_per_point,
+            |_, _, _| {},
+        )
+    }
+
+    /// Record paint commands for a frame split into several render passes, e.g. the layers
+    /// of [`LayeredShapes`] tessellated separately.
+    ///
+    /// `between_passes` is called with the index of the next pass before every pass but the
+    /// first, with the target in the state described for [`PaintTarget`]. Use it to e.g. blur
+    /// the background panels before the windows are drawn over them. With the software
+    /// renderer, all passes are painted in one.
+    pub fn paint_passes<F: FnMut(&Device, &PaintTarget, usize)>(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        passes: Vec<Vec<egui::ClippedPrimitive>>,
+        between_passes: F,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        let passes = passes.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let pixels_per_point = self.scale_factor as f32;
+        self.paint_frame(command_buffer, swapchain_image_index, textures_delta, &passes, pixels_per_point, between_passes)
+    }
+
+    /// Record paint commands for a frame split at the layer callbacks, calling each callback
+    /// between the groups of layers before and after its order.
+    ///
+    /// `groups` holds one more group than there are callbacks, e.g. the shapes of
+    /// [`LayeredShapes::split_before`] with [`layer_callback_orders`](Self::layer_callback_orders),
+    /// each tessellated separately. Callbacks are called even if the groups around them are
+    /// empty.
+    ///
+    /// # Panics
+    /// Panics if the number of groups does not match the callbacks.
+    pub fn paint_layered(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        groups: Vec<Vec<egui::ClippedPrimitive>>,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        assert_eq!(
+            groups.len(),
+            self.layer_callbacks.len() + 1,
+            "paint_layered takes one more group than there are layer callbacks."
+        );
+        let mut layer_callbacks = std::mem::take(&mut self.layer_callbacks);
+        let passes = groups.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let result = self.paint_frame(
+            command_buffer,
+            swapchain_image_index,
+            textures_delta,
+            &passes,
+            self.scale_factor as f32,
+            |device, paint_target, pass| (layer_callbacks[pass - 1].1)(device, paint_target),
+        );
+        self.layer_callbacks = layer_callbacks;
+        result
+    }
+
+    /// Record the texture updates of `textures_delta` ahead of the paint of the frame.
+    ///
+    /// Lets the uploads go into an earlier command buffer than the draws, e.g. one submitted
+    /// at the start of the frame. `command_buffer` must be submitted to a queue of
+    /// [`IntegrationOptions::queue_family_index`] before the command buffer of the paint,
+    /// which is then passed an empty [`TexturesDelta`]. The textures the delta frees are
+    /// destroyed after the next paint. With the `strict` feature, paint warns about meshes
+    /// using textures that were never uploaded.
+    pub fn update_textures(&mut self, command_buffer: vk::CommandBuffer, textures_delta: &TexturesDelta) {
+        for (id, image_delta) in &textures_delta.set {
+            self.update_texture(command_buffer, *id, image_delta);
+        }
+        // freed once the frame using them is painted, as with paint
+        self.pending_textures_delta.free.extend(&textures_delta.free);
+        if !textures_delta.set.is_empty() {
+            for recorded_frame in self.recorded_frames.iter_mut() {
+                recorded_frame.key = None;
+            }
+        }
+    }
+
+    /// The egui pass of a frame as a node of a frame graph, painted like
+    /// [`paint`](Self::paint) when recorded.
+    pub fn frame_graph_pass(
+        &mut self,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        clipped_primitives: Vec<egui::ClippedPrimitive>,
+    ) -> EguiPass<'_, A> {
+        EguiPass {
+            renderer: self,
+            swapchain_image_index,
+            textures_delta,
+            clipped_primitives,
+        }
+    }
+
+    /// The images painting `clipped_primitives` to `swapchain_image_index` samples and
+    /// renders into.
+    ///
+    /// Textures created by the [`TexturesDelta`] of the same frame are owned and uploaded by
+    /// the integration, so they are not reported.
+    pub fn pass_dependencies(
+        &self,
+        swapchain_image_index: usize,
+        clipped_primitives: &[egui::ClippedPrimitive],
+    ) -> PassDependencies {
+        let mut dependencies = PassDependencies::default();
+        let mut texture_ids = HashSet::new();
+        for clipped_primitive in clipped_primitives {
+            if let egui::epaint::Primitive::Mesh(mesh) = &clipped_primitive.primitive {
+                if !texture_ids.insert(mesh.texture_id) {
+                    continue;
+                }
+                if let Some((image, image_view, layout)) = self.sampled_image(mesh.texture_id) {
+                    dependencies.reads.push(ImageDependency {
+                        image,
+                        image_view,
+                        layout,
+                        final_layout: layout,
+                        stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                        access_mask: vk::AccessFlags2::SHADER_READ,
+                    });
+                }
+            }
+        }
+
+        let index = swapchain_image_index;
+        if let (Some(&image), Some(&image_view)) = (
+            self.render_target_images.get(index),
+            self.framebuffer_color_image_views.get(index),
+        ) {
+            dependencies.writes.push(ImageDependency {
+                image: Some(image),
+                image_view,
+                layout: target_initial_layout(&self.options),
+                final_layout: target_final_layout(&self.options),
+                stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            });
+        }
+        if let Some(&image_view) = self
+            .depth_attachment
+            .as_ref()
+            .and_then(|depth_attachment| depth_attachment.image_views.get(index))
+        {
+            dependencies.writes.push(ImageDependency {
+                image: None,
+                image_view,
+                layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                stage_mask: vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                access_mask: vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            });
+        }
+        dependencies
+    }
+
+    // Image, view and layout sampled for `texture_id`, resolved like mesh_descriptor_set.
+    fn sampled_image(&self, texture_id: egui::TextureId) -> Option<(Option<vk::Image>, vk::ImageView, vk::ImageLayout)> {
+        let sampled = |texture: &VkTexture2D<A>| (Some(texture.image), texture.view, texture.sampled_layout());
+        if let egui::TextureId::User(id) = texture_id {
+            if let Some(((image_view, _), _)) = self.user_textures.descriptor_sets.get(&id) {
+                let image = self.user_texture_owners.get(&id).map(|&(image, _, _)| image);
+                return Some((image, *image_view, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL));
+            }
+            if let Some(streaming_texture) = self.streaming_textures.get(&id) {
+                let slot = streaming_texture.pending.or(streaming_texture.front)?;
+                return Some(sampled(&streaming_texture.slots[slot].0));
+            }
+            if let Some(offscreen_panel) = self.offscreen_panels.get(&id) {
+                return Some((
+                    Some(offscreen_panel.image),
+                    offscreen_panel.sampled_view,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ));
+            }
+        }
+        self.textures.get(&texture_id).map(|(texture, _)| sampled(texture))
+    }
+
+    fn paint_frame<F: FnMut(&Device, &PaintTarget, usize)>(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        swapchain_image_index: usize,
+        textures_delta: TexturesDelta,
+        passes: &[&[egui::ClippedPrimitive]],
+        pixels_per_point: f32,
+        mut between_passes: F,
+    ) -> Result<(), NeedsSwapchainUpdate> {
+        let index = swapchain_image_index;
+        if self.needs_swapchain_update || index >= self.framebuffer_color_image_views.len() {
+            log::warn!("The swapchain is out of date; the frame is not painted.");
+            self.pending_textures_delta.append(textures_delta);
+            return Err(NeedsSwapchainUpdate);
+        }
+        let textures_delta = {
+            let mut pending = std::mem::take(&mut self.pending_textures_delta);
+            pending.append(textures_delta);
+            pending
+        };
+        let frame_index = self.frame_index.unwrap_or(index);
+        self.frame_count += 1;
+        self.last_frame_index = frame_index;
+        self.finish_pipeline_creation();
+        // the previous frame painted with this frame index has completed, as its per-frame
+        // resources are reused, and so have all frames before it
+        self.destroy_deferred(|deferred_frame_index| deferred_frame_index == frame_index);
+        self.complete_readbacks(|readback_frame_index| readback_frame_index == frame_index);
+        for descriptor_set in self
+            .user_textures
+            .evict(self.frame_count.saturating_sub(USER_TEXTURE_CACHE_FRAMES))
+        {
+            self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+        }
+        while self.mesh_buffers.len() <= frame_index {
+            self.mesh_buffers.push(vec![MeshBuffers::new(
+                &self.device,
+                &self.allocator,
+                Self::vertex_buffer_size(),
+                Self::index_buffer_size(),
+                self.options.memory_locations.vertex,
+                self.options.memory_locations.index,
+            )]);
+        }
+
+        #[cfg(feature = "software-renderer")]
+        let software_pass: Vec<egui::ClippedPrimitive>;
+        #[cfg(feature = "software-renderer")]
+        let software_passes: [&[egui::ClippedPrimitive]; 1];
+        #[cfg(feature = "software-renderer")]
+        let passes = if self.software_renderer.is_some() {
+            software_pass = self.paint_in_software(&textures_delta, &passes.concat(), pixels_per_point);
+            software_passes = [&software_pass];
+            &software_passes[..]
+        } else {
+            passes
+        };
+
+        let uv_rect_passes: Vec<Vec<egui::ClippedPrimitive>>;
+        let uv_rect_pass_slices: Vec<&[egui::ClippedPrimitive]>;
+        let passes = match map_uv_rects(passes, &self.user_texture_uv_rects) {
+            Some(mapped_passes) => {
+                uv_rect_passes = mapped_passes;
+                uv_rect_pass_slices = uv_rect_passes.iter().map(Vec::as_slice).collect();
+                &uv_rect_pass_slices[..]
+            }
+            None => passes,
+        };
+
+        self.frame_stats = FrameStats {
+            passes: passes.len(),
+            ..Default::default()
+        };
+        for clipped_primitive in passes.iter().flat_map(|pass| pass.iter()) {
+            match &clipped_primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) => {
+                    self.last_draw_frames.insert(mesh.texture_id, self.frame_count);
+                    self.frame_stats.meshes += 1;
+                    self.frame_stats.vertices += mesh.vertices.len();
+                    self.frame_stats.indices += mesh.indices.len();
+                }
+                egui::epaint::Primitive::Callback(_) => self.frame_stats.callbacks += 1,
+            }
+        }
+
+        let mut textures_changed = !textures_delta.set.is_empty() || !textures_delta.free.is_empty();
+        if textures_delta.set.len() > MANY_TEXTURE_UPDATES {
+            let (updates, frame) = (textures_delta.set.len(), self.frame_count);
+            self.warn_slow_path(SlowPath::TextureUpdates, || {
+                format!(
+                    "{} textures are updated in frame {}; textures changing every frame are better drawn as user textures.",
+                    updates, frame
+                )
+            });
+        }
+        for (id, image_delta) in textures_delta.set {
+            self.update_texture(command_buffer, id, &image_delta);
+        }
+        textures_changed |= self.apply_texture_queue(command_buffer);
+
+        let pending_streaming_textures = self
+            .streaming_textures
+            .iter()
+            .filter(|(_, streaming_texture)| streaming_texture.pending.is_some())
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in pending_streaming_textures {
+            textures_changed = true;
+            let (size, mut staging_buffer) = self.staging_buffer(self.streaming_textures[&id].texels.len() as u64);
+            let streaming_texture = self.streaming_textures.get_mut(&id).unwrap();
+            staging_buffer.upload_data(0, &streaming_texture.texels);
+            let slot = streaming_texture.pending.take().unwrap();
+            streaming_texture.slots[slot].0.record_upload_from(
+                &self.device,
+                command_buffer,
+                staging_buffer.buffer,
+                (0, 0),
+                streaming_texture.size,
+                self.frame_count,
+            );
+            streaming_texture.front = Some(slot);
+            self.pending_staging_buffers.push((size, staging_buffer));
+        }
+        for index in 0..self.array_textures.len() {
+            if !self.array_textures[index].pending_upload {
+                continue;
+            }
+            textures_changed = true;
+            let (size, mut staging_buffer) = self.staging_buffer(self.array_textures[index].texels.len() as u64);
+            let array_texture = &mut self.array_textures[index];
+            staging_buffer.upload_data(0, &array_texture.texels);
+            let extent = (array_texture.texture.size.0 as u32, array_texture.texture.size.1 as u32);
+            array_texture.texture.record_upload_from(
+                &self.device,
+                command_buffer,
+                staging_buffer.buffer,
+                (0, 0),
+                extent,
+                self.frame_count,
+            );
+            array_texture.pending_upload = false;
+            self.pending_staging_buffers.push((size, staging_buffer));
+        }
+        // read by this frame or by the command buffer of update_textures submitted before it
+        for (size, staging_buffer) in std::mem::take(&mut self.pending_staging_buffers) {
+            self.defer_destruction(DeferredDestruction::StagingBuffer(size, staging_buffer));
+        }
+        
+        // acquire resources owned by other queue families
+        let acquire_barriers = self.queue_family_transfer_barriers(index, true);
+        if !acquire_barriers.is_empty() {
+            unsafe {
+                self.device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &vk::DependencyInfo::builder().image_memory_barriers(&acquire_barriers),
+                );
+            }
+        }
+        if self.queue_audit.is_some() {
+            for barrier in &acquire_barriers {
+                self.audit(
+                    command_buffer,
+                    AuditEvent::Acquire {
+                        image: barrier.image,
+                        src_queue_family: barrier.src_queue_family_index,
+                    },
+                );
+            }
+            self.audit(command_buffer, AuditEvent::Use(self.render_target_images[index]));
+            for clipped_primitive in passes.iter().flat_map(|pass| pass.iter()) {
+                if let egui::epaint::Primitive::Mesh(mesh) = &clipped_primitive.primitive {
+                    if let Some(image) = self.texture_image(mesh.texture_id) {
+                        self.audit(command_buffer, AuditEvent::Use(image));
+                    }
+                }
+            }
+        }
+
+        // recorded frames may bind descriptor sets of textures that were replaced or freed
+        if textures_changed {
+            for recorded_frame in self.recorded_frames.iter_mut() {
+                recorded_frame.key = None;
+            }
+        }
+        let reuse = self.command_pool != vk::CommandPool::null() && self.ui_layer.is_none() && passes.len() == 1;
+        if reuse && self.is_recorded(index, frame_index, passes[0], pixels_per_point) {
+            self.replay_recorded_frame(command_buffer, index, passes[0], pixels_per_point);
+        } else {
+            // the mesh buffers of the frame are rewritten under the frames recorded with them
+            for recorded_frame in self.recorded_frames.iter_mut() {
+                if recorded_frame.key.as_ref().is_some_and(|key| key.frame_index == frame_index) {
+                    recorded_frame.key = None;
+                }
+            }
+            let secondary = reuse.then(|| self.recorded_frame_command_buffer(index));
+            let mut buffer_index = 0;
+            let mut cursor = MeshBufferCursor::for_frame(passes);
+            // the UI layer composite is uploaded while drawing
+            if self.ui_layer.is_none() && cursor.frame_bytes(passes) <= TINY_FRAME_BYTES {
+                self.upload_tiny_frame(command_buffer, frame_index, passes, cursor);
+                cursor.tiny = true;
+            }
+            for (pass, clipped_meshes) in passes.iter().enumerate() {
+                if pass > 0 {
+                    between_passes(&self.device, &self.paint_target(command_buffer, index), pass);
+                }
+                let depth = self.depth_attachment.as_ref().map(|depth_attachment| depth_attachment.pass_depth(pass));
+                self.record_pass(
+                    command_buffer,
+                    index,
+                    frame_index,
+                    &mut buffer_index,
+                    &mut cursor,
+                    clipped_meshes,
+                    pixels_per_point,
+                    depth,
+                    secondary,
+                );
+            }
+            if reuse {
+                self.recorded_frames[index].key = Some(RecordedFrameKey {
+                    frame_index,
+                    clipped_primitives: passes[0].to_vec(),
+                    pixels_per_point,
+                    transform: self.transform,
+                    ui_tint: self.ui_tint,
+                    display_calibration: self.display_calibration,
+                    custom_push_constants: self.custom_push_constants.clone(),
+                });
+            }
+        }
+
+        let paint_target = self.paint_target(command_buffer, index);
+        if let Some(hook) = self.post_paint_hook.as_mut() {
+            hook(&self.device, &paint_target);
+        }
+        self.record_readbacks(command_buffer, index, frame_index);
+
+        // give them back
+        let release_barriers = self.queue_family_transfer_barriers(index, false);
+        if !release_barriers.is_empty() {
+            unsafe {
+                self.device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &vk::DependencyInfo::builder().image_memory_barriers(&release_barriers),
+                );
+            }
+        }
+        for barrier in &release_barriers {
+            self.audit(
+                command_buffer,
+                AuditEvent::Release {
+                    image: barrier.image,
+                    dst_queue_family: barrier.dst_queue_family_index,
+                },
+            );
+        }
+
+        for id in textures_delta.free {
+            self.free_texture(id);
+        }
+        Ok(())
+    }
+
+    // Destroy a texture freed by a TexturesDelta.
+    fn free_texture(&mut self, id: egui::TextureId) {
+        log::trace!("Freeing texture {:?}", id);
+        self.texture_names.remove(&id);
+        self.last_draw_frames.remove(&id);
+        self.downscaled_textures.remove(&id);
+        self.texture_samplers.remove(&id);
+        if let Some((texture, descriptor_set)) = self.textures.remove(&id) {
+            self.defer_destruction(DeferredDestruction::Texture(texture));
+            self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+        }
+    }
+
+    /// Use the per-frame resources (e.g. mesh buffers) of frame in flight `frame_index` for
+    /// the frames painted from now on, instead of those of the swapchain image painted to.
+    ///
+    /// Lets applications with their own frame-in-flight counter, which need not match the
+    /// swapchain image count, report completed frames with
+    /// [`on_frame_complete`](Self::on_frame_complete). `None` goes back to following the
+    /// swapchain image index.
+    pub fn set_frame_index(&mut self, frame_index: Option<usize>) {
+        self.frame_index = frame_index;
+    }
+
+    /// Tell the renderer that the GPU has finished the frame painted with `frame_index`,
+    /// e.g. once its fence has been waited on. Frames must complete in the order they were
+    /// painted.
+    ///
+    /// Textures and buffers released while that frame was the latest one painted are
+    /// destroyed. Without [`set_frame_index`](Self::set_frame_index), `frame_index` is the
+    /// swapchain image index the frame was painted to.
+    pub fn on_frame_complete(&mut self, frame_index: usize) {
+        self.destroy_deferred(|deferred_frame_index| deferred_frame_index == frame_index);
+        self.complete_readbacks(|readback_frame_index| readback_frame_index == frame_index);
+    }
+
+    /// Request a copy of `source` into host memory, recorded at the end of the next
+    /// [`paint`](Self::paint).
+    ///
+    /// Does not stall: poll the returned handle with [`poll_readback`](Self::poll_readback)
+    /// in later frames, e.g. for color pickers and thumbnails. Readbacks of textures that do
+    /// not exist when the paint records them are dropped with a warning and never complete.
+    pub fn request_readback(&mut self, source: ReadbackSource) -> ReadbackHandle {
+        let id = self.next_readback;
+        self.next_readback += 1;
+        self.readbacks.insert(id, PendingReadback::Requested(source));
+        ReadbackHandle(id)
+    }
+
+    /// The texels of readback `handle`, once the GPU has finished the frame that copied them.
+    ///
+    /// Frames complete when their frame index is painted again or with
+    /// [`on_frame_complete`](Self::on_frame_complete).
+    /// Returns `None` until then; the readback is returned once and then forgotten.
+    pub fn poll_readback(&mut self, handle: ReadbackHandle) -> Option<Readback> {
+        if !matches!(self.readbacks.get(&handle.0), Some(PendingReadback::Complete(_))) {
+            return None;
+        }
+        match self.readbacks.remove(&handle.0) {
+            Some(PendingReadback::Complete(readback)) => Some(readback),
+            _ => None,
+        }
+    }
+
+    // Record the copies of the readbacks requested since the last paint.
+    fn record_readbacks(&mut self, command_buffer: vk::CommandBuffer, index: usize, frame_index: usize) {
+        let requested = self
+            .readbacks
+            .iter()
+            .filter_map(|(&id, readback)| match readback {
+                PendingReadback::Requested(source) => Some((id, *source)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (id, source) in requested {
+            let (image, layout, layer, extent, format) = match source {
+                ReadbackSource::Frame => (
+                    self.render_target_images[index],
+                    target_final_layout(&self.options),
+                    self.render_target_layer,
+                    vk::Extent2D {
+                        width: self.physical_width,
+                        height: self.physical_height,
+                    },
+                    self.render_target_format,
+                ),
+                ReadbackSource::Texture(texture_id) => match self.owned_texture(texture_id) {
+                    Some(texture) => (
+                        texture.image,
+                        texture.layout,
+                        0,
+                        vk::Extent2D {
+                            width: texture.size.0 as u32,
+                            height: texture.size.1 as u32,
+                        },
+                        texture.format,
+                    ),
+                    None => {
+                        log::warn!("{:?} is not a texture of the integration; the readback is dropped.", texture_id);
+                        self.readbacks.remove(&id);
+                        continue;
+                    }
+                },
+            };
+            let mut buffer = VkStagingBuffer::<A>::new();
+            buffer.create(
+                &self.device,
+                &self.allocator,
+                extent.width as u64 * extent.height as u64 * texel_size(format),
+                MemoryLocation::GpuToCpu,
+                vk::BufferUsageFlags::TRANSFER_DST,
+            );
+            record_image_readback(&self.device, command_buffer, image, layout, layer, extent, buffer.buffer);
+            self.readbacks.insert(
+                id,
+                PendingReadback::InFlight {
+                    frame_index,
+                    buffer,
+                    extent,
+                    format,
+                },
+            );
+        }
+    }
+
+    // A texture owned by the integration.
+    fn owned_texture(&self, texture_id: egui::TextureId) -> Option<&VkTexture2D<A>> {
+        if let Some((texture, _)) = self.textures.get(&texture_id) {
+            return Some(texture);
+        }
+        match texture_id {
+            egui::TextureId::User(id) => {
+                let streaming_texture = self.streaming_textures.get(&id)?;
+                Some(&streaming_texture.slots[streaming_texture.front?].0)
+            }
+            egui::TextureId::Managed(_) => None,
+        }
+    }
+
+    // Read back the copies of the completed frames.
+    fn complete_readbacks(&mut self, mut completed: impl FnMut(usize) -> bool) {
+        let ids = self
+            .readbacks
+            .iter()
+            .filter(|(_, readback)| {
+                matches!(readback, PendingReadback::InFlight { frame_index, .. } if completed(*frame_index))
+            })
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            if let Some(PendingReadback::InFlight {
+                mut buffer,
+                extent,
+                format,
+                ..
+            }) = self.readbacks.remove(&id)
+            {
+                let len = extent.width as u64 * extent.height as u64 * texel_size(format);
+                let data = buffer.read_data(len as usize);
+                buffer.destroy(&self.device, &self.allocator);
+                let readback = Readback {
+                    width: extent.width,
+                    height: extent.height,
+                    format,
+                    data,
+                };
+                self.readbacks.insert(id, PendingReadback::Complete(readback));
+            }
+        }
+    }
+
+    // Destroy `resource` once the frame painted last has completed.
+    fn defer_destruction(&mut self, resource: DeferredDestruction<A>) {
+        self.deferred_destruction.push((self.last_frame_index, resource));
+    }
+
+    fn destroy_deferred(&mut self, mut completed: impl FnMut(usize) -> bool) {
+        let (destroyed, deferred) = std::mem::take(&mut self.deferred_destruction)
+            .into_iter()
+            .partition::<Vec<_>, _>(|&(frame_index, _)| completed(frame_index));
+        self.deferred_destruction = deferred;
+        for (_, resource) in destroyed {
+            match resource {
+                DeferredDestruction::Texture(mut texture) => {
+                    if let Some(queue_audit) = self.queue_audit.as_mut() {
+                        queue_audit.forget(texture.image);
+                    }
+                    texture.destroy(&self.device, &self.allocator);
+                }
+                DeferredDestruction::DescriptorSet(descriptor_set) => self.free_descriptor_sets.push(descriptor_set),
+                DeferredDestruction::ImageView(image_view) => unsafe {
+                    self.device.destroy_image_view(image_view, None);
+                },
+                DeferredDestruction::MeshBuffers(buffers) => buffers.destroy(&self.device, &self.allocator),
+                DeferredDestruction::OffscreenPanel(panel) => self.destroy_offscreen_panel(panel),
+                DeferredDestruction::StagingBuffer(size, buffer) => self.free_staging_buffers.push((size, buffer)),
+            }
+        }
+    }
+    
+    // Upload all meshes of `passes` into the device local buffers of frame `frame_index`
+    // with cmd_update_buffer, laid out as `cursor` would reserve them.
+    fn upload_tiny_frame(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        passes: &[&[egui::ClippedPrimitive]],
+        cursor: MeshBufferCursor,
+    ) {
+        let mut vertices = std::mem::take(&mut self.scratch_tiny_vertices);
+        let mut indices = std::mem::take(&mut self.scratch_tiny_indices);
+        vertices.clear();
+        indices.clear();
+        for clipped_primitive in passes.iter().flat_map(|pass| pass.iter()) {
+            let mesh = match &clipped_primitive.primitive {
+                egui::epaint::Primitive::Mesh(mesh) if !mesh.vertices.is_empty() && !mesh.indices.is_empty() => mesh,
+                _ => continue,
+            };
+            vertices.extend_from_slice(bytemuck::cast_slice(&mesh.vertices));
+            if cursor.short_indices {
+                for &vertex_index in mesh.indices.iter() {
+                    indices.extend_from_slice(&(vertex_index as u16).to_ne_bytes());
+                }
+            } else {
+                indices.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+            }
+        }
+        // updates are made in whole words
+        indices.resize(indices.len().next_multiple_of(4), 0);
+
+        if self.tiny_mesh_buffers.len() <= frame_index {
+            self.tiny_mesh_buffers.resize_with(frame_index + 1, || None);
+        }
+        let (device, allocator) = (&self.device, &self.allocator);
+        let buffers = self.tiny_mesh_buffers[frame_index].get_or_insert_with(|| {
+            MeshBuffers::new(
+                device,
+                allocator,
+                TINY_FRAME_BYTES as u64,
+                TINY_FRAME_BYTES as u64,
+                MemoryLocation::GpuOnly,
+                MemoryLocation::GpuOnly,
+            )
+        });
+        unsafe {
+            if !vertices.is_empty() {
+                self.device.cmd_update_buffer(command_buffer, buffers.vertex_buffer, 0, &vertices);
+            }
+            if !indices.is_empty() {
+                self.device.cmd_update_buffer(command_buffer, buffers.index_buffer, 0, &indices);
+            }
+            self.device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::builder().memory_barriers(&[vk::MemoryBarrier2::builder()
+                    .src_stage_mask(vk::PipelineStageFlags2::TRANSFER)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT | vk::PipelineStageFlags2::INDEX_INPUT)
+                    .dst_access_mask(vk::AccessFlags2::VERTEX_ATTRIBUTE_READ | vk::AccessFlags2::INDEX_READ)
+                    .build()]),
+            );
+        }
+        self.scratch_tiny_vertices = vertices;
+        self.scratch_tiny_indices = indices;
+    }
+
+    // Whether the recorded frame of swapchain image `index` draws `clipped_primitives` as they
+    // would be drawn now at `pixels_per_point` from the mesh buffers of frame `frame_index`.
+    fn is_recorded(
+        &self,
+        index: usize,
+        frame_index: usize,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> bool {
+        match self.recorded_frames.get(index).and_then(|recorded_frame| recorded_frame.key.as_ref()) {
+            Some(key) => {
+                key.frame_index == frame_index
+                    && key.pixels_per_point == pixels_per_point
+                    && key.transform == self.transform
+                    && key.ui_tint == self.ui_tint
+                    && key.display_calibration == self.display_calibration
+                    && key.custom_push_constants == self.custom_push_constants
+                    && same_primitives(&key.clipped_primitives, clipped_primitives)
+            }
+            None => false,
+        }
+    }
+
+    // Secondary command buffer to record the frame of swapchain image `index` into.
+    fn recorded_frame_command_buffer(&mut self, index: usize) -> vk::CommandBuffer {
+        while self.recorded_frames.len() <= index {
+            let command_buffer = unsafe {
+                self.device.allocate_command_buffers(
+                    &vk::CommandBufferAllocateInfo::builder()
+                        .command_pool(self.command_pool)
+                        .level(vk::CommandBufferLevel::SECONDARY)
+                        .command_buffer_count(1),
+                )
+            }
+            .expect("Failed to allocate command buffers.")[0];
+            self.recorded_frames.push(RecordedFrame {
+                command_buffer,
+                key: None,
+            });
+        }
+        self.recorded_frames[index].command_buffer
+    }
+
+    fn free_recorded_frames(&mut self) {
+        if self.recorded_frames.is_empty() {
+            return;
+        }
+        let command_buffers = self
+            .recorded_frames
+            .drain(..)
+            .map(|recorded_frame| recorded_frame.command_buffer)
+            .collect::<Vec<_>>();
+        unsafe {
+            self.device.free_command_buffers(self.command_pool, &command_buffers);
+        }
+    }
+
+    // Draw the unchanged frame of swapchain image `index`, which draws `clipped_primitives`, by
+    // executing its recorded commands.
+    fn replay_recorded_frame(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        index: usize,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) {
+        let extent = vk::Extent2D {
+            width: self.physical_width,
+            height: self.physical_height,
+        };
+        self.begin_target_pass(
+            command_buffer,
+            index,
+            self.pass_render_area(clipped_primitives, extent, pixels_per_point),
+            vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+        );
+        unsafe {
+            self.device
+                .cmd_execute_commands(command_buffer, &[self.recorded_frames[index].command_buffer]);
+        }
+        self.end_target_pass(command_buffer, index);
+    }
+
+    // Record the render pass drawing `clipped_meshes` at `scale_factor` to the target of swapchain
+    // image `index`, continuing in the mesh buffers of frame `frame_index` after the previous passes. `depth` is written
+    // into the depth attachment, if any. With `secondary`, the draws are recorded into it
+    // and executed from `command_buffer`.
+    #[allow(clippy::too_many_arguments)]
+    fn record_pass(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        index: usize,
+        frame_index: usize,
+        buffer_index: &mut usize,
+        cursor: &mut MeshBufferCursor,
+        clipped_meshes: &[egui::ClippedPrimitive],
+        scale_factor: f32,
+        depth: Option<f32>,
+        secondary: Option<vk::CommandBuffer>,
+    ) {
+        // the UI is rendered into the UI layer if there is one, and composited over the render target afterwards
+        let target_extent = vk::Extent2D {
+            width: self.physical_width,
+            height: self.physical_height,
+        };
+        let (render_pass, framebuffer, extent) = match &self.ui_layer {
+            Some(ui_layer) => (
+                ui_layer.render_pass,
+                ui_layer.images[index].framebuffer,
+                ui_layer.extent,
+            ),
+            None => (
+                self.render_pass,
+                self.framebuffers.get(index).copied().unwrap_or_default(),
+                target_extent,
+            ),
+        };
+        let pixels_per_point = scale_factor * extent.width as f32 / self.physical_width as f32;
+
+        // begin render pass
+        let contents = match secondary {
+            Some(_) => vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+            None => vk::SubpassContents::INLINE,
+        };
+        let render_area = self.pass_render_area(clipped_meshes, extent, scale_factor);
+        if self.ui_layer.is_some() {
+            self.begin_render_pass(command_buffer, render_pass, framebuffer, render_area, contents);
+        } else {
+            self.begin_target_pass(command_buffer, index, render_area, contents);
+        }
+        let primary = command_buffer;
+        let command_buffer = match secondary {
+            Some(secondary) => {
+                let color_attachment_formats = [self.render_target_format];
+                let (depth_attachment_format, stencil_attachment_format) = depth_stencil_formats(
+                    self.depth_attachment.as_ref().map(|depth_attachment| depth_attachment.format),
+                );
+                let mut rendering_info = vk::CommandBufferInheritanceRenderingInfo::builder()
+                    .color_attachment_formats(&color_attachment_formats)
+                    .depth_attachment_format(depth_attachment_format)
+                    .stencil_attachment_format(stencil_attachment_format)
+                    .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+                let mut inheritance_info = vk::CommandBufferInheritanceInfo::builder()
+                    .render_pass(render_pass)
+                    .subpass(0)
+                    .framebuffer(framebuffer);
+                if render_pass == vk::RenderPass::null() {
+                    inheritance_info = inheritance_info.push_next(&mut rendering_info);
+                }
+                unsafe {
+                    self.device.begin_command_buffer(
+                        secondary,
+                        &vk::CommandBufferBeginInfo::builder()
+                            .flags(vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
+                            .inheritance_info(&inheritance_info),
+                    )
+                }
+                .expect("Failed to begin command buffer.");
+                secondary
+            }
+            None => primary,
+        };
+        let tint = egui::Rgba::from(self.ui_tint).to_array();
+        let calibration = [self.display_calibration.gamma, self.display_calibration.brightness];
+        self.bind_pipeline_state(
+            command_buffer,
+            extent,
+            self.screen_size(scale_factor),
+            self.pipeline,
+            self.transform.unwrap_or(IDENTITY),
+            tint,
+            calibration,
+        );
+        if let Some(depth) = depth {
+            // every fragment of the pass gets the same depth
+            unsafe {
+                self.device.cmd_set_viewport(
+                    command_buffer,
+                    0,
+                    &[vk::Viewport::builder()
+                        .width(extent.width as f32)
+                        .height(extent.height as f32)
+                        .min_depth(depth)
+                        .max_depth(depth)
+                        .build()],
+                );
+            }
+        }
+
+        // render meshes
+        let buffers = if cursor.tiny {
+            self.tiny_mesh_buffers[frame_index].as_ref().unwrap()
+        } else {
+            &self.mesh_buffers[frame_index][*buffer_index]
+        };
+        self.bind_mesh_buffers(command_buffer, buffers, cursor.index_type());
+        let mut bound_pipeline = self.pipeline;
+        // dynamic state is only set when it changes between meshes
+        let mut bound_scissor = None;
+        let mut bound_clip_rect = None;
+        for egui::ClippedPrimitive{clip_rect, primitive} in clipped_meshes {
+            let mesh = match primitive {
+                egui::epaint::Primitive::Mesh(mesh) => mesh,
+                egui::epaint::Primitive::Callback(_) => {
+                    log::warn!("Paint callbacks are not supported, the one in {:?} is skipped", clip_rect);
+                    self.report_error(RuntimeError::UnsupportedCallback { clip_rect: *clip_rect });
+                    continue;
+                }
+            };
+            if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+                continue;
+            }
+            // uploaded before meshes are skipped, so every mesh lands where upload_tiny_frame put it
+            let (first_index, vertex_offset) =
+                self.upload_mesh(command_buffer, frame_index, buffer_index, cursor, mesh);
+
+            // text is drawn from the font atlas
+            let pipeline = match self.text_pipeline {
+                Some(text_pipeline) if mesh.texture_id == egui::TextureId::default() => text_pipeline,
+                _ => self.pipeline,
+            };
+            if pipeline != bound_pipeline {
+                unsafe {
+                    self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+                }
+                bound_pipeline = pipeline;
+            }
+            // update texture
+            match self.mesh_descriptor_set(mesh.texture_id) {
+                Some(descriptor_set) => self.bind_descriptor_set(command_buffer, descriptor_set),
+                None => continue,
+            }
+
+            // record draw commands
+            unsafe {
+                if self.transform.is_some() {
+                    // the clip rect is not axis aligned on the target, so clip in the fragment shader
+                    if bound_clip_rect.replace(*clip_rect) != Some(*clip_rect) {
+                        self.device.cmd_push_constants(
+                            command_buffer,
+                            self.pipeline_layout,
+                            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                            CLIP_RECT_OFFSET,
+                            bytes_of(&[clip_rect.min.x, clip_rect.min.y, clip_rect.max.x, clip_rect.max.y]),
+                        );
+                    }
+                    let scissor = vk::Rect2D {
+                        offset: Default::default(),
+                        extent,
+                    };
+                    if replace_scissor(&mut bound_scissor, scissor) {
+                        self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                    }
+                } else {
+                    let scissor = scissor_rect(*clip_rect, pixels_per_point, extent.width, extent.height);
+                    if replace_scissor(&mut bound_scissor, scissor) {
+                        self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                    }
+                    if self.error_hook.is_some()
+                        && scissor_is_clamped(*clip_rect, pixels_per_point, extent.width, extent.height)
+                    {
+                        self.report_error(RuntimeError::ScissorClamped {
+                            clip_rect: *clip_rect,
+                            scissor,
+                        });
+                    }
+                }
+                self.device.cmd_draw_indexed(
+                    command_buffer,
+                    mesh.indices.len() as u32,
+                    1,
+                    first_index,
+                    vertex_offset,
+                    0,
+                );
+            }
+        }
+
+        // end render pass
+        unsafe {
+            if secondary.is_some() {
+                self.device
+                    .end_command_buffer(command_buffer)
+                    .expect("Failed to end command buffer.");
+                self.device.cmd_execute_commands(primary, &[command_buffer]);
+            }
+        }
+        if self.ui_layer.is_some() {
+            unsafe {
+                self.device.cmd_end_render_pass(primary);
+            }
+        } else {
+            self.end_target_pass(primary, index);
+        }
+
+        if let Some(ui_layer) = &self.ui_layer {
+            // composite the UI layer over the render target; frames with a UI layer are not reused
+            let command_buffer = primary;
+            let descriptor_set = ui_layer.images[index].descriptor_set;
+            let composite_pipeline = ui_layer.composite_pipeline;
+            self.begin_target_pass(
+                command_buffer,
+                index,
+                vk::Rect2D::builder().extent(target_extent).build(),
+                vk::SubpassContents::INLINE,
+            );
+            // the layer is already tinted and calibrated
+            self.bind_pipeline_state(
+                command_buffer,
+                target_extent,
+                self.screen_size(scale_factor),
+                composite_pipeline,
+                IDENTITY,
+                [1.0; 4],
+                [1.0; 2],
+            );
+
+            let mut mesh = std::mem::take(&mut self.scratch_composite_mesh);
+            mesh.clear();
+            mesh.add_rect_with_uv(
+                egui::Rect::from_min_size(
+                    Default::default(),
+                    vec2(self.physical_width as f32, self.physical_height as f32) / scale_factor,
+                ),
+                egui::Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+            let (first_index, vertex_offset) =
+                self.upload_mesh(command_buffer, frame_index, buffer_index, cursor, &mesh);
+            self.bind_descriptor_set(command_buffer, descriptor_set);
+            unsafe {
+                self.device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    &[vk::Rect2D {
+                        offset: Default::default(),
+                        extent: target_extent,
+                    }],
+                );
+                self.device.cmd_draw_indexed(
+                    command_buffer,
+                    mesh.indices.len() as u32,
+                    1,
+                    first_index,
+                    vertex_offset,
+                    0,
+                );
+            }
+            self.end_target_pass(command_buffer, index);
+            self.scratch_composite_mesh = mesh;
+        }
+    }
+
+    // Descriptor set to draw meshes using `texture_id` with, None if they are skipped.
+    fn mesh_descriptor_set(&mut self, texture_id: egui::TextureId) -> Option<vk::DescriptorSet> {
+        if let egui::TextureId::User(id) = texture_id {
+            let descriptor_set = if let Some(descriptor_set) = self.user_textures.descriptor_set(id) {
+                Some(descriptor_set)
+            } else if let Some(streaming_texture) = self.streaming_textures.get(&id) {
+                // nothing has been uploaded yet without a front image
+                return streaming_texture.front.map(|front| streaming_texture.slots[front].1);
+            } else if let Some(offscreen_panel) = self.offscreen_panels.get(&id) {
+                Some(offscreen_panel.descriptor_set)
+            } else {
+                #[cfg(feature = "strict")]
+                self.check_texture_layout(texture_id);
+                self.textures.get(&texture_id).map(|&(_, descriptor_set)| descriptor_set)
+            };
+            if descriptor_set.is_none() {
+                log::warn!("This UserTexture has already been unregistered: {:?}", texture_id);
+                self.report_error(RuntimeError::UnregisteredTexture(texture_id));
+            }
+            descriptor_set
+        } else {
+            #[cfg(feature = "strict")]
+            if !self.textures.contains_key(&texture_id) {
+                log::warn!(
+                    "strict: a mesh uses {:?}, which has not been uploaded; pass every TexturesDelta egui returns to paint() in order.",
+                    texture_id
+                );
+            }
+            #[cfg(feature = "strict")]
+            self.check_texture_layout(texture_id);
+            Some(self.textures[&texture_id].1)
+        }
+    }
+
+    fn paint_target(&self, command_buffer: vk::CommandBuffer, index: usize) -> PaintTarget {
+        PaintTarget {
+            command_buffer,
+            image_index: index,
+            image: self.render_target_images[index],
+            image_view: self.framebuffer_color_image_views[index],
+            layer: self.render_target_layer,
+            format: self.render_target_format,
+            extent: vk::Extent2D {
+                width: self.physical_width,
+                height: self.physical_height,
+            },
+        }
+    }
+
+    // Acquire (or release) barriers for the user textures and the render target of swapchain
+    // image `index` that are owned by other queue families.
+    fn queue_family_transfer_barriers(&self, index: usize, acquire: bool) -> Vec<ImageMemoryBarrier2> {
+        let queue_family = self.options.queue_family_index;
+        let transfer = |owner: u32| if acquire { (owner, queue_family) } else { (queue_family, owner) };
+
+        let mut barriers = vec![];
+        for &(image, subresource_range, owner) in self.user_texture_owners.values() {
+            let (src, dst) = transfer(owner);
+            barriers.extend(queue_family_transfer_barrier(
+                image,
+                subresource_range,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                src,
+                dst,
+                acquire,
+                vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                vk::AccessFlags2::SHADER_READ,
+            ));
+        }
+        if let Some(owner) = self.render_target_queue_family {
+            let (src, dst) = transfer(owner);
+            let (layout, stage_mask, access_mask) = if acquire {
+                (
+                    target_initial_layout(&self.options),
+                    vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                    vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                )
+            } else {
+                // the post-paint hook may have written to the target too
+                (
+                    target_final_layout(&self.options),
+                    vk::PipelineStageFlags2::ALL_COMMANDS,
+                    vk::AccessFlags2::MEMORY_WRITE,
+                )
+            };
+            barriers.extend(queue_family_transfer_barrier(
+                self.render_target_images[index],
+                vk::ImageSubresourceRange::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .base_mip_level(0)
+                    .level_count(1)
+                    .base_array_layer(self.render_target_layer)
+                    .layer_count(1)
+                    .build(),
+                layout,
+                src,
+                dst,
+                acquire,
+                stage_mask,
+                access_mask,
+            ));
+        }
+        barriers
+    }
+
+    // Begin `render_pass`, with the draws recorded inline or in secondary command buffers.
+    fn begin_render_pass(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        render_area: vk::Rect2D,
+        contents: vk::SubpassContents,
+    ) {
+        unsafe {
+            self.device.cmd_begin_render_pass(
+                command_buffer,
+                &vk::RenderPassBeginInfo::builder()
+                    .render_pass(render_pass)
+                    .framebuffer(framebuffer)
+                    // only used by the UI layer, which is cleared to transparent
+                    .clear_values(&[vk::ClearValue {
+                        color: vk::ClearColorValue {
+                            float32: [0.0, 0.0, 0.0, 0.0],
+                        },
+                    }])
+                    .render_area(render_area),
+                contents,
+            );
+        }
+    }
+
+    // Begin drawing into the render target of swapchain image `index`: its render pass, or
+    // dynamic rendering with IntegrationOptions::dynamic_rendering.
+    fn begin_target_pass(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        index: usize,
+        render_area: vk::Rect2D,
+        contents: vk::SubpassContents,
+    ) {
+        if self.render_pass != vk::RenderPass::null() {
+            self.begin_render_pass(command_buffer, self.render_pass, self.framebuffers[index], render_area, contents);
+            return;
+        }
+        let color_attachments = [vk::RenderingAttachmentInfo::builder()
+            .image_view(self.framebuffer_color_image_views[index])
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .build()];
+        // the depth buffer belongs to the application, so it is kept as it is
+        let depth_attachment = self.depth_attachment.as_ref().map(|depth_attachment| {
+            vk::RenderingAttachmentInfo::builder()
+                .image_view(depth_attachment.image_views[index])
+                .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::LOAD)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .build()
+        });
+        let mut rendering_info = vk::RenderingInfo::builder()
+            .render_area(render_area)
+            .layer_count(1)
+            .color_attachments(&color_attachments);
+        if contents == vk::SubpassContents::SECONDARY_COMMAND_BUFFERS {
+            rendering_info = rendering_info.flags(vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS);
+        }
+        let mut memory_barriers = vec![];
+        if let (Some(depth_attachment), Some(attachment_info)) = (&self.depth_attachment, &depth_attachment) {
+            rendering_info = rendering_info.depth_attachment(attachment_info);
+            if format_has_stencil(depth_attachment.format) {
+                rendering_info = rendering_info.stencil_attachment(attachment_info);
+            }
+            let depth_stages =
+                vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS;
+            memory_barriers.push(
+                vk::MemoryBarrier2::builder()
+                    .src_stage_mask(depth_stages)
+                    .src_access_mask(vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                    .dst_stage_mask(depth_stages)
+                    .dst_access_mask(
+                        vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                            | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                    )
+                    .build(),
+            );
+        }
+        let image_barriers = [dynamic_rendering_barrier(
+            &self.options,
+            self.render_target_images[index],
+            self.render_target_layer,
+            true,
+        )];
+        unsafe {
+            self.device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::builder()
+                    .memory_barriers(&memory_barriers)
+                    .image_memory_barriers(&image_barriers),
+            );
+            self.device.cmd_begin_rendering(command_buffer, &rendering_info);
+        }
+    }
+
+    // End drawing into the render target of swapchain image `index`, begun with
+    // begin_target_pass.
+    fn end_target_pass(&self, command_buffer: vk::CommandBuffer, index: usize) {
+        unsafe {
+            if self.render_pass != vk::RenderPass::null() {
+                self.device.cmd_end_render_pass(command_buffer);
+                return;
+            }
+            self.device.cmd_end_rendering(command_buffer);
+            self.device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::builder().image_memory_barriers(&[dynamic_rendering_barrier(
+                    &self.options,
+                    self.render_target_images[index],
+                    self.render_target_layer,
+                    false,
+                )]),
+            );
+        }
+    }
+
+    // Size of the render target in points at `pixels_per_point`.
+    fn screen_size(&self, pixels_per_point: f32) -> [f32; 2] {
+        [
+            self.physical_width as f32 / pixels_per_point,
+            self.physical_height as f32 / pixels_per_point,
+        ]
+    }
+
+    // Render area of a pass drawing `clipped_primitives` at `pixels_per_point` into a target of `extent`. Passes to the
+    // render target only cover the scissors of their meshes, so tilers do not load and store
+    // the tiles a HUD in a corner leaves untouched. The UI layer is cleared, so it is rendered
+    // whole, as are transformed UIs, which are not clipped by scissors.
+    fn pass_render_area(
+        &self,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        extent: vk::Extent2D,
+        pixels_per_point: f32,
+    ) -> vk::Rect2D {
+        if self.ui_layer.is_some() || self.transform.is_some() {
+            return vk::Rect2D::builder().extent(extent).build();
+        }
+        render_area(
+            clipped_primitives.iter().map(|clipped_primitive| clipped_primitive.clip_rect),
+            pixels_per_point,
+            extent,
+        )
+    }
+
+    // Bind `pipeline` with the state shared by all draws of a render pass over a screen of
+    // `screen_size` points.
+    #[allow(clippy::too_many_arguments)]
+    fn bind_pipeline_state(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        screen_size: [f32; 2],
+        pipeline: vk::Pipeline,
+        transform: [[f32; 4]; 4],
+        tint: [f32; 4],
+        calibration: [f32; 2],
+    ) {
+        unsafe {
+            // bind resources
+            self.device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline,
+            );
+            if let Some(descriptor_buffer) = &self.descriptor_buffer {
+                let binding_info = vk::DescriptorBufferBindingInfoEXT::builder()
+                    .address(descriptor_buffer.address)
+                    .usage(
+                        vk::BufferUsageFlags::SAMPLER_DESCRIPTOR_BUFFER_EXT
+                            | vk::BufferUsageFlags::RESOURCE_DESCRIPTOR_BUFFER_EXT,
+                    );
+                (descriptor_buffer.fns.cmd_bind_descriptor_buffers_ext)(command_buffer, 1, &*binding_info);
+            }
+            self.device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[vk::Viewport::builder()
+                    .x(0.0)
+                    .y(0.0)
+                    .width(extent.width as f32)
+                    .height(extent.height as f32)
+                    .min_depth(0.0)
+                    .max_depth(1.0)
+                    .build()],
+            );
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                0,
+                bytes_of(&PushConstants {
+                    transform,
+                    screen_size,
+                    calibration,
+                    clip_rect: [f32::MIN, f32::MIN, f32::MAX, f32::MAX],
+                    tint,
+                }),
+            );
+            if !self.custom_push_constants.is_empty() {
+                self.device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    CUSTOM_PUSH_CONSTANTS_OFFSET,
+                    &self.custom_push_constants,
+                );
+            }
+        }
+    }
+
+    // Copy `mesh` into the mesh buffers of frame `frame_index`, continuing in overflow
+    // buffers when they are full. Returns the first index and vertex offset to draw it with.
+    fn upload_mesh(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        buffer_index: &mut usize,
+        cursor: &mut MeshBufferCursor,
+        mesh: &egui::Mesh,
+    ) -> (u32, i32) {
+        if cursor.tiny {
+            // already in the buffers
+            let (vertex_offset, index_offset) = cursor
+                .reserve(&mesh.vertices, &mesh.indices, TINY_FRAME_BYTES, TINY_FRAME_BYTES)
+                .unwrap();
+            return (
+                (index_offset / cursor.index_size()) as u32,
+                (vertex_offset / std::mem::size_of::<egui::epaint::Vertex>()) as i32,
+            );
+        }
+        let buffers = &self.mesh_buffers[frame_index][*buffer_index];
+        let (vertex_capacity, index_capacity) = (buffers.vertex_capacity, buffers.index_capacity);
+        #[cfg(feature = "strict")]
+        let before = *cursor;
+        let offsets = cursor.reserve(&mesh.vertices, &mesh.indices, vertex_capacity, index_capacity);
+        if *buffer_index == 0
+            && (above_headroom(cursor.vertex_offset, vertex_capacity) || above_headroom(cursor.index_offset, index_capacity))
+        {
+            self.warn_slow_path(SlowPath::MeshBuffers, || {
+                format!(
+                    "the mesh buffers of frame {} are over {}% full; larger frames spill into overflow buffers allocated while painting.",
+                    frame_index,
+                    SLOW_PATH_HEADROOM * 100.0
+                )
+            });
+        }
+        #[cfg(feature = "strict")]
+        if *buffer_index == 0
+            && (crosses_headroom(before.vertex_offset, cursor.vertex_offset, vertex_capacity)
+                || crosses_headroom(before.index_offset, cursor.index_offset, index_capacity))
+        {
+            log::warn!(
+                "strict: the mesh buffers of frame {} are over {}% full; larger frames spill into overflow buffers allocated while painting.",
+                frame_index,
+                STRICT_HEADROOM * 100.0
+            );
+        }
+        let (vertex_offset, index_offset) = match offsets {
+            Some(offsets) => offsets,
+            None => {
+                #[cfg(feature = "strict")]
+                log::warn!(
+                    "strict: the mesh buffers of frame {} overflowed; continuing in overflow buffers {}.",
+                    frame_index,
+                    *buffer_index + 1
+                );
+                self.warn_slow_path(SlowPath::MeshBuffers, || {
+                    format!("the mesh buffers of frame {} overflowed; overflow buffers are allocated while painting.", frame_index)
+                });
+                // the buffers are full: continue drawing from the next (overflow) buffers
+                *buffer_index += 1;
+                self.prepare_overflow_buffers(frame_index, *buffer_index, mesh);
+                self.bind_mesh_buffers(command_buffer, &self.mesh_buffers[frame_index][*buffer_index], cursor.index_type());
+                cursor.restart();
+                let buffers = &self.mesh_buffers[frame_index][*buffer_index];
+                cursor
+                    .reserve(&mesh.vertices, &mesh.indices, buffers.vertex_capacity, buffers.index_capacity)
+                    .unwrap()
+            }
+        };
+        let buffers = &self.mesh_buffers[frame_index][*buffer_index];
+
+        // map memory
+        unsafe {
+            let vertex_buffer_ptr = buffers.vertex_allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            let index_buffer_ptr = buffers.index_allocation.mapped_ptr().unwrap().as_ptr() as *mut u8;
+            vertex_buffer_ptr
+                .add(vertex_offset)
+                .copy_from(mesh.vertices.as_ptr() as *const u8, std::mem::size_of_val(mesh.vertices.as_slice()));
+            if cursor.short_indices {
+                let index_buffer_ptr = index_buffer_ptr.add(index_offset) as *mut u16;
+                for (i, &vertex_index) in mesh.indices.iter().enumerate() {
+                    index_buffer_ptr.add(i).write(vertex_index as u16);
+                }
+            } else {
+                index_buffer_ptr
+                    .add(index_offset)
+                    .copy_from(mesh.indices.as_ptr() as *const u8, std::mem::size_of_val(mesh.indices.as_slice()));
+            }
+        }
+
+        (
+            (index_offset / cursor.index_size()) as u32,
+            (vertex_offset / std::mem::size_of::<egui::epaint::Vertex>()) as i32,
+        )
+    }
+
+    fn bind_mesh_buffers(&self, command_buffer: vk::CommandBuffer, buffers: &MeshBuffers<A>, index_type: vk::IndexType) {
+        unsafe {
+            self.device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0,
+                &[buffers.vertex_buffer],
+                &[0],
+            );
+            self.device.cmd_bind_index_buffer(
+                command_buffer,
+                buffers.index_buffer,
+                0,
+                index_type,
+            );
+        }
+    }
+
+    // Make sure overflow buffers `buffer_index` of frame `frame_index` exist and can hold `mesh`.
+    // Overflow buffers are kept for later frames.
+    fn prepare_overflow_buffers(&mut self, frame_index: usize, buffer_index: usize, mesh: &egui::Mesh) {
+        let vertex_size = std::mem::size_of_val(mesh.vertices.as_slice()) as u64;
+        let index_size = std::mem::size_of_val(mesh.indices.as_slice()) as u64;
+        let fits = |buffers: &MeshBuffers<A>| {
+            buffers.vertex_capacity as u64 >= vertex_size && buffers.index_capacity as u64 >= index_size
+        };
+        let chain = &mut self.mesh_buffers[frame_index];
+        if chain.get(buffer_index).is_some_and(fits) {
+            return;
+        }
+        let buffers = MeshBuffers::new(
+            &self.device,
+            &self.allocator,
+            Self::vertex_buffer_size().max(vertex_size),
+            Self::index_buffer_size().max(index_size),
+            self.options.memory_locations.vertex,
+            self.options.memory_locations.index,
+        );
+        if buffer_index < chain.len() {
+            // earlier frames may still read the smaller buffers
+            let buffers = std::mem::replace(&mut chain[buffer_index], buffers);
+            self.defer_destruction(DeferredDestruction::MeshBuffers(buffers));
+        } else {
+            chain.push(buffers);
+        }
+    }
+
+    // Rasterize the frame on the CPU and replace it with a single mesh drawing the result.
+    #[cfg(feature = "software-renderer")]
+    fn paint_in_software(
+        &mut self,
+        textures_delta: &TexturesDelta,
+        clipped_meshes: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> Vec<egui::ClippedPrimitive> {
+        let size = (self.physical_width, self.physical_height);
+        let (renderer, texture_id) = self.software_renderer.as_mut().unwrap();
+        renderer.paint(
+            [size.0 as usize, size.1 as usize],
+            pixels_per_point,
+            textures_delta,
+            clipped_meshes,
+        );
+        // the fragment shader only converts texels to linear in premultiplied alpha mode
+        let data: Vec<u8> = if self.options.premultiplied_alpha {
+            bytemuck::cast_slice(&renderer.to_color32()).to_vec()
+        } else {
+            renderer
+                .pixels()
+                .iter()
+                .flat_map(|pixel| pixel.to_array())
+                .map(egui::epaint::color::linear_u8_from_linear_f32)
+                .collect()
+        };
+
+        // (re)create the target texture when the size changed
+        let old_texture_id = *texture_id;
+        let texture_id = match old_texture_id {
+            Some(id) if self.streaming_texture_mut(id).unwrap().size() == size => id,
+            _ => {
+                if let Some(id) = old_texture_id {
+                    self.destroy_streaming_texture(id);
+                }
+                let id = self.create_streaming_texture(size.0, size.1);
+                self.software_renderer.as_mut().unwrap().1 = Some(id);
+                id
+            }
+        };
+        self.streaming_texture_mut(texture_id).unwrap().update(&data);
+
+        let screen_rect = egui::Rect::from_min_size(
+            Default::default(),
+            vec2(size.0 as f32, size.1 as f32) / pixels_per_point,
+        );
+        let mut mesh = egui::Mesh::with_texture(texture_id);
+        mesh.add_rect_with_uv(
+            screen_rect,
+            egui::Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        vec![egui::ClippedPrimitive {
+            clip_rect: screen_rect,
+            primitive: egui::epaint::Primitive::Mesh(mesh),
+        }]
+    }
+
+    fn update_texture(&mut self, command_buffer: vk::CommandBuffer, id: egui::TextureId, image_delta: &egui::epaint::ImageDelta) {
+        let image_data = &image_delta.image;
+        
+        let (width, height) = (image_data.width(), image_data.height());
+        let dimensions = (width as u32, height as u32);
+        log::trace!(
+            "Updating texture {:?}: {}x{} at {:?}",
+            id,
+            width,
+            height,
+            image_delta.pos
+        );
+        
+        let mut texels = std::mem::take(&mut self.scratch_texels);
+        let (mut data_bytes, format): (&[u8], _) = match image_data {
+            egui::ImageData::Color(image) => {
+                assert_eq!(width * height, image.pixels.len(), "Mismatch between texture size and texel count");
+                (bytemuck::cast_slice(&image.pixels), vk::Format::R8G8B8A8_UNORM)
+            }
+            egui::ImageData::Font(image) => {
+                // a quarter of the RGBA texels; the view expands the coverage to all channels
+                assert_eq!(width * height, image.pixels.len(), "Mismatch between texture size and texel count");
+                texels.clear();
+                texels.extend(image.srgba_pixels(1.0).map(|color| color.a()));
+                (&texels, vk::Format::R8_UNORM)
+            }
+        };
+
+        let mut dimensions = dimensions;
+        let mut pos = image_delta.pos;
+        let downscaled;
+        let full_size = match pos {
+            Some(_) => self.downscaled_textures.get(&id).copied(),
+            None => Some(dimensions),
+        };
+        if let Some(full_size) = full_size {
+            let scaled_size = fit_texture_size(full_size, self.options.max_texture_dimension);
+            if scaled_size == full_size {
+                self.downscaled_textures.remove(&id);
+            } else {
+                if pos.is_none() {
+                    log::trace!("Downscaling {:?} from {:?} to {:?}", id, full_size, scaled_size);
+                    self.downscaled_textures.insert(id, full_size);
+                }
+                // a partial update covers the scaled texels its region overlaps
+                let [x, y] = pos.unwrap_or([0, 0]);
+                let scale = |position: usize, full_length: u32, scaled_length: u32| {
+                    (position as u64 * scaled_length as u64 / full_length as u64) as u32
+                };
+                let start = (scale(x, full_size.0, scaled_size.0), scale(y, full_size.1, scaled_size.1));
+                let end = (
+                    scale(x + width, full_size.0, scaled_size.0).clamp(start.0 + 1, scaled_size.0),
+                    scale(y + height, full_size.1, scaled_size.1).clamp(start.1 + 1, scaled_size.1),
+                );
+                let scaled_dimensions = (end.0 - start.0, end.1 - start.1);
+                downscaled = downscale_texels(data_bytes, dimensions, scaled_dimensions, texel_size(format) as usize);
+                data_bytes = &downscaled;
+                dimensions = scaled_dimensions;
+                pos = pos.map(|_| [start.0 as usize, start.1 as usize]);
+            }
+        }
+
+        let (texture, descriptor_set) = match TextureUpdate::plan(self.textures.contains_key(&id), image_delta) {
+            TextureUpdate::Unknown => {
+                log::warn!("Tried to update {:?}, which has not been allocated yet.", id);
+                self.scratch_texels = texels;
+                self.report_error(RuntimeError::UnknownTexture(id));
+                return;
+            }
+            TextureUpdate::Partial => {
+                // update the existing texture from a staging buffer no frame in flight reads;
+                // its descriptor set, which they may use, stays as it is. Frames in flight may
+                // also sample linear images, so the host does not write them in place.
+                let pos = pos.unwrap();
+                let (size, mut staging_buffer) = self.staging_buffer(data_bytes.len() as u64);
+                staging_buffer.upload_data(0, data_bytes);
+                let texture = &mut self.textures.get_mut(&id).unwrap().0;
+                texture.record_upload_from(
+                    &self.device,
+                    command_buffer,
+                    staging_buffer.buffer,
+                    (pos[0] as i32, pos[1] as i32),
+                    dimensions,
+                    self.frame_count,
+                );
+                let image = texture.image;
+                self.pending_staging_buffers.push((size, staging_buffer));
+                self.scratch_texels = texels;
+                self.audit(command_buffer, AuditEvent::Use(image));
+                return;
+            }
+            update => {
+                // allocate a new texture
+                let mut texture = VkTexture2D::<A>::new();
+                if self.options.unified_memory {
+                    texture.create_linear(&self.device, &self.allocator, dimensions, format);
+                } else {
+                    texture.create(&self.device, &self.allocator, dimensions, 1, format, self.options.memory_locations);
+                }
+
+                if update == TextureUpdate::Replace {
+                    // a full update of an existing texture (e.g. the font atlas after set_fonts) replaces it
+                    let (old_texture, old_descriptor_set) = self.textures.remove(&id).unwrap();
+                    self.defer_destruction(DeferredDestruction::Texture(old_texture));
+                    self.defer_destruction(DeferredDestruction::DescriptorSet(old_descriptor_set));
+                }
+                let descriptor_set = self.allocate_descriptor_set();
+                texture.upload_data(&self.device, command_buffer, data_bytes, (0, 0), dimensions, self.frame_count);
+                self.textures.insert(id, (texture, descriptor_set));
+                self.textures.get_mut(&id).expect("Failed to insert texture into hashmap")
+            }
+        };
+
+        let (view, layout) = (texture.view, texture.sampled_layout());
+        let descriptor_set = *descriptor_set;
+        let sampler = self.get_or_create_sampler(texture_sampler_options(
+            id,
+            &self.texture_samplers,
+            self.options.font_sampler,
+        ));
+        self.write_descriptor_set_in_layout(descriptor_set, view, sampler, layout);
+        self.scratch_texels = texels;
+        if let Some(image) = self.texture_image(id) {
+            self.audit(command_buffer, AuditEvent::Use(image));
+        }
+    }
+
+    // A free staging buffer of at least `size` bytes, with its size.
+    fn staging_buffer(&mut self, size: u64) -> (u64, VkStagingBuffer<A>) {
+        let fitting = self
+            .free_staging_buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, (buffer_size, _))| *buffer_size >= size)
+            .min_by_key(|(_, (buffer_size, _))| *buffer_size)
+            .map(|(index, _)| index);
+        if let Some(index) = fitting {
+            return self.free_staging_buffers.swap_remove(index);
+        }
+        let size = size.next_power_of_two();
+        let mut staging_buffer = VkStagingBuffer::new();
+        staging_buffer.create(
+            &self.device,
+            &self.allocator,
+            size,
+            self.options.memory_locations.staging,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+        );
+        (size, staging_buffer)
+    }
+
+    // Record `event` for the queue audit, if enabled.
+    fn audit(&mut self, command_buffer: vk::CommandBuffer, event: AuditEvent) {
+        if let Some(queue_audit) = self.queue_audit.as_mut() {
+            queue_audit.record(command_buffer, event);
+        }
+    }
+
+    /// Check the command buffers the integration recorded into against the queue family
+    /// they are submitted to, with [`IntegrationOptions::queue_audit`].
+    ///
+    /// Call it for every command buffer passed to [`paint`](Self::paint),
+    /// [`update_textures`](Self::update_textures) and the other recording methods, in
+    /// submission order. Images used on a queue family other than the one they were last
+    /// used on without an ownership transfer are logged as warnings. Does nothing without
+    /// the audit.
+    pub fn audit_submission(&mut self, command_buffer: vk::CommandBuffer, queue_family_index: u32) {
+        if let Some(queue_audit) = self.queue_audit.as_mut() {
+            for violation in queue_audit.submit(command_buffer, queue_family_index) {
+                log::warn!("queue audit: {}.", violation);
+            }
+        }
+    }
+
+    // The image drawn for `texture_id`, if the integration knows it.
+    fn texture_image(&self, texture_id: egui::TextureId) -> Option<vk::Image> {
+        if let Some(texture) = self.owned_texture(texture_id) {
+            return Some(texture.image);
+        }
+        match texture_id {
+            egui::TextureId::User(id) => self.user_texture_owners.get(&id).map(|&(image, _, _)| image),
+            egui::TextureId::Managed(_) => None,
+        }
+    }
+
+    // Report textures drawn while not in the layout the descriptor sets expect, i.e. before
+    // their upload was recorded.
+    #[cfg(feature = "strict")]
+    fn check_texture_layout(&self, texture_id: egui::TextureId) {
+        if let Some((texture, _)) = self.textures.get(&texture_id) {
+            if texture.layout != texture.sampled_layout() {
+                log::warn!(
+                    "strict: {:?} is drawn in layout {:?}, but its descriptor set expects {:?}.",
+                    texture_id,
+                    texture.layout,
+                    texture.sampled_layout()
+                );
+            }
+        }
+    }
+
+    // List the textures the application did not unregister or destroy. Managed textures are
+    // egui's to free, so they are expected to be left.
+    fn report_leaks(&self) {
+        let leaked = self
+            .registered_textures()
+            .filter(|info| info.kind != TextureKind::Managed)
+            .collect::<Vec<_>>();
+        if leaked.is_empty() {
+            return;
+        }
+        // streaming textures hold a descriptor set per image
+        let descriptor_sets = leaked.len() + self.streaming_textures.len();
+        let bytes = leaked.iter().map(|info| info.memory_bytes).sum::<u64>();
+        log::warn!(
+            "Destroying the renderer with {} textures that were not unregistered or destroyed ({} descriptor sets, {} bytes):",
+            leaked.len(),
+            descriptor_sets,
+            bytes
+        );
+        for info in leaked {
+            let size = match info.size {
+                Some((width, height)) => format!("{}x{}", width, height),
+                None => "unknown size".to_owned(),
+            };
+            log::warn!(
+                "  {:?} {:?}: {:?}, {}, {} bytes",
+                info.id,
+                self.texture_name(info.id).unwrap_or("(unnamed)"),
+                info.kind,
+                size,
+                info.memory_bytes
+            );
+        }
+    }
+
+    // Pop a recycled descriptor set or allocate a new one from the pool.
+    fn allocate_descriptor_set(&mut self) -> vk::DescriptorSet {
+        if let Some(descriptor_set) = self.free_descriptor_sets.pop() {
+            return descriptor_set;
+        }
+        let allocated = self.allocated_descriptor_sets + 1;
+        let max_sets = self.options.descriptor_pool.max_sets;
+        #[cfg(feature = "strict")]
+        if crosses_headroom(self.allocated_descriptor_sets, allocated, max_sets as usize) {
+            log::warn!(
+                "strict: {} of {} descriptor sets are allocated; textures registered or created without being unregistered or destroyed leak them.",
+                allocated, max_sets
+            );
+        }
+        self.allocated_descriptor_sets = allocated;
+        if let Some(descriptor_buffer) = self.descriptor_buffer.as_mut() {
+            return descriptor_buffer.allocate();
+        }
+        if above_headroom(allocated, max_sets as usize) {
+            self.warn_slow_path(SlowPath::DescriptorPool, || {
+                format!(
+                    "{} of {} descriptor sets are allocated; raise DescriptorPoolOptions::max_sets or unregister unused textures.",
+                    allocated, max_sets
+                )
+            });
+        }
+        // TODO: create more descriptor sets at once and add them to free_descriptor_sets to optimize
+        unsafe {
+            self.device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(self.descriptor_pool)
+                    .set_layouts(&[self.descriptor_set_layout]),
+            ).expect("Failed to create descriptor set for texture")[0]
+        }
+    }
+
+    // Log a warning about a hit of `slow_path`, at most once per interval, see SlowPathWarnings.
+    fn warn_slow_path(&mut self, slow_path: SlowPath, message: impl FnOnce() -> String) {
+        if let Some(hits) = self.slow_paths.hit(slow_path, Instant::now()) {
+            log::warn!("slow path ({}, {} hits since the last warning): {}", slow_path, hits, message());
+        }
+    }
+
+    // Bind the descriptor set of a texture for the following draws.
+    fn bind_descriptor_set(&self, command_buffer: vk::CommandBuffer, descriptor_set: vk::DescriptorSet) {
+        unsafe {
+            match &self.descriptor_buffer {
+                Some(descriptor_buffer) => (descriptor_buffer.fns.cmd_set_descriptor_buffer_offsets_ext)(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    self.texture_set(),
+                    1,
+                    &0,
+                    &descriptor_buffer.offset(descriptor_set),
+                ),
+                None => self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    self.texture_set(),
+                    &[descriptor_set],
+                    &[],
+                ),
+            }
+        }
+    }
+
+    fn texture_set(&self) -> u32 {
+        texture_descriptor(&self.options).0
+    }
+
+    // `spirv` of a fragment shader, sampling the texture at the set and binding of the texture
+    // descriptor sets.
+    fn fragment_shader(options: &IntegrationOptions, spirv: &'static [u8]) -> Cow<'static, [u8]> {
+        match texture_descriptor(options) {
+            (0, 0) => Cow::Borrowed(spirv),
+            (set, binding) => Cow::Owned(relocate_descriptor(spirv, set, binding)),
+        }
+    }
+
+    /// Push constant range of the egui pipeline layout, including the
+    /// [custom push constants](IntegrationOptions::custom_push_constants), for pipeline
+    /// layouts compatible with it, see [`DescriptorSetLayoutOptions`].
+    pub fn push_constant_range(&self) -> vk::PushConstantRange {
+        push_constant_range(&self.options)
+    }
+
+    // Point a descriptor set at an image view and sampler.
+    fn write_descriptor_set(&self, descriptor_set: vk::DescriptorSet, image_view: vk::ImageView, sampler: vk::Sampler) {
+        self.write_descriptor_set_in_layout(
+            descriptor_set,
+            image_view,
+            sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+    }
+
+    // Like write_descriptor_set, for an image sampled in `image_layout`.
+    fn write_descriptor_set_in_layout(
+        &self,
+        descriptor_set: vk::DescriptorSet,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+        image_layout: vk::ImageLayout,
+    ) {
+        unsafe {
+            let data = vk::DescriptorImageInfo::builder()
+                    .image_view(image_view)
+                    .image_layout(image_layout)
+                    .sampler(sampler)
+                    .build();
+            if let Some(descriptor_buffer) = &self.descriptor_buffer {
+                descriptor_buffer.write(&self.device, descriptor_set, &data);
+                return;
+            }
+
+            self.device.update_descriptor_set_with_template(
+                descriptor_set,
+                self.descriptor_update_template,
+                &data as *const _ as *const std::ffi::c_void,
+            );
+        }
+    }
+
+    /// Update swapchain.
+    ///
+    /// Pass `Some(scale_factor)` when the DPI changed together with the resize
+    /// (e.g. the window was moved to another monitor), so the screen rect and the
+    /// scissor math are updated in the same call. `None` keeps the current scale factor.
+    ///
+    /// The new swapchain may differ in extent, surface format and image count at once
+    /// (e.g. when switching to exclusive fullscreen); all per-image resources are recreated
+    /// to match. The device must be idle. The depth attachment, if any, is removed; set it
+    /// again for the new images.
+    ///
+    /// Both `*_SRGB` and `*_UNORM` surface formats show the same colors: the shaders encode
+    /// their output to sRGB for formats that do not do it on write.
+    ///
+    /// # Panics
+    /// Panics if the extent is zero or the swapchain has no images.
+    pub fn update_swapchain(
+        &mut self,
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: Option<f64>,
+        swapchain: vk::SwapchainKHR,
+        surface_format: vk::SurfaceFormatKHR,
+    ) {
+        // swap images
+        let swap_images = unsafe { self.swapchain_loader.get_swapchain_images(swapchain) }
+            .expect("Failed to get swapchain images.");
+
+        self.depth_attachment = None;
+        self.recreate_render_targets(
+            physical_width,
+            physical_height,
+            scale_factor,
+            &swap_images,
+            surface_format,
+            0,
+        );
+        self.swapchain_targets = true;
+    }
+
+    /// Make the paint methods fail with [`NeedsSwapchainUpdate`] until the next
+    /// [`update_swapchain`](Self::update_swapchain), e.g. when acquiring or presenting
+    /// reported `ERROR_OUT_OF_DATE_KHR` or `SUBOPTIMAL_KHR`.
+    ///
+    /// [`Integration`] calls it when the window is resized away from the size of the
+    /// swapchain.
+    pub fn mark_swapchain_out_of_date(&mut self) {
+        self.needs_swapchain_update = true;
+    }
+
+    // The window has been resized to the given physical size.
+    pub(crate) fn on_window_resized(&mut self, physical_width: u32, physical_height: u32) {
+        if self.swapchain_targets && (physical_width, physical_height) != (self.physical_width, self.physical_height) {
+            self.mark_swapchain_out_of_date();
+        }
+    }
+
+    /// Render into one layer of application owned images instead of the swapchain images.
+    ///
+    /// Use this to draw the UI into a cubemap face or a texture array layer, e.g. for in-world
+    /// screens. `paint(command_buffer, i, ..)` renders into `images[i]`.
+    /// As with swapchain images, `layer` must be in `COLOR_ATTACHMENT_OPTIMAL` layout
+    /// (`PRESENT_SRC_KHR` with [`IntegrationOptions::tile_based_gpu`]) when `paint` is
+    /// recorded and is left in `PRESENT_SRC_KHR` layout, unless
+    /// [`IntegrationOptions::target_layouts`] are set.
+    /// Call [`update_swapchain`](Self::update_swapchain) to render to the swapchain again.
+    /// The depth attachment, if any, is removed.
+    pub fn set_render_targets(
+        &mut self,
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: Option<f64>,
+        images: &[vk::Image],
+        format: vk::Format,
+        layer: u32,
+    ) {
+        self.depth_attachment = None;
+        self.recreate_render_targets(
+            physical_width,
+            physical_height,
+            scale_factor,
+            images,
+            vk::SurfaceFormatKHR {
+                format,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            },
+            layer,
+        );
+        self.swapchain_targets = false;
+    }
+
+    /// [`set_render_targets`](Self::set_render_targets) with [`TargetImage`]s, validated to
+    /// share one format, extent and layer.
+    ///
+    /// # Panics
+    /// Panics with the name of the first offending image if they do not.
+    pub fn set_target_images(&mut self, targets: &[TargetImage], scale_factor: Option<f64>) {
+        validate_target_images(targets);
+        let images = targets.iter().map(|target| target.image).collect::<Vec<_>>();
+        let first = &targets[0];
+        self.set_render_targets(
+            first.extent.width,
+            first.extent.height,
+            scale_factor,
+            &images,
+            first.format,
+            first.layer,
+        );
+    }
+
+    /// Share the render targets with queues of another queue family.
+    ///
+    /// With `Some(queue_family_index)` every [`paint`](Self::paint) acquires the target layer
+    /// from `queue_family_index` before rendering (in the layout `paint` expects it in) and
+    /// releases it back after the post-paint hook (in the layout `paint` leaves it in), e.g. for
+    /// sampling images set with [`set_render_targets`](Self::set_render_targets) on a compute
+    /// queue. The application must record the matching release before each `paint` and the
+    /// matching acquire after it on its queue.
+    pub fn set_render_target_queue_family(&mut self, queue_family_index: Option<u32>) {
+        self.render_target_queue_family = queue_family_index;
+    }
+
+    /// Write the depth of every UI pass into an application owned depth buffer.
+    ///
+    /// See [`DepthAttachment`]. `None` stops writing depth. Not supported together with
+    /// [`IntegrationOptions::ui_layer`], which renders the UI offscreen. The device must be idle.
+    ///
+    /// # Panics
+    /// Panics if there is not one view per render target image, no pass depth, or a stencil
+    /// mask with a format without stencil.
+    pub fn set_depth_attachment(&mut self, depth_attachment: Option<DepthAttachment>) {
+        if let Some(depth_attachment) = &depth_attachment {
+            assert_eq!(
+                depth_attachment.image_views.len(),
+                self.render_target_images.len(),
+                "One depth image view is needed per render target image."
+            );
+            assert!(!depth_attachment.pass_depths.is_empty(), "No pass depths.");
+            assert!(
+                depth_attachment.stencil.is_none() || format_has_stencil(depth_attachment.format),
+                "The stencil mask needs a depth attachment format with a stencil aspect."
+            );
+            if self.options.ui_layer.is_some() {
+                log::warn!("The UI layer is rendered offscreen; the depth attachment is ignored.");
+                return;
+            }
+        }
+        self.depth_attachment = depth_attachment;
+        let images = self.render_target_images.clone();
+        let surface_format = vk::SurfaceFormatKHR {
+            format: self.render_target_format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        self.recreate_render_targets(
+            self.physical_width,
+            self.physical_height,
+            None,
+            &images,
+            surface_format,
+            self.render_target_layer,
+        );
+    }
+
+    /// Give memory kept for reuse back to the driver.
+    ///
+    /// Frees the descriptor sets of released textures, including those of unregistered user
+    /// textures kept for reuse, back to the descriptor pool, drops
+    /// scratch buffers and trims the command pool of
+    /// [`IntegrationOptions::reuse_static_frames`]. Useful after a heavy session in a
+    /// long-running application. Slots of the
+    /// [`descriptor_buffer`](IntegrationOptions::descriptor_buffer) are kept. The device must
+    /// be idle.
+    pub fn trim_caches(&mut self) {
+        let evicted = self.user_textures.evict(u64::MAX);
+        self.free_descriptor_sets.extend(evicted);
+        if self.descriptor_buffer.is_none() && !self.free_descriptor_sets.is_empty() {
+            unsafe {
+                self.device
+                    .free_descriptor_sets(self.descriptor_pool, &self.free_descriptor_sets)
+                    .expect("Failed to free descriptor sets.");
+            }
+            self.allocated_descriptor_sets -= self.free_descriptor_sets.len();
+            self.free_descriptor_sets = vec![];
+        }
+        for (_, mut staging_buffer) in self.free_staging_buffers.drain(..) {
+            staging_buffer.destroy(&self.device, &self.allocator);
+        }
+        self.scratch_texels = vec![];
+        self.scratch_tiny_vertices = vec![];
+        self.scratch_tiny_indices = vec![];
+        self.scratch_composite_mesh = Default::default();
+        if self.command_pool != vk::CommandPool::null() {
+            unsafe {
+                self.device
+                    .trim_command_pool(self.command_pool, vk::CommandPoolTrimFlags::empty());
+            }
+        }
+    }
+
+    fn recreate_render_targets(
+        &mut self,
+        physical_width: u32,
+        physical_height: u32,
+        scale_factor: Option<f64>,
+        images: &[vk::Image],
+        surface_format: vk::SurfaceFormatKHR,
+        layer: u32,
+    ) {
+        validate_render_targets(physical_width, physical_height, images.len(), surface_format.format);
+        log::trace!(
+            "Recreating render targets: {} images of {}x{}, {:?}, layer {}",
+            images.len(),
+            physical_width,
+            physical_height,
+            surface_format.format,
+            layer
+        );
+
+        self.physical_width = physical_width;
+        self.physical_height = physical_height;
+        self.needs_swapchain_update = false;
+        if let Some(scale_factor) = scale_factor {
+            self.scale_factor = scale_factor;
+        }
+
+        // the render pass and pipelines only depend on the formats
+        let keep_pipelines = self.depth_attachment.is_none()
+            && self.options.ui_layer.is_none()
+            && self.target_pipelines_format == Some(surface_format.format);
+
+        // the pipelines of dynamic rendering are created for the format
+        self.render_target_format = surface_format.format;
+
+        // release vk objects to be regenerated.
+        self.destroy_ui_layer();
+        self.free_recorded_frames();
+        self.finish_pipeline_creation();
+        unsafe {
+            if !keep_pipelines {
+                self.device.destroy_render_pass(self.render_pass, None);
+                self.device.destroy_pipeline(self.pipeline, None);
+                if let Some(text_pipeline) = self.text_pipeline.take() {
+                    self.device.destroy_pipeline(text_pipeline, None);
+                }
+            }
+            for &image_view in self.framebuffer_color_image_views.iter() {
+                self.device.destroy_image_view(image_view, None);
+            }
+            for &framebuffer in self.framebuffers.iter() {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+
+        if !keep_pipelines {
+            // Recreate render pass for update surface format
+            self.render_pass = Self::create_target_render_pass(
+                &self.device,
+                surface_format,
+                &self.options,
+                self.depth_attachment.as_ref().map(|depth_attachment| depth_attachment.format),
+            );
+            self.create_target_pipelines();
+        }
+        self.target_pipelines_format = (self.depth_attachment.is_none() && self.options.ui_layer.is_none())
+            .then_some(surface_format.format);
+        let depth_attachment = self.depth_attachment.as_ref();
+
+        // Recreate color image views and framebuffers for new images
+        let (framebuffer_color_image_views, framebuffers) = Self::create_framebuffers(
+            &self.device,
+            images,
+            depth_attachment.map_or(&[][..], |depth_attachment| &depth_attachment.image_views),
+            self.render_pass,
+            surface_format,
+            layer,
+            physical_width,
+            physical_height,
+        );
+        self.framebuffer_color_image_views = framebuffer_color_image_views;
+        self.framebuffers = framebuffers;
+
+        // one set of mesh buffers per image, as the image count may have changed
+        for chain in self.mesh_buffers.drain(images.len().min(self.mesh_buffers.len())..) {
+            for buffers in chain {
+                buffers.destroy(&self.device, &self.allocator);
+            }
+        }
+        for buffers in self
+            .tiny_mesh_buffers
+            .drain(images.len().min(self.tiny_mesh_buffers.len())..)
+            .flatten()
+        {
+            buffers.destroy(&self.device, &self.allocator);
+        }
+        while self.mesh_buffers.len() < images.len() {
+            self.mesh_buffers.push(vec![MeshBuffers::new(
+                &self.device,
+                &self.allocator,
+                Self::vertex_buffer_size(),
+                Self::index_buffer_size(),
+                self.options.memory_locations.vertex,
+                self.options.memory_locations.index,
+            )]);
+        }
+
+        self.render_target_images = images.to_vec();
+        self.render_target_layer = layer;
+
+        self.create_ui_layer();
+    }
+
+    // What the pipelines drawing into the render target are created for.
+    fn target_pipeline_target(&self) -> PipelineTarget {
+        PipelineTarget::render_target(
+            self.render_pass,
+            self.render_target_format,
+            self.depth_attachment.as_ref().map(|depth_attachment| depth_attachment.format),
+        )
+    }
+
+    // Pipelines binding descriptors from the descriptor buffer must be created for it.
+    fn pipeline_create_flags(&self) -> vk::PipelineCreateFlags {
+        if self.descriptor_buffer.is_some() {
+            vk::PipelineCreateFlags::DESCRIPTOR_BUFFER_EXT
+        } else {
+            vk::PipelineCreateFlags::empty()
+        }
+    }
+
+    // Create the pipelines drawing into the render pass of the render target, on a thread with
+    // IntegrationOptions::background_pipeline_creation.
+    fn create_target_pipelines(&mut self) {
+        // a function pointer, so the thread does not depend on the allocator type
+        let create_pipeline: CreatePipelineFn = Self::create_pipeline;
+        let device = self.device.clone();
+        let (target, pipeline_layout, flags) = (self.target_pipeline_target(), self.pipeline_layout, self.pipeline_create_flags());
+        let premultiplied_alpha = self.options.premultiplied_alpha;
+        let variant = ShaderVariant::new(premultiplied_alpha, self.render_target_format);
+        let subpixel_text = self.options.subpixel_text && self.options.ui_layer.is_none();
+        let depth_attachment = self.depth_attachment.clone();
+        let (frag_spv, frag_subpixel_spv) = (
+            Self::fragment_shader(&self.options, FRAG_SPV),
+            Self::fragment_shader(&self.options, FRAG_SUBPIXEL_SPV),
+        );
+        let create = move || {
+            let create = |color_blend_attachment, fragment_shader| {
+                create_pipeline(
+                    &device,
+                    target,
+                    pipeline_layout,
+                    flags,
+                    variant,
+                    color_blend_attachment,
+                    depth_attachment.as_ref(),
+                    fragment_shader,
+                )
+            };
+            let pipeline = create(color_blend_attachment_state(premultiplied_alpha), &frag_spv);
+            let text_pipeline = subpixel_text
+                .then(|| create(subpixel_blend_attachment_state(premultiplied_alpha), &frag_subpixel_spv));
+            (pipeline, text_pipeline)
+        };
+        // the UI layer replaces the pipeline right away
+        if self.options.background_pipeline_creation && self.options.ui_layer.is_none() {
+            self.pipeline = vk::Pipeline::null();
+            self.text_pipeline = None;
+            self.pending_pipelines = Some(
+                std::thread::Builder::new()
+                    .name("egui pipeline creation".to_owned())
+                    .spawn(create)
+                    .expect("Failed to spawn pipeline creation thread."),
+            );
+        } else {
+            (self.pipeline, self.text_pipeline) = create();
+        }
+    }
+
+    // Wait for the pipelines created on a thread, if any.
+    fn finish_pipeline_creation(&mut self) {
+        if let Some(pending_pipelines) = self.pending_pipelines.take() {
+            (self.pipeline, self.text_pipeline) = pending_pipelines.join().expect("Failed to create pipelines.");
+        }
+    }
+
+    // Create the pipeline drawing the font atlas if IntegrationOptions::subpixel_text is set.
+    // The UI layer is blended as a whole, so it only gets grayscale text.
+    fn create_text_pipeline(&self) -> Option<vk::Pipeline> {
+        if !self.options.subpixel_text || self.options.ui_layer.is_some() {
+            return None;
+        }
+        Some(Self::create_pipeline(
+            &self.device,
+            self.target_pipeline_target(),
+            self.pipeline_layout,
+            self.pipeline_create_flags(),
+            ShaderVariant::new(self.options.premultiplied_alpha, self.render_target_format),
+            subpixel_blend_attachment_state(self.options.premultiplied_alpha),
+            self.depth_attachment.as_ref(),
+            &Self::fragment_shader(&self.options, FRAG_SUBPIXEL_SPV),
+        ))
+    }
+
+    // Create the UI layer if IntegrationOptions::ui_layer is set. The egui pipeline is
+    // recreated to render into the layer.
+    fn create_ui_layer(&mut self) {
+        let ui_layer_options = match self.options.ui_layer {
+            Some(ui_layer_options) => ui_layer_options,
+            None => return,
+        };
+        let extent = vk::Extent2D {
+            width: ((self.physical_width as f32 * ui_layer_options.resolution_scale).round() as u32).max(1),
+            height: ((self.physical_height as f32 * ui_layer_options.resolution_scale).round() as u32).max(1),
+        };
+
+        let render_pass = Self::create_render_pass(
+            &self.device,
+            ui_layer_options.format,
+            vk::AttachmentLoadOp::CLEAR,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            None,
+        );
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+        }
+        // the layer keeps coverage in alpha so it can be composited
+        self.pipeline = Self::create_pipeline(
+            &self.device,
+            PipelineTarget::RenderPass(render_pass),
+            self.pipeline_layout,
+            self.pipeline_create_flags(),
+            ShaderVariant::new(self.options.premultiplied_alpha, ui_layer_options.format),
+            color_blend_attachment_state(true),
+            None,
+            &Self::fragment_shader(&self.options, FRAG_SPV),
+        );
+        // layer texels are already what the egui pipeline outputs, so they are only converted
+        // where the layer and the target encode colors differently
+        let composite_pipeline = Self::create_pipeline(
+            &self.device,
+            self.target_pipeline_target(),
+            self.pipeline_layout,
+            self.pipeline_create_flags(),
+            composite_variant(ui_layer_options.format, self.render_target_format),
+            color_blend_attachment_state(self.options.premultiplied_alpha),
+            None,
+            &Self::fragment_shader(&self.options, FRAG_SPV),
+        );
+
+        let images = (0..self.framebuffer_color_image_views.len())
+            .map(|_| {
+                let image = unsafe {
+                    self.device.create_image(
+                        &vk::ImageCreateInfo::builder()
+                            .format(ui_layer_options.format)
+                            .initial_layout(vk::ImageLayout::UNDEFINED)
+                            .samples(vk::SampleCountFlags::TYPE_1)
+                            .tiling(vk::ImageTiling::OPTIMAL)
+                            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+                            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                            .image_type(vk::ImageType::TYPE_2D)
+                            .mip_levels(1)
+                            .array_layers(1)
+                            .extent(vk::Extent3D {
+                                width: extent.width,
+                                height: extent.height,
+                                depth: 1,
+                            }),
+                        None,
+                    )
+                }
+                .expect("Failed to create UI layer image.");
+                let allocation = self
+                    .allocator
+                    .allocate(image_allocation_create_info::<A>(&self.device, image, MemoryLocation::GpuOnly))
+                    .expect("Failed to create UI layer image.");
+                unsafe {
+                    self.device
+                        .bind_image_memory(image, allocation.memory(), allocation.offset())
+                        .expect("Failed to create UI layer image.");
+                }
+                let view = create_layer_view(&self.device, image, ui_layer_options.format, 0);
+                let framebuffer = unsafe {
+                    self.device.create_framebuffer(
+                        &vk::FramebufferCreateInfo::builder()
+                            .render_pass(render_pass)
+                            .attachments(&[view])
+                            .width(extent.width)
+                            .height(extent.height)
+                            .layers(1),
+                        None,
+                    )
+                }
+                .expect("Failed to create framebuffer.");
+                let descriptor_set = self.allocate_descriptor_set();
+                self.write_descriptor_set(descriptor_set, view, self.sampler);
+                UiLayerImage {
+                    image,
+                    allocation,
+                    view,
+                    framebuffer,
+                    descriptor_set,
+                }
+            })
+            .collect();
+
+        self.ui_layer = Some(UiLayer {
+            render_pass,
+            composite_pipeline,
+            extent,
+            images,
+        });
+    }
+
+    fn destroy_ui_layer(&mut self) {
+        if let Some(ui_layer) = self.ui_layer.take() {
+            for image in ui_layer.images {
+                unsafe {
+                    self.device.destroy_framebuffer(image.framebuffer, None);
+                    self.device.destroy_image_view(image.view, None);
+                    self.device.destroy_image(image.image, None);
+                }
+                self.allocator
+                    .free(image.allocation)
+                    .expect("Failed to free allocation");
+                self.free_descriptor_sets.push(image.descriptor_set);
+            }
+            unsafe {
+                self.device.destroy_pipeline(ui_layer.composite_pipeline, None);
+                self.device.destroy_render_pass(ui_layer.render_pass, None);
+            }
+        }
+    }
+
+    /// Set a hook that [`paint`](Self::paint) calls after the egui render pass.
+    ///
+    /// The hook can record more commands into the command buffer, e.g. a post effect on the
+    /// UI. See [`PaintTarget`] for the state the target is in.
+    pub fn set_post_paint_hook(&mut self, hook: impl FnMut(&Device, &PaintTarget) + Send + 'static) {
+        self.post_paint_hook = Some(Box::new(hook));
+    }
+
+    /// Remove the hook set with [`set_post_paint_hook`](Self::set_post_paint_hook).
+    pub fn clear_post_paint_hook(&mut self) {
+        self.post_paint_hook = None;
+    }
+
+    /// Add a callback that [`paint_layered`](Self::paint_layered) calls before the layers of
+    /// `order` and above are drawn.
+    ///
+    /// E.g. [`egui::Order::Foreground`] for world space gizmos drawn over the panels and
+    /// windows but under popups and tooltips. The callback can record commands like the
+    /// `between_passes` of [`paint_passes`](Self::paint_passes). Callbacks of the same order
+    /// are called in the order they were added.
+    pub fn add_layer_callback(
+        &mut self,
+        order: egui::Order,
+        callback: impl FnMut(&Device, &PaintTarget) + Send + 'static,
+    ) {
+        let index = self.layer_callbacks.partition_point(|&(callback_order, _)| callback_order <= order);
+        self.layer_callbacks.insert(index, (order, Box::new(callback)));
+    }
+
+    /// Remove the callbacks added with [`add_layer_callback`](Self::add_layer_callback).
+    pub fn clear_layer_callbacks(&mut self) {
+        self.layer_callbacks.clear();
+    }
+
+    /// The orders of the layer callbacks, ascending: the boundaries to split the shapes of a
+    /// frame at with [`LayeredShapes::split_before`].
+    pub fn layer_callback_orders(&self) -> Vec<egui::Order> {
+        self.layer_callbacks.iter().map(|&(order, _)| order).collect()
+    }
+
+    /// Set a hook called with the recoverable problems of painting, see [`RuntimeError`].
+    ///
+    /// They are logged too.
+    pub fn set_error_hook(&mut self, hook: impl FnMut(&RuntimeError) + Send + 'static) {
+        self.error_hook = Some(Box::new(hook));
+    }
+
+    /// Remove the hook set with [`set_error_hook`](Self::set_error_hook).
+    pub fn clear_error_hook(&mut self) {
+        self.error_hook = None;
+    }
+
+    fn report_error(&mut self, error: RuntimeError) {
+        if let Some(hook) = self.error_hook.as_mut() {
+            hook(&error);
+        }
+    }
+
+    /// Registering user texture.
+    ///
+    /// Pass the Vulkan ImageView and Sampler.
+    /// `image_view`'s image layout must be `SHADER_READ_ONLY_OPTIMAL`.
+    ///
+    /// The same `image_view` can be registered with different samplers (e.g. NEAREST for
+    /// a minimap and LINEAR for a thumbnail); each pair gets its own TextureId.
+    /// Registering a pair that is already registered returns the existing TextureId
+    /// without allocating another descriptor set; it is then registered until unregistered
+    /// as often as it was registered.
+    ///
+    /// UserTexture needs to be unregistered when it is no longer needed. Its descriptor set
+    /// is kept for a couple of frames after that, so immediate-mode code registering and
+    /// unregistering the same pair every frame gets back the same TextureId without
+    /// allocating. Keep the image view alive until those frames have been painted, or call
+    /// [`trim_caches`](Self::trim_caches) before reusing its handle.
+    ///
+    /// # Example
+    /// ```sh
+    /// cargo run --example user_texture
+    /// ```
+    /// [The example for user texture is in examples directory](https://github.com/MatchaChoco010/egui_winit_ash_vk_mem/tree/main/examples/user_texture)
+    pub fn register_user_texture(
+        &mut self,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> egui::TextureId {
+        if let Some(id) = self.user_textures.acquire(image_view, sampler) {
+            return egui::TextureId::User(id);
+        }
+
+        let descriptor_set = self.allocate_descriptor_set();
+        self.write_descriptor_set(descriptor_set, image_view, sampler);
+        let id = self.user_textures.insert(image_view, sampler, descriptor_set);
+
+        egui::TextureId::User(id)
+    }
+
+    /// Register a [`UserTexture`], validated against its metadata.
+    ///
+    /// Like [`register_user_texture`](Self::register_user_texture), but the texture may be
+    /// sampled in any read only layout, and its name, format and size are reported by
+    /// [`registered_textures`](Self::registered_textures).
+    ///
+    /// # Panics
+    /// Panics with the name of the texture if it is empty, not a color format or in a layout
+    /// that cannot be sampled.
+    pub fn register_texture(&mut self, texture: &UserTexture) -> egui::TextureId {
+        texture.validate();
+        let id = match self.user_textures.acquire(texture.image_view, texture.sampler) {
+            Some(id) => id,
+            None => {
+                let descriptor_set = self.allocate_descriptor_set();
+                self.write_descriptor_set_in_layout(descriptor_set, texture.image_view, texture.sampler, texture.layout);
+                self.user_textures.insert(texture.image_view, texture.sampler, descriptor_set)
+            }
+        };
+        self.user_texture_metadata.insert(id, (texture.format, texture.extent));
+        if let Some(image) = texture.image {
+            let subresource_range = vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .level_count(vk::REMAINING_MIP_LEVELS)
+                .layer_count(vk::REMAINING_ARRAY_LAYERS)
+                .build();
+            self.user_texture_images.insert(id, (image, subresource_range));
+        }
+        if let Some(name) = &texture.name {
+            self.set_texture_name(egui::TextureId::User(id), name);
+        }
+        egui::TextureId::User(id)
+    }
+
+    /// Registering user texture with a sampler created and owned by the integration.
+    ///
+    /// Like [`register_user_texture`](Self::register_user_texture); textures registered with
+    /// the same `sampler_options` share the sampler, which lives until
+    /// [`destroy`](Self::destroy).
+    ///
+    /// # Panics
+    /// Panics if `min_lod` is greater than `max_lod`.
+    pub fn register_user_texture_with_sampler(
+        &mut self,
+        image_view: vk::ImageView,
+        sampler_options: SamplerOptions,
+    ) -> egui::TextureId {
+        let sampler = self.get_or_create_sampler(sampler_options);
+        self.register_user_texture(image_view, sampler)
+    }
+
+    /// Registering a region of an image view, e.g. one sprite of an atlas, as its own user texture.
+    ///
+    /// `uv_rect` is the region in normalized UV coordinates of the view; meshes drawing the
+    /// returned TextureId with UVs from (0, 0) to (1, 1) show just the region, without a new
+    /// image view or copy. Unlike [`register_user_texture`](Self::register_user_texture), every
+    /// call returns a new TextureId, so several regions of a view can be registered. The
+    /// sampler is that of [`register_user_texture_with_sampler`](Self::register_user_texture_with_sampler);
+    /// its [`lod_bias`](SamplerOptions::lod_bias) sets the mip bias of the region. Linear
+    /// filtering samples texels next to the region at its edges, so pad the sprites of an
+    /// atlas.
+    ///
+    /// The UVs are mapped in the frames painted by [`paint`](Self::paint) and its variants,
+    /// not in offscreen panels.
+    pub fn register_user_texture_region(
+        &mut self,
+        image_view: vk::ImageView,
+        sampler_options: SamplerOptions,
+        uv_rect: egui::Rect,
+    ) -> egui::TextureId {
+        let sampler = self.get_or_create_sampler(sampler_options);
+        let descriptor_set = self.allocate_descriptor_set();
+        self.write_descriptor_set(descriptor_set, image_view, sampler);
+        let id = self.user_textures.insert_unshared(image_view, sampler, descriptor_set);
+        self.user_texture_uv_rects.insert(id, uv_rect);
+        egui::TextureId::User(id)
+    }
+
+    /// Get the sampler of the integration for `sampler_options`, creating it on first use.
+    ///
+    /// Lets paint callbacks and other application draws reuse the samplers egui textures are
+    /// drawn with instead of creating duplicates, e.g. under a tight `maxSamplerAllocationCount`.
+    /// The default options give the sampler of egui's own textures. The sampler is owned by
+    /// the integration and lives until [`destroy`](Self::destroy).
+    ///
+    /// # Panics
+    /// Panics if `min_lod` is greater than `max_lod`.
+    pub fn get_or_create_sampler(&mut self, sampler_options: SamplerOptions) -> vk::Sampler {
+        assert!(
+            sampler_options.min_lod <= sampler_options.max_lod,
+            "min_lod must not be greater than max_lod."
+        );
+        if sampler_options == SamplerOptions::default() {
+            return self.sampler;
+        }
+        if let Some(&(_, sampler)) = self.user_samplers.iter().find(|(options, _)| *options == sampler_options) {
+            return sampler;
+        }
+        let anisotropy = sampler_anisotropy(sampler_options.anisotropy, self.options.max_sampler_anisotropy);
+        let sampler = unsafe {
+            self.device.create_sampler(
+                &vk::SamplerCreateInfo::builder()
+                    .address_mode_u(sampler_options.address_mode)
+                    .address_mode_v(sampler_options.address_mode)
+                    .address_mode_w(sampler_options.address_mode)
+                    .anisotropy_enable(anisotropy.is_some())
+                    .max_anisotropy(anisotropy.unwrap_or(1.0))
+                    .min_filter(sampler_options.filter)
+                    .mag_filter(sampler_options.filter)
+                    .mipmap_mode(sampler_mipmap_mode(sampler_options.filter))
+                    .min_lod(sampler_options.min_lod)
+                    .max_lod(sampler_options.max_lod)
+                    .mip_lod_bias(sampler_options.lod_bias),
+                None,
+            )
+        }
+        .expect("Failed to create sampler.");
+        self.user_samplers.push((sampler_options, sampler));
+        sampler
+    }
+
+    /// Draw a texture of the integration, e.g. one egui created with
+    /// [`egui::Context::load_texture`], with a sampler for `sampler_options` instead of the
+    /// default linear clamping one.
+    ///
+    /// egui 0.18 sends no sampling options with its textures, so pixel art or tiled patterns
+    /// pick theirs here. Also replaces [`IntegrationOptions::font_sampler`] for
+    /// [`FONT_TEXTURE_ID`]. Applies to the current texture of `texture_id` and to the ones
+    /// egui sends for it later, until egui frees it.
+    ///
+    /// # Panics
+    /// Panics if `min_lod` is greater than `max_lod`.
+    pub fn set_texture_sampler(&mut self, texture_id: egui::TextureId, sampler_options: SamplerOptions) {
+        let sampler = self.get_or_create_sampler(sampler_options);
+        self.texture_samplers.insert(texture_id, sampler_options);
+        let Some((texture, descriptor_set)) = self.textures.get(&texture_id) else {
+            return;
+        };
+        // frames in flight may use the current descriptor set
+        let (view, layout, old_descriptor_set) = (texture.view, texture.sampled_layout(), *descriptor_set);
+        let descriptor_set = self.allocate_descriptor_set();
+        self.write_descriptor_set_in_layout(descriptor_set, view, sampler, layout);
+        self.textures.get_mut(&texture_id).unwrap().1 = descriptor_set;
+        self.defer_destruction(DeferredDestruction::DescriptorSet(old_descriptor_set));
+    }
+
+    /// Registering a user texture whose image is owned by another queue family.
+    ///
+    /// Like [`register_user_texture`](Self::register_user_texture), but every
+    /// [`paint`](Self::paint) acquires `subresource_range` of `image` from `queue_family_index`
+    /// before drawing and releases it back afterwards, both in `SHADER_READ_ONLY_OPTIMAL`
+    /// layout. The application must record the matching release before each `paint` and the
+    /// matching acquire after it on its queue.
+    /// No barriers are recorded if `queue_family_index` is
+    /// [`IntegrationOptions::queue_family_index`].
+    pub fn register_user_texture_from_queue_family(
+        &mut self,
+        image: vk::Image,
+        image_view: vk::ImageView,
+        subresource_range: vk::ImageSubresourceRange,
+        sampler: vk::Sampler,
+        queue_family_index: u32,
+    ) -> egui::TextureId {
+        let texture_id = self.register_user_texture(image_view, sampler);
+        if let egui::TextureId::User(id) = texture_id {
+            self.user_texture_owners
+                .insert(id, (image, subresource_range, queue_family_index));
+        }
+        texture_id
+    }
+
+    /// Registering a single layer of an array image as user texture.
+    ///
+    /// A 2D view of `layer` is created and owned by the integration, and destroyed by
+    /// [`unregister_user_texture`](Self::unregister_user_texture).
+    /// `image`'s layout must be `SHADER_READ_ONLY_OPTIMAL`.
+    pub fn register_user_texture_layer(
+        &mut self,
+        image: vk::Image,
+        format: vk::Format,
+        layer: u32,
+        sampler: vk::Sampler,
+    ) -> egui::TextureId {
+        let image_view = create_layer_view(&self.device, image, format, layer);
+        let texture_id = self.register_user_texture(image_view, sampler);
+        if let egui::TextureId::User(id) = texture_id {
+            self.user_texture_views.insert(id, image_view);
+            let subresource_range = vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .level_count(1)
+                .base_array_layer(layer)
+                .layer_count(1)
+                .build();
+            self.user_texture_images.insert(id, (image, subresource_range));
+        }
+        texture_id
+    }
+
+    /// Record a barrier moving user texture `texture_id` from `old_layout` to `new_layout`,
+    /// e.g. to render into it and then show it in egui.
+    ///
+    /// `stage_mask` holds the stages of the application's accesses; the side of the barrier
+    /// in a sampled layout (`SHADER_READ_ONLY_OPTIMAL` or `READ_ONLY_OPTIMAL`) is the fragment
+    /// shader of egui. The access masks follow from the layouts. Only the images of user
+    /// textures registered with an image are known:
+    /// [`register_texture`](Self::register_texture) with [`UserTexture::image`],
+    /// [`register_user_texture_from_queue_family`](Self::register_user_texture_from_queue_family)
+    /// and [`register_user_texture_layer`](Self::register_user_texture_layer); nothing is
+    /// recorded for others.
+    pub fn transition_user_texture(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        texture_id: egui::TextureId,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        stage_mask: vk::PipelineStageFlags2,
+    ) {
+        let (image, subresource_range) = match self.user_texture_image(texture_id) {
+            Some(image) => image,
+            None => {
+                log::warn!("The image of {:?} is not known; no barrier is recorded.", texture_id);
+                return;
+            }
+        };
+        unsafe {
+            self.device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::builder().image_memory_barriers(&[user_texture_barrier(
+                    image,
+                    subresource_range,
+                    old_layout,
+                    new_layout,
+                    stage_mask,
+                )]),
+            );
+        }
+        self.audit(command_buffer, AuditEvent::Use(image));
+    }
+
+    fn user_texture_image(&self, texture_id: egui::TextureId) -> Option<(vk::Image, vk::ImageSubresourceRange)> {
+        let id = match texture_id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Managed(_) => return None,
+        };
+        self.user_texture_images.get(&id).copied().or_else(|| {
+            self.user_texture_owners
+                .get(&id)
+                .map(|&(image, subresource_range, _)| (image, subresource_range))
+        })
+    }
+
+    /// Create an array texture with one TextureId per layer.
+    ///
+    /// Each element of `layers` holds the tightly packed RGBA8 (sRGB, premultiplied) texels of
+    /// one layer. They are uploaded in the next [`paint`](Self::paint).
+    pub fn create_array_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        layers: &[&[u8]],
+    ) -> Vec<egui::TextureId> {
+        let layer_size = (width * height * 4) as usize;
+        let mut texture = VkTexture2D::<A>::new();
+        texture.create(
+            &self.device,
+            &self.allocator,
+            (width, height),
+            layers.len() as u32,
+            vk::Format::R8G8B8A8_UNORM,
+            self.options.memory_locations,
+        );
+        texture.destroy_staging_buffer(&self.device, &self.allocator);
+
+        let mut layer_views = vec![];
+        let mut ids = vec![];
+        let mut texels = Vec::with_capacity(layer_size * layers.len());
+        for (layer, data) in layers.iter().enumerate() {
+            assert_eq!(data.len(), layer_size, "Mismatch between array texture size and layer data length");
+            texels.extend_from_slice(data);
+
+            let image_view = create_layer_view(&self.device, texture.image, vk::Format::R8G8B8A8_UNORM, layer as u32);
+            let descriptor_set = self.allocate_descriptor_set();
+            self.write_descriptor_set(descriptor_set, image_view, self.sampler);
+            layer_views.push(image_view);
+            ids.push(self.user_textures.insert(image_view, self.sampler, descriptor_set));
+        }
+
+        let texture_ids = ids.iter().map(|&id| egui::TextureId::User(id)).collect();
+        self.array_textures.push(ArrayTexture {
+            texture,
+            layer_views,
+            ids,
+            texels,
+            pending_upload: true,
+        });
+        texture_ids
+    }
+
+    /// Replace the contents of the array texture layer shown by `texture_id`.
+    ///
+    /// The new texels are uploaded in the next [`paint`](Self::paint).
+    pub fn update_array_texture_layer(&mut self, texture_id: egui::TextureId, data: &[u8]) {
+        let (array_texture, layer) = match self.find_array_texture(texture_id) {
+            Some((index, layer)) => (&mut self.array_textures[index], layer),
+            None => {
+                log::warn!("This TextureId is not a layer of an array texture: {:?}", texture_id);
+                return;
+            }
+        };
+        let layer_size = (array_texture.texture.size.0 * array_texture.texture.size.1 * 4) as usize;
+        assert_eq!(data.len(), layer_size, "Mismatch between array texture size and layer data length");
+        // frames in flight may still read the staging buffer of an earlier update
+        array_texture.texels[layer * layer_size..(layer + 1) * layer_size].copy_from_slice(data);
+        array_texture.pending_upload = true;
+    }
+
+    /// Destroy the array texture `texture_id` is a layer of, together with the TextureIds of all its layers.
+    pub fn destroy_array_texture(&mut self, texture_id: egui::TextureId) {
+        if let Some((index, _)) = self.find_array_texture(texture_id) {
+            let array_texture = self.array_textures.swap_remove(index);
+            for id in array_texture.ids {
+                self.texture_names.remove(&egui::TextureId::User(id));
+                self.last_draw_frames.remove(&egui::TextureId::User(id));
+                if let Some(descriptor_set) = self.user_textures.remove(id) {
+                    self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+                }
+            }
+            for image_view in array_texture.layer_views {
+                self.defer_destruction(DeferredDestruction::ImageView(image_view));
+            }
+            self.defer_destruction(DeferredDestruction::Texture(array_texture.texture));
+        }
+    }
+
+    // Index of the array texture and layer shown by `texture_id`.
+    fn find_array_texture(&self, texture_id: egui::TextureId) -> Option<(usize, usize)> {
+        let id = match texture_id {
+            egui::TextureId::User(id) => id,
+            egui::TextureId::Managed(_) => return None,
+        };
+        self.array_textures.iter().enumerate().find_map(|(index, array_texture)| {
+            array_texture
+                .ids
+                .iter()
+                .position(|&layer_id| layer_id == id)
+                .map(|layer| (index, layer))
+        })
+    }
+
+    /// Get a [`TextureQueue`] to register textures from other threads.
+    pub fn texture_queue(&self) -> TextureQueue {
+        TextureQueue {
+            sender: self.texture_queue_sender.clone(),
+            next_id: self.user_textures.next_id.clone(),
+        }
+    }
+
+    // Returns whether any registration was applied.
+    fn apply_texture_queue(&mut self, command_buffer: vk::CommandBuffer) -> bool {
+        let mut applied = false;
+        while let Ok(registration) = self.texture_queue_receiver.try_recv() {
+            applied = true;
+            match registration {
+                QueuedRegistration::UserTexture {
+                    id,
+                    image_view,
+                    sampler,
+                } => {
+                    let descriptor_set = self.allocate_descriptor_set();
+                    self.write_descriptor_set(descriptor_set, image_view, sampler);
+                    self.user_textures
+                        .insert_with_id(id, image_view, sampler, descriptor_set);
+                }
+                QueuedRegistration::Texture {
+                    id,
+                    width,
+                    height,
+                    data,
+                } => {
+                    let size = fit_texture_size((width, height), self.options.max_texture_dimension);
+                    let data = if size == (width, height) {
+                        data
+                    } else {
+                        log::trace!("Downscaling user texture {} from {:?} to {:?}", id, (width, height), size);
+                        self.downscaled_textures.insert(egui::TextureId::User(id), (width, height));
+                        downscale_texels(&data, (width, height), size, 4)
+                    };
+                    let mut texture = VkTexture2D::<A>::new();
+                    texture.create(&self.device, &self.allocator, size, 1, vk::Format::R8G8B8A8_UNORM, self.options.memory_locations);
+                    texture.upload_data(&self.device, command_buffer, &data, (0, 0), size, self.frame_count);
+                    let descriptor_set = self.allocate_descriptor_set();
+                    self.write_descriptor_set(descriptor_set, texture.view, self.sampler);
+                    self.textures
+                        .insert(egui::TextureId::User(id), (texture, descriptor_set));
+                }
+                QueuedRegistration::Unregister { id } => {
+                    self.unregister_user_texture(egui::TextureId::User(id));
+                }
+            }
+        }
+        applied
+    }
+
+    /// Unregister user texture.
+    ///
+    /// The internal texture (egui::TextureId::Managed) cannot be unregistered.
+    pub fn unregister_user_texture(&mut self, texture_id: egui::TextureId) {
+        if let egui::TextureId::User(id) = texture_id {
+            self.texture_names.remove(&texture_id);
+            self.last_draw_frames.remove(&texture_id);
+            self.downscaled_textures.remove(&texture_id);
+            if let Some((texture, descriptor_set)) = self.textures.remove(&texture_id) {
+                // created through a TextureQueue
+                self.defer_destruction(DeferredDestruction::Texture(texture));
+                self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+            } else if let Some(image_view) = self.user_texture_views.remove(&id) {
+                // the view of a layer is destroyed, so its descriptor set is not kept
+                if let Some(descriptor_set) = self.user_textures.remove(id) {
+                    self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+                }
+                self.user_texture_owners.remove(&id);
+                self.user_texture_metadata.remove(&id);
+                self.user_texture_images.remove(&id);
+                self.user_texture_uv_rects.remove(&id);
+                self.defer_destruction(DeferredDestruction::ImageView(image_view));
+            } else {
+                match self.user_textures.release(id, self.frame_count) {
+                    Some(Release::Registered) | None => return,
+                    Some(Release::Cached) => {}
+                    Some(Release::Removed(descriptor_set)) => {
+                        self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+                    }
+                }
+                self.user_texture_owners.remove(&id);
+                self.user_texture_metadata.remove(&id);
+                self.user_texture_images.remove(&id);
+                self.user_texture_uv_rects.remove(&id);
+            }
+        } else {
+            log::warn!("The internal texture cannot be unregistered; please pass the texture ID of UserTexture.");
+        }
+    }
+
+    /// Create a [`StreamingTexture`] of `width` x `height` texels.
+    ///
+    /// The returned TextureId can be used like a user texture; meshes using it are skipped
+    /// until the first [`StreamingTexture::update`] has been painted.
+    /// Use [`streaming_texture_mut`](Self::streaming_texture_mut) to update it.
+    pub fn create_streaming_texture(&mut self, width: u32, height: u32) -> egui::TextureId {
+        let mut create_slot = || {
+            let mut texture = VkTexture2D::<A>::new();
+            texture.create(&self.device, &self.allocator, (width, height), 1, vk::Format::R8G8B8A8_UNORM, self.options.memory_locations);
+            texture.destroy_staging_buffer(&self.device, &self.allocator);
+            let descriptor_set = self.allocate_descriptor_set();
+            self.write_descriptor_set(descriptor_set, texture.view, self.sampler);
+            (texture, descriptor_set)
+        };
+        let slots = [create_slot(), create_slot()];
+
+        let id = self.user_textures.next_id();
+        self.streaming_textures.insert(
+            id,
+            StreamingTexture {
+                size: (width, height),
+                slots,
+                front: None,
+                pending: None,
+                texels: vec![],
+            },
+        );
+
+        egui::TextureId::User(id)
+    }
+
+    /// Get the [`StreamingTexture`] created with [`create_streaming_texture`](Self::create_streaming_texture).
+    pub fn streaming_texture_mut(
+        &mut self,
+        texture_id: egui::TextureId,
+    ) -> Option<&mut StreamingTexture<A>> {
+        match texture_id {
+            egui::TextureId::User(id) => self.streaming_textures.get_mut(&id),
+            egui::TextureId::Managed(_) => None,
+        }
+    }
+
+    /// Destroy a [`StreamingTexture`] and release its images.
+    pub fn destroy_streaming_texture(&mut self, texture_id: egui::TextureId) {
+        if let egui::TextureId::User(id) = texture_id {
+            if let Some(streaming_texture) = self.streaming_textures.remove(&id) {
+                self.texture_names.remove(&texture_id);
+                self.last_draw_frames.remove(&texture_id);
+                for (texture, descriptor_set) in streaming_texture.slots {
+                    self.defer_destruction(DeferredDestruction::Texture(texture));
+                    self.defer_destruction(DeferredDestruction::DescriptorSet(descriptor_set));
+                }
+            }
+        }
+    }
+
+    /// destroy vk objects.
+    ///
+    /// Textures that were not unregistered or destroyed are reported as warnings, with the names
+    /// given by [`set_texture_name`](Self::set_texture_name).
+    ///
+    /// # Safety
+    /// This method release vk objects memory that is not managed by Rust.
+    pub unsafe fn destroy(&mut self) {
+        self.report_leaks();
+        self.destroy_deferred(|_| true);
+        for (_, readback) in self.readbacks.drain() {
+            if let PendingReadback::InFlight { mut buffer, .. } = readback {
+                buffer.destroy(&self.device, &self.allocator);
+            }
+        }
+        self.finish_pipeline_creation();
+        // self.device
+        //     .destroy_descriptor_set_layout(self.user_texture_layout, None);
+        // self.font_texture.destroy(&self.device, &self.allocator);
+        for (_, (mut texture, _)) in self.textures.drain() {
+            texture.destroy(&self.device, &self.allocator);
+        }
+        for (_, image_view) in self.user_texture_views.drain() {
+            self.device.destroy_image_view(image_view, None);
+        }
+        for mut array_texture in self.array_textures.drain(..) {
+            for image_view in array_texture.layer_views {
+                self.device.destroy_image_view(image_view, None);
+            }
+            array_texture.texture.destroy(&self.device, &self.allocator);
+        }
+        for (_, streaming_texture) in self.streaming_textures.drain() {
+            for (mut texture, _) in streaming_texture.slots {
+                texture.destroy(&self.device, &self.allocator);
+            }
+        }
+        
+        for (_, mut staging_buffer) in self.free_staging_buffers.drain(..).chain(self.pending_staging_buffers.drain(..)) {
+            staging_buffer.destroy(&self.device, &self.allocator);
+        }
+        for mesh_buffers in self.tiny_mesh_buffers.drain(..).flatten() {
+            mesh_buffers.destroy(&self.device, &self.allocator);
+        }
+        for mesh_buffers in self.mesh_buffers.drain(..) {
+            for mesh_buffers in mesh_buffers {
+                mesh_buffers.destroy(&self.device, &self.allocator);
+            }
+        }
+        self.destroy_ui_layer();
+        for (_, panel) in std::mem::take(&mut self.offscreen_panels) {
+            self.destroy_offscreen_panel(panel);
+        }
+        if let Some((render_pass, pipeline)) = self.offscreen_panel_pipeline.take() {
+            self.device.destroy_pipeline(pipeline, None);
+            self.device.destroy_render_pass(render_pass, None);
+        }
+        for &image_view in self.framebuffer_color_image_views.iter() {
+            self.device.destroy_image_view(image_view, None);
+        }
+        for &framebuffer in self.framebuffers.iter() {
+            self.device.destroy_framebuffer(framebuffer, None);
+        }
+        self.device.destroy_render_pass(self.render_pass, None);
+        self.device.destroy_sampler(self.sampler, None);
+        for (_, sampler) in self.user_samplers.drain(..) {
+            self.device.destroy_sampler(sampler, None);
+        }
+        self.device.destroy_pipeline(self.pipeline, None);
+        if let Some(text_pipeline) = self.text_pipeline.take() {
+            self.device.destroy_pipeline(text_pipeline, None);
+        }
+        self.recorded_frames.clear();
+        if self.frame_command_pool != vk::CommandPool::null() {
+            // frees the command buffers too
+            self.device.destroy_command_pool(self.frame_command_pool, None);
+        }
+        if self.command_pool != vk::CommandPool::null() {
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
+        self.device
+            .destroy_pipeline_layout(self.pipeline_layout, None);
+        if self.options.descriptor_set_layout.is_none() {
+            self.device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    // for &descriptor_set_layout in self.descriptor_set_layouts.iter() {
+        //     self.device
+        //         .destroy_descriptor_set_layout(descriptor_set_layout, None);
+        // }
+        self.device
+            .destroy_descriptor_pool(self.descriptor_pool, None);
+        self.device
+            .destroy_descriptor_update_template(self.descriptor_update_template, None);
+        if let Some(descriptor_buffer) = self.descriptor_buffer.take() {
+            descriptor_buffer.destroy(&self.device, &self.allocator);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factor(factor: vk::BlendFactor, src: [f32; 4], dst: [f32; 4]) -> f32 {
+        match factor {
+            vk::BlendFactor::ZERO => 0.0,
+            vk::BlendFactor::ONE => 1.0,
+            vk::BlendFactor::SRC_ALPHA => src[3],
+            vk::BlendFactor::ONE_MINUS_SRC_ALPHA => 1.0 - src[3],
+            vk::BlendFactor::ONE_MINUS_DST_ALPHA => 1.0 - dst[3],
+            _ => unimplemented!("{:?}", factor),
+        }
+    }
+
+    /// Evaluate the fixed function blending (with `ADD` ops) on the CPU.
+    fn blend(state: vk::PipelineColorBlendAttachmentState, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+        let src_color = factor(state.src_color_blend_factor, src, dst);
+        let dst_color = factor(state.dst_color_blend_factor, src, dst);
+        let src_alpha = factor(state.src_alpha_blend_factor, src, dst);
+        let dst_alpha = factor(state.dst_alpha_blend_factor, src, dst);
+        [
+            src[0] * src_color + dst[0] * dst_color,
+            src[1] * src_color + dst[1] * dst_color,
+            src[2] * src_color + dst[2] * dst_color,
+            src[3] * src_alpha + dst[3] * dst_alpha,
+        ]
+    }
+
+    /// The linear premultiplied color the shaders output for `color`.
+    fn premultiplied(color: egui::Color32) -> [f32; 4] {
+        egui::Rgba::from(color).to_array()
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-3, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn translucent_window_keeps_coverage_on_transparent_target() {
+        let window_fill = egui::Visuals::dark().window_fill().linear_multiply(0.5);
+        let src = premultiplied(window_fill);
+        let transparent = [0.0; 4];
+
+        let result = blend(color_blend_attachment_state(true), src, transparent);
+        assert_close(result, src);
+    }
+
+    #[test]
+    fn antialiased_text_edge_has_no_dark_fringe() {
+        // a white glyph edge with 50% coverage over a white background
+        let edge = premultiplied(egui::Color32::from_white_alpha(128));
+        let white = [1.0; 4];
+
+        let result = blend(color_blend_attachment_state(true), edge, white);
+        assert_close(result, white);
+    }
+
+    #[test]
+    fn translucent_shadow_over_opaque_target() {
+        let shadow = premultiplied(egui::Color32::from_black_alpha(96));
+        let background = [0.2, 0.4, 0.6, 1.0];
+
+        let result = blend(color_blend_attachment_state(true), shadow, background);
+        let keep = 1.0 - shadow[3];
+        assert_close(
+            result,
+            [background[0] * keep, background[1] * keep, background[2] * keep, 1.0],
+        );
+    }
+
+    fn mesh(vertex_count: usize, index_count: usize) -> egui::Mesh {
+        egui::Mesh {
+            indices: vec![0; index_count],
+            vertices: vec![Default::default(); vertex_count],
+            texture_id: Default::default(),
+        }
+    }
+
+    #[test]
+    fn mesh_buffer_cursor_packs_meshes_back_to_back() {
+        let vertex_size = std::mem::size_of::<egui::epaint::Vertex>();
+        let mut cursor = MeshBufferCursor::default();
+        let a = mesh(4, 6);
+        let b = mesh(3, 3);
+        assert_eq!(cursor.reserve(&a.vertices, &a.indices, 1024, 1024), Some((0, 0)));
+        assert_eq!(
+            cursor.reserve(&b.vertices, &b.indices, 1024, 1024),
+            Some((4 * vertex_size, 6 * 4))
+        );
+    }
+
+    #[test]
+    fn mesh_buffer_cursor_accepts_exact_fit_and_rejects_overflow() {
+        let vertex_size = std::mem::size_of::<egui::epaint::Vertex>();
+        let mut cursor = MeshBufferCursor::default();
+        let a = mesh(2, 3);
+        assert!(cursor.reserve(&a.vertices, &a.indices, 2 * vertex_size, 12).is_some());
+        let before = cursor;
+        assert!(cursor.reserve(&a.vertices, &a.indices, 3 * vertex_size, 1024).is_none());
+        assert_eq!(cursor, before, "a rejected mesh must not consume space");
+    }
+
+    #[test]
+    fn stencil_mask_is_tested_but_never_written() {
+        let state = stencil_op_state(Some(StencilMask {
+            compare_op: vk::CompareOp::EQUAL,
+            reference: 1,
+            compare_mask: 0xff,
+        }));
+        assert_eq!((state.compare_op, state.reference, state.compare_mask), (vk::CompareOp::EQUAL, 1, 0xff));
+        assert_eq!(state.write_mask, 0);
+        assert_eq!(state.pass_op, vk::StencilOp::KEEP);
+        assert!(format_has_stencil(vk::Format::D24_UNORM_S8_UINT));
+        assert!(!format_has_stencil(vk::Format::D32_SFLOAT));
+    }
+
+    #[test]
+    fn subpixel_text_blends_each_channel_with_its_coverage() {
+        let state = subpixel_blend_attachment_state(true);
+        assert_eq!(state.dst_color_blend_factor, vk::BlendFactor::ONE_MINUS_SRC1_COLOR);
+        // alpha is composited like the rest of the UI
+        let regular = color_blend_attachment_state(true);
+        assert_eq!(state.src_alpha_blend_factor, regular.src_alpha_blend_factor);
+        assert_eq!(state.dst_alpha_blend_factor, regular.dst_alpha_blend_factor);
+    }
+
+    #[test]
+    fn frames_with_the_same_meshes_are_the_same() {
+        let primitive = |clip_rect, vertex_count| egui::ClippedPrimitive {
+            clip_rect,
+            primitive: egui::epaint::Primitive::Mesh(mesh(vertex_count, 3)),
+        };
+        let frame = [primitive(egui::Rect::EVERYTHING, 3), primitive(egui::Rect::NOTHING, 4)];
+        assert!(same_primitives(&frame, &frame.clone()));
+        assert!(!same_primitives(&frame, &frame[..1]));
+        assert!(!same_primitives(&frame, &[frame[0].clone(), primitive(egui::Rect::EVERYTHING, 4)]));
+        assert!(!same_primitives(&frame, &[frame[0].clone(), primitive(egui::Rect::NOTHING, 5)]));
+    }
+
+    #[test]
+    fn frame_bytes_count_indices_at_the_index_size_of_the_frame() {
+        let vertex_size = std::mem::size_of::<egui::epaint::Vertex>();
+        let primitive = |mesh| egui::ClippedPrimitive {
+            clip_rect: egui::Rect::EVERYTHING,
+            primitive: egui::epaint::Primitive::Mesh(mesh),
+        };
+        let tooltip = [primitive(mesh(4, 6)), primitive(mesh(3, 3))];
+        let cursor = MeshBufferCursor::for_frame(&[&tooltip]);
+        assert_eq!(cursor.frame_bytes(&[&tooltip]), 7 * vertex_size + 9 * 2);
+        assert!(cursor.frame_bytes(&[&tooltip]) <= TINY_FRAME_BYTES);
+    }
+
+    #[test]
+    fn later_passes_use_the_last_depth() {
+        let depth_attachment = DepthAttachment {
+            image_views: vec![],
+            format: vk::Format::D32_SFLOAT,
+            compare_op: vk::CompareOp::ALWAYS,
+            pass_depths: vec![0.9, 0.1],
+            stencil: None,
+        };
+        assert_eq!(depth_attachment.pass_depth(0), 0.9);
+        assert_eq!(depth_attachment.pass_depth(1), 0.1);
+        assert_eq!(depth_attachment.pass_depth(5), 0.1);
+    }
+
+    #[test]
+    fn large_images_get_dedicated_allocations() {
+        let no_preference = vk::MemoryDedicatedRequirements::default();
+        let preferred = vk::MemoryDedicatedRequirements {
+            prefers_dedicated_allocation: vk::TRUE,
+            ..Default::default()
+        };
+        // a 2048x2048 RGBA atlas
+        assert!(wants_dedicated_allocation(2048 * 2048 * 4, &no_preference));
+        assert!(!wants_dedicated_allocation(512 * 512 * 4, &no_preference));
+        assert!(wants_dedicated_allocation(512 * 512 * 4, &preferred));
+    }
+
+    #[test]
+    fn target_layouts_override_the_defaults() {
+        let options = IntegrationOptions {
+            tile_based_gpu: true,
+            ..Default::default()
+        };
+        assert_eq!(target_initial_layout(&options), vk::ImageLayout::PRESENT_SRC_KHR);
+        assert_eq!(target_final_layout(&options), vk::ImageLayout::PRESENT_SRC_KHR);
+
+        let options = IntegrationOptions {
+            tile_based_gpu: true,
+            target_layouts: Some(TargetLayouts {
+                initial_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                final_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(target_initial_layout(&options), vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        assert_eq!(target_final_layout(&options), vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+    }
+
+    #[test]
+    fn dynamic_rendering_barriers_transition_like_the_render_pass() {
+        let options = IntegrationOptions {
+            dynamic_rendering: true,
+            target_layouts: Some(TargetLayouts {
+                initial_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            }),
+            ..Default::default()
+        };
+        let image = vk::Image::from_raw(1);
+        let begin = dynamic_rendering_barrier(&options, image, 2, true);
+        assert_eq!(begin.old_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        assert_eq!(begin.new_layout, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        assert_eq!(begin.src_stage_mask, vk::PipelineStageFlags2::ALL_COMMANDS);
+        assert_eq!(begin.subresource_range.base_array_layer, 2);
+        let end = dynamic_rendering_barrier(&options, image, 2, false);
+        assert_eq!(end.new_layout, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        assert_eq!(end.dst_access_mask, vk::AccessFlags2::SHADER_READ);
+        assert_eq!(
+            PipelineTarget::render_target(vk::RenderPass::null(), vk::Format::B8G8R8A8_SRGB, None),
+            PipelineTarget::Dynamic {
+                color_format: vk::Format::B8G8R8A8_SRGB,
+                depth_format: None,
+            }
+        );
+        assert_eq!(
+            depth_stencil_formats(Some(vk::Format::D24_UNORM_S8_UINT)),
+            (vk::Format::D24_UNORM_S8_UINT, vk::Format::D24_UNORM_S8_UINT)
+        );
+    }
+
+    #[test]
+    fn sampler_anisotropy_is_limited_by_the_device() {
+        assert_eq!(sampler_anisotropy(Some(16.0), Some(8.0)), Some(8.0));
+        assert_eq!(sampler_anisotropy(Some(0.5), Some(8.0)), Some(1.0));
+        assert_eq!(sampler_anisotropy(Some(16.0), None), None);
+        assert_eq!(sampler_anisotropy(None, Some(8.0)), None);
+    }
+
+    #[test]
+    fn oversized_textures_are_box_filtered_to_fit() {
+        assert_eq!(fit_texture_size((16384, 8192), Some(4096)), (4096, 2048));
+        assert_eq!(fit_texture_size((100, 50), Some(4096)), (100, 50));
+        assert_eq!(fit_texture_size((16384, 1), Some(4096)), (4096, 1));
+
+        // 4x2 texels of two colors down to 2x1
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let data = [red, red, blue, blue, red, red, blue, blue].concat();
+        assert_eq!(downscale_texels(&data, (4, 2), (2, 1), 4), [red, blue].concat());
+        // font coverage
+        assert_eq!(downscale_texels(&[0, 255, 255, 255], (4, 1), (2, 1), 1), [128, 255]);
+    }
+
+    #[test]
+    fn nearest_font_sampler_does_not_blend_mip_levels() {
+        let options = SamplerOptions {
+            filter: vk::Filter::NEAREST,
+            max_lod: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(sampler_mipmap_mode(options.filter), vk::SamplerMipmapMode::NEAREST);
+        assert_eq!(sampler_mipmap_mode(SamplerOptions::default().filter), vk::SamplerMipmapMode::LINEAR);
+    }
+
+    #[test]
+    fn textures_keep_the_sampler_set_for_them() {
+        let nearest_repeat = SamplerOptions {
+            filter: vk::Filter::NEAREST,
+            address_mode: vk::SamplerAddressMode::REPEAT,
+            ..Default::default()
+        };
+        let font_sampler = SamplerOptions {
+            lod_bias: -0.5,
+            ..Default::default()
+        };
+        let texture_samplers = HashMap::from([(egui::TextureId::Managed(3), nearest_repeat)]);
+        let options = |id| texture_sampler_options(id, &texture_samplers, font_sampler);
+        assert_eq!(options(egui::TextureId::Managed(3)), nearest_repeat);
+        assert_eq!(options(FONT_TEXTURE_ID), font_sampler);
+        assert_eq!(options(egui::TextureId::Managed(4)), SamplerOptions::default());
+    }
+
+    #[test]
+    fn copied_rows_follow_the_row_pitch() {
+        // 3x2 texels in rows of 16 bytes
+        let mut texels = vec![0; 32];
+        let data = (1..=8).collect::<Vec<u8>>();
+        copy_rows(&mut texels, 16, &data, (1, 1), (2, 1), 4);
+        assert_eq!(&texels[..20], &[0; 20]);
+        assert_eq!(&texels[20..28], &data[..]);
+        assert_eq!(&texels[28..], &[0; 4]);
+    }
+
+    #[test]
+    fn descriptor_slots_are_aligned() {
+        assert_eq!(descriptor_slot_size(40, 64), 64);
+        assert_eq!(descriptor_slot_size(64, 64), 64);
+        assert_eq!(descriptor_slot_size(65, 16), 80);
+    }
+
+    #[test]
+    fn relocated_fragment_shader_samples_the_given_descriptor() {
+        let decorations = |spirv: &[u8]| {
+            let words = spirv
+                .chunks_exact(4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            let (mut set, mut binding, mut i) = (None, None, 5);
+            while i < words.len() {
+                let word_count = (words[i] >> 16) as usize;
+                if words[i] & 0xffff == SPIRV_OP_DECORATE && word_count == 4 {
+                    match words[i + 2] {
+                        SPIRV_DECORATION_DESCRIPTOR_SET => set = Some(words[i + 3]),
+                        SPIRV_DECORATION_BINDING => binding = Some(words[i + 3]),
+                        _ => {}
+                    }
+                }
+                i += word_count;
+            }
+            (set, binding)
+        };
+        assert_eq!(decorations(FRAG_SPV), (Some(0), Some(0)));
+        let relocated = relocate_descriptor(FRAG_SPV, 2, 5);
+        assert_eq!(relocated.len(), FRAG_SPV.len());
+        assert_eq!(decorations(&relocated), (Some(2), Some(5)));
+        assert_eq!(relocate_descriptor(FRAG_SPV, 0, 0), FRAG_SPV);
+    }
+
+    #[test]
+    fn short_indices_are_used_when_every_mesh_fits() {
+        let primitive = |mesh| egui::ClippedPrimitive {
+            clip_rect: egui::Rect::EVERYTHING,
+            primitive: egui::epaint::Primitive::Mesh(mesh),
+        };
+        let small = [primitive(mesh(4, 6))];
+        let large = [primitive(mesh(4, 6)), primitive(mesh(u16::MAX as usize + 2, 3))];
+
+        let mut cursor = MeshBufferCursor::for_frame(&[&small]);
+        assert_eq!(cursor.index_type(), vk::IndexType::UINT16);
+        let a = mesh(4, 6);
+        cursor.reserve(&a.vertices, &a.indices, 1024, 1024);
+        assert_eq!(cursor.index_offset, 6 * 2);
+        cursor.restart();
+        assert_eq!(cursor.index_offset, 0);
+        assert_eq!(cursor.index_type(), vk::IndexType::UINT16);
+
+        assert_eq!(MeshBufferCursor::for_frame(&[&small, &large]).index_type(), vk::IndexType::UINT32);
+    }
+
+    #[test]
+    #[cfg(feature = "strict")]
+    fn headroom_is_crossed_once() {
+        assert!(!crosses_headroom(0, 90, 100));
+        assert!(crosses_headroom(90, 91, 100));
+        assert!(!crosses_headroom(91, 95, 100));
+    }
+
+    #[test]
+    fn scissor_rect_scales_rounds_and_clamps() {
+        let clip = egui::Rect::from_min_max(egui::pos2(-10.0, 0.3), egui::pos2(50.0, 30.0));
+        let rect = scissor_rect(clip, 2.0, 80, 40);
+        assert_eq!((rect.offset.x, rect.offset.y), (0, 1));
+        assert_eq!((rect.extent.width, rect.extent.height), (80, 39));
+
+        let outside = egui::Rect::from_min_max(egui::pos2(100.0, 100.0), egui::pos2(200.0, 200.0));
+        let rect = scissor_rect(outside, 1.0, 80, 40);
+        assert_eq!((rect.extent.width, rect.extent.height), (0, 0));
+    }
+
+    #[test]
+    fn unchanged_scissors_are_not_set_again() {
+        let full = scissor_rect(egui::Rect::EVERYTHING, 1.0, 80, 40);
+        let window = scissor_rect(egui::Rect::from_min_max(pos2(0.0, 0.0), pos2(10.0, 10.0)), 1.0, 80, 40);
+        let mut bound = None;
+        assert!(replace_scissor(&mut bound, full));
+        assert!(!replace_scissor(&mut bound, full));
+        assert!(replace_scissor(&mut bound, window));
+        assert!(replace_scissor(&mut bound, full));
+    }
+
+    #[test]
+    fn scissors_are_clamped_beyond_rounding() {
+        let screen = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(40.0001, 20.0));
+        assert!(!scissor_is_clamped(screen, 2.0, 80, 40));
+        let offscreen = egui::Rect::from_min_max(egui::pos2(-10.0, 0.0), egui::pos2(30.0, 20.0));
+        assert!(scissor_is_clamped(offscreen, 2.0, 80, 40));
+    }
+
+    #[test]
+    fn render_area_covers_the_scissors_of_the_frame() {
+        let extent = vk::Extent2D { width: 80, height: 40 };
+        let hud = [
+            egui::Rect::from_min_max(egui::pos2(1.0, 2.0), egui::pos2(5.0, 4.0)),
+            egui::Rect::from_min_max(egui::pos2(3.0, 1.0), egui::pos2(8.0, 3.0)),
+        ];
+        let area = render_area(hud.into_iter(), 2.0, extent);
+        assert_eq!((area.offset.x, area.offset.y), (2, 2));
+        assert_eq!((area.extent.width, area.extent.height), (14, 6));
+
+        let area = render_area(std::iter::empty(), 2.0, extent);
+        assert_eq!(area.extent, extent);
+    }
+
+    #[test]
+    fn texture_update_plan() {
+        let full = egui::epaint::ImageDelta::full(egui::ColorImage::new([1, 1], egui::Color32::WHITE));
+        let partial = egui::epaint::ImageDelta::partial([0, 0], egui::ColorImage::new([1, 1], egui::Color32::WHITE));
+        assert_eq!(TextureUpdate::plan(false, &full), TextureUpdate::Create);
+        assert_eq!(TextureUpdate::plan(true, &full), TextureUpdate::Replace);
+        assert_eq!(TextureUpdate::plan(true, &partial), TextureUpdate::Partial);
+    }
+
+    #[test]
+    fn partial_update_of_unknown_texture_is_skipped() {
+        let partial = egui::epaint::ImageDelta::partial([0, 0], egui::ColorImage::new([1, 1], egui::Color32::WHITE));
+        assert_eq!(TextureUpdate::plan(false, &partial), TextureUpdate::Unknown);
+    }
+
+    #[test]
+    fn queue_family_transfer_barrier_sides() {
+        let range = vk::ImageSubresourceRange::default();
+        let layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        let stage = vk::PipelineStageFlags2::FRAGMENT_SHADER;
+        let access = vk::AccessFlags2::SHADER_READ;
+        assert!(queue_family_transfer_barrier(vk::Image::null(), range, layout, 1, 1, true, stage, access).is_none());
+
+        let acquire = queue_family_transfer_barrier(vk::Image::null(), range, layout, 2, 0, true, stage, access).unwrap();
+        assert_eq!((acquire.src_queue_family_index, acquire.dst_queue_family_index), (2, 0));
+        assert_eq!((acquire.dst_stage_mask, acquire.dst_access_mask), (stage, access));
+        assert_eq!(acquire.src_stage_mask, vk::PipelineStageFlags2::NONE);
+        assert_eq!(acquire.old_layout, acquire.new_layout);
+
+        let release = queue_family_transfer_barrier(vk::Image::null(), range, layout, 0, 2, false, stage, access).unwrap();
+        assert_eq!((release.src_stage_mask, release.src_access_mask), (stage, access));
+        assert_eq!(release.dst_access_mask, vk::AccessFlags2::NONE);
+    }
+
+    #[test]
+    fn unorm_targets_get_srgb_encoded_output() {
+        assert!(ShaderVariant::new(false, vk::Format::B8G8R8A8_UNORM).encode_srgb);
+        assert!(ShaderVariant::new(false, vk::Format::A2B10G10R10_UNORM_PACK32).encode_srgb);
+        assert!(!ShaderVariant::new(false, vk::Format::B8G8R8A8_SRGB).encode_srgb);
+        assert!(!ShaderVariant::new(false, vk::Format::R16G16B16A16_SFLOAT).encode_srgb);
+
+        let unorm_layer = composite_variant(vk::Format::R8G8B8A8_UNORM, vk::Format::B8G8R8A8_SRGB);
+        assert!(unorm_layer.premultiplied_alpha && !unorm_layer.encode_srgb);
+        let srgb_layer = composite_variant(vk::Format::R8G8B8A8_SRGB, vk::Format::B8G8R8A8_UNORM);
+        assert!(!srgb_layer.premultiplied_alpha && srgb_layer.encode_srgb);
+        assert_eq!(
+            composite_variant(vk::Format::R8G8B8A8_UNORM, vk::Format::B8G8R8A8_UNORM),
+            ShaderVariant {
+                premultiplied_alpha: false,
+                encode_srgb: false,
+            }
+        );
+    }
+
+    #[test]
+    fn meshes_of_user_texture_regions_sample_their_uv_rect() {
+        let mesh = |texture_id| {
+            let mut mesh = egui::Mesh::with_texture(texture_id);
+            mesh.add_rect_with_uv(
+                egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(8.0, 8.0)),
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+            egui::ClippedPrimitive {
+                clip_rect: egui::Rect::EVERYTHING,
+                primitive: egui::epaint::Primitive::Mesh(mesh),
+            }
+        };
+        let pass = [mesh(egui::TextureId::User(1)), mesh(egui::TextureId::User(2))];
+        let mut uv_rects = HashMap::new();
+        assert!(map_uv_rects(&[&pass], &uv_rects).is_none());
+        uv_rects.insert(2, egui::Rect::from_min_max(egui::pos2(0.5, 0.25), egui::pos2(0.75, 0.5)));
+
+        let mapped = map_uv_rects(&[&pass], &uv_rects).unwrap();
+        let uvs = |clipped_primitive: &egui::ClippedPrimitive| match &clipped_primitive.primitive {
+            egui::epaint::Primitive::Mesh(mesh) => mesh.vertices.iter().map(|vertex| vertex.uv).collect::<Vec<_>>(),
+            _ => unreachable!(),
+        };
+        assert_eq!(uvs(&mapped[0][0]), uvs(&pass[0]));
+        let region = uvs(&mapped[0][1]);
+        assert_eq!(region.first(), Some(&egui::pos2(0.5, 0.25)));
+        assert!(region.contains(&egui::pos2(0.75, 0.5)));
+    }
+
+    #[test]
+    fn user_texture_barrier_waits_on_egui_sampling() {
+        let range = vk::ImageSubresourceRange::default();
+        let stage = vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT;
+        let render = user_texture_barrier(
+            vk::Image::null(),
+            range,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            stage,
+        );
+        assert_eq!(render.src_stage_mask, vk::PipelineStageFlags2::FRAGMENT_SHADER);
+        assert_eq!(render.src_access_mask, vk::AccessFlags2::NONE);
+        assert_eq!(render.dst_stage_mask, stage);
+        assert!(render.dst_access_mask.contains(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE));
+
+        let sample = user_texture_barrier(
+            vk::Image::null(),
+            range,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            stage,
+        );
+        assert_eq!(
+            (sample.src_stage_mask, sample.src_access_mask),
+            (stage, vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+        );
+        assert_eq!(
+            (sample.dst_stage_mask, sample.dst_access_mask),
+            (vk::PipelineStageFlags2::FRAGMENT_SHADER, vk::AccessFlags2::SHADER_READ)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be empty")]
+    fn validate_render_targets_rejects_zero_extent() {
+        // any image count and format is accepted
+        validate_render_targets(1920, 1080, 2, vk::Format::B8G8R8A8_UNORM);
+        validate_render_targets(1920, 1080, 5, vk::Format::A2B10G10R10_UNORM_PACK32);
+        validate_render_targets(0, 1080, 3, vk::Format::B8G8R8A8_UNORM);
+    }
+
+    #[test]
+    #[should_panic(expected = "Vertex buffers must be host visible")]
+    fn validate_memory_locations_rejects_device_only_vertex_buffers() {
+        validate_memory_locations(&MemoryLocations {
+            texture: MemoryLocation::CpuToGpu,
+            ..Default::default()
+        });
+        validate_memory_locations(&MemoryLocations {
+            vertex: MemoryLocation::GpuOnly,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn push_constants_match_shader_layout() {
+        // std430 offsets of the push constant block in the shaders
+        assert_eq!(std::mem::size_of::<PushConstants>(), 112);
+        let push_constants: PushConstants = bytemuck::Zeroable::zeroed();
+        let base = &push_constants as *const _ as usize;
+        assert_eq!(&push_constants.screen_size as *const _ as usize - base, 64);
+        assert_eq!(&push_constants.calibration as *const _ as usize - base, 72);
+        assert_eq!(&push_constants.clip_rect as *const _ as usize - base, CLIP_RECT_OFFSET as usize);
+        assert_eq!(&push_constants.tint as *const _ as usize - base, 96);
+    }
+
+    #[test]
+    fn custom_push_constants_extend_the_egui_range() {
+        assert_eq!(push_constant_range(&Default::default()).size, CUSTOM_PUSH_CONSTANTS_OFFSET);
+        let options = IntegrationOptions {
+            custom_push_constants: 16,
+            ..Default::default()
+        };
+        let range = push_constant_range(&options);
+        assert_eq!((range.offset, range.size), (0, CUSTOM_PUSH_CONSTANTS_OFFSET + 16));
+        // within the guaranteed maxPushConstantsSize
+        assert!(range.size <= 128);
+    }
+
+    #[test]
+    fn renderer_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Renderer<MockAllocator>>();
+    }
+
+    #[test]
+    fn user_texture_registry_keys_by_view_and_sampler() {
+        use ash::vk::Handle;
+        let view = vk::ImageView::from_raw(1);
+        let nearest = vk::Sampler::from_raw(2);
+        let linear = vk::Sampler::from_raw(3);
+
+        let mut registry = UserTextureRegistry::default();
+        let a = registry.insert(view, nearest, vk::DescriptorSet::from_raw(10));
+        let b = registry.insert(view, linear, vk::DescriptorSet::from_raw(11));
+        assert_ne!(a, b);
+        assert_eq!(registry.find(view, nearest), Some(a));
+        assert_eq!(registry.descriptor_set(b), Some(vk::DescriptorSet::from_raw(11)));
+
+        assert_eq!(registry.remove(a), Some(vk::DescriptorSet::from_raw(10)));
+        assert_eq!(registry.find(view, nearest), None);
+        assert_eq!(registry.remove(a), None);
+        // ids are not reused, so a stale TextureId never shows another texture
+        assert_ne!(registry.insert(view, nearest, vk::DescriptorSet::from_raw(10)), a);
+    }
+
+    #[test]
+    fn released_user_textures_are_revived_until_evicted() {
+        use ash::vk::Handle;
+        let (view, sampler) = (vk::ImageView::from_raw(1), vk::Sampler::from_raw(2));
+        let mut registry = UserTextureRegistry::default();
+        let id = registry.insert(view, sampler, vk::DescriptorSet::from_raw(10));
+        assert_eq!(registry.acquire(view, sampler), Some(id));
+        assert_eq!(registry.release(id, 1), Some(Release::Registered));
+        assert_eq!(registry.release(id, 1), Some(Release::Cached));
+        assert_eq!(registry.descriptor_set(id), None);
+        assert_eq!(registry.release(id, 1), None);
+
+        // registered again the next frame
+        assert!(registry.evict(1).is_empty());
+        assert_eq!(registry.acquire(view, sampler), Some(id));
+        assert_eq!(registry.descriptor_set(id), Some(vk::DescriptorSet::from_raw(10)));
+
+        assert_eq!(registry.release(id, 2), Some(Release::Cached));
+        assert_eq!(registry.evict(3), vec![vk::DescriptorSet::from_raw(10)]);
+        assert_eq!(registry.acquire(view, sampler), None);
+    }
+}
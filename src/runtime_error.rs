@@ -0,0 +1,94 @@
+use std::fmt;
+
+use ash::vk;
+
+/// A recoverable problem, passed to the hook set with
+/// [`Integration::set_error_hook`](crate::Integration::set_error_hook).
+///
+/// The integration carries on after reporting it; the hook lets applications show it in
+/// their own diagnostics UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    /// Reading or writing the clipboard failed.
+    Clipboard(String),
+    /// Opening a link clicked in the UI failed.
+    OpenUrl(String),
+    /// A [`egui::TexturesDelta`] updated a region of a texture that does not exist; the
+    /// update is skipped.
+    UnknownTexture(egui::TextureId),
+    /// A mesh uses a user texture that has been unregistered; the mesh is not drawn.
+    UnregisteredTexture(egui::TextureId),
+    /// A [`egui::epaint::Primitive::Callback`] was passed to a paint method that cannot call
+    /// it, e.g. [`Renderer::paint_primitives`](crate::Renderer::paint_primitives); it is
+    /// skipped.
+    UnsupportedCallback {
+        /// The clip rect of the callback in points.
+        clip_rect: egui::Rect,
+    },
+    /// A clip rect reached outside the render target and was clamped to it.
+    ScissorClamped {
+        /// The clip rect of the mesh in points.
+        clip_rect: egui::Rect,
+        /// The scissor the mesh was drawn with, in physical pixels.
+        scissor: vk::Rect2D,
+    },
+    /// The glyphs of a single frame do not fit into the font atlas, even with the text
+    /// scaled down as far as [`Platform`](crate::Platform) goes; some text is garbled.
+    FontAtlasOverflow {
+        /// Largest width or height of the atlas, see
+        /// [`Platform::set_max_texture_side`](crate::Platform::set_max_texture_side).
+        max_texture_side: usize,
+    },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Clipboard(err) => write!(f, "Clipboard error: {}", err),
+            RuntimeError::OpenUrl(err) => write!(f, "Failed to open url: {}", err),
+            RuntimeError::UnknownTexture(id) => {
+                write!(f, "Tried to update {:?}, which has not been allocated yet", id)
+            }
+            RuntimeError::UnregisteredTexture(id) => {
+                write!(f, "This UserTexture has already been unregistered: {:?}", id)
+            }
+            RuntimeError::UnsupportedCallback { clip_rect } => {
+                write!(f, "Paint callbacks are not supported, the one in {:?} is skipped", clip_rect)
+            }
+            RuntimeError::ScissorClamped { clip_rect, scissor } => write!(
+                f,
+                "The clip rect {:?} was clamped to the scissor {:?}",
+                clip_rect, scissor
+            ),
+            RuntimeError::FontAtlasOverflow { max_texture_side } => write!(
+                f,
+                "The glyphs of a frame do not fit into a font atlas of {0}x{0} texels, use fewer or smaller fonts",
+                max_texture_side
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Returned by [`Renderer::paint`](crate::Renderer::paint) and the other paint methods
+/// when the render targets no longer match the swapchain, e.g. after the window was
+/// resized, or the swapchain image index is out of range.
+///
+/// Nothing is recorded; the texture updates of the frame are kept for the next one. Call
+/// [`update_swapchain`](crate::Integration::update_swapchain) with the recreated swapchain
+/// before painting again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeedsSwapchainUpdate;
+
+impl fmt::Display for NeedsSwapchainUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The swapchain is out of date, call update_swapchain before painting")
+    }
+}
+
+impl std::error::Error for NeedsSwapchainUpdate {}
+
+/// Hook called with the recoverable problems of the [`Platform`](crate::Platform) and the
+/// [`Renderer`](crate::Renderer).
+pub type ErrorHook = dyn FnMut(&RuntimeError) + Send;
@@ -1,10 +1,65 @@
 #version 450
 
+layout(constant_id = 0) const bool PREMULTIPLIED_ALPHA = false;
+
+// the target is UNORM, so the output is encoded to sRGB here instead of by the hardware
+layout(constant_id = 1) const bool ENCODE_SRGB = false;
+
 layout(location = 0) in vec4 inColor;
 layout(location = 1) in vec2 inUV;
+layout(location = 2) in vec2 inPos;
 
 layout(location = 0) out vec4 outColor;
 
 layout(binding = 0, set = 0) uniform sampler2D font_texture;
 
-void main() { outColor = inColor * texture(font_texture, inUV); }
+layout(push_constant) uniform PushConstants {
+  mat4 transform;
+  vec2 screen_size;
+  vec2 calibration;
+  vec4 clip_rect;
+}
+pushConstants;
+
+// display calibration of the UI: gamma and brightness of the unpremultiplied color
+vec4 calibrate(vec4 color) {
+  float gamma = pushConstants.calibration.x;
+  float brightness = pushConstants.calibration.y;
+  if ((gamma == 1.0 && brightness == 1.0) || color.a <= 0.0) {
+    return color;
+  }
+  vec3 straight = color.rgb / color.a;
+  return vec4(brightness * pow(straight, vec3(1.0 / gamma)) * color.a, color.a);
+}
+
+vec3 srgb_to_linear(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+vec3 linear_to_srgb(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+  // clip rect in points, for transforms the scissor rect cannot follow
+  if (any(lessThan(inPos, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(inPos, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  vec4 texel = texture(font_texture, inUV);
+  if (PREMULTIPLIED_ALPHA) {
+    // texels are premultiplied sRGB like the vertex colors, so bring them to linear as well
+    texel = vec4(srgb_to_linear(texel.rgb), texel.a);
+  }
+  vec4 color = calibrate(inColor * texel);
+  if (ENCODE_SRGB) {
+    color.rgb = linear_to_srgb(color.rgb);
+  }
+  outColor = color;
+}
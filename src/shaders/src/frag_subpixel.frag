@@ -0,0 +1,63 @@
+#version 450
+
+layout(constant_id = 0) const bool PREMULTIPLIED_ALPHA = false;
+
+// the target is UNORM, so the output is encoded to sRGB here instead of by the hardware
+layout(constant_id = 1) const bool ENCODE_SRGB = false;
+
+layout(location = 0) in vec4 inColor;
+layout(location = 1) in vec2 inUV;
+layout(location = 2) in vec2 inPos;
+
+// dual-source blending: the color and the coverage of each channel it is blended with
+layout(location = 0, index = 0) out vec4 outColor;
+layout(location = 0, index = 1) out vec4 outCoverage;
+
+layout(binding = 0, set = 0) uniform sampler2D font_texture;
+
+layout(push_constant) uniform PushConstants {
+  mat4 transform;
+  vec2 screen_size;
+  vec2 calibration;
+  vec4 clip_rect;
+}
+pushConstants;
+
+// display calibration of the UI: gamma and brightness of the unpremultiplied color
+vec4 calibrate(vec4 color) {
+  float gamma = pushConstants.calibration.x;
+  float brightness = pushConstants.calibration.y;
+  if ((gamma == 1.0 && brightness == 1.0) || color.a <= 0.0) {
+    return color;
+  }
+  vec3 straight = color.rgb / color.a;
+  return vec4(brightness * pow(straight, vec3(1.0 / gamma)) * color.a, color.a);
+}
+
+vec3 linear_to_srgb(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+  // clip rect in points, for transforms the scissor rect cannot follow
+  if (any(lessThan(inPos, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(inPos, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  // sample the glyph coverage a third of a pixel left and right for the red and blue
+  // subpixels. Shapes use a single white texel of the atlas, so their UVs are constant and
+  // they are drawn as usual.
+  vec2 subpixel = dFdx(inUV) / 3.0;
+  vec3 coverage = vec3(texture(font_texture, inUV - subpixel).a,
+                       texture(font_texture, inUV).a,
+                       texture(font_texture, inUV + subpixel).a);
+  vec4 color = calibrate(inColor);
+  if (ENCODE_SRGB) {
+    color.rgb = linear_to_srgb(color.rgb);
+  }
+  outColor = vec4(color.rgb * coverage, color.a * coverage.g);
+  outCoverage = vec4(color.a * coverage, color.a * coverage.g);
+}
@@ -6,8 +6,15 @@ layout(location = 2) in vec4 inColor;
 
 layout(location = 0) out vec4 outColor;
 layout(location = 1) out vec2 outUV;
+layout(location = 2) out vec2 outPos;
 
-layout(push_constant) uniform PushConstants { vec2 screen_size; }
+layout(push_constant) uniform PushConstants {
+  mat4 transform;
+  vec2 screen_size;
+  vec2 calibration;
+  vec4 clip_rect;
+  vec4 tint;
+}
 pushConstants;
 
 vec3 srgb_to_linear(vec3 srgb) {
@@ -19,8 +26,10 @@ vec3 srgb_to_linear(vec3 srgb) {
 
 void main() {
   gl_Position =
+      pushConstants.transform *
       vec4(2.0 * inPos.x / pushConstants.screen_size.x - 1.0,
            2.0 * inPos.y / pushConstants.screen_size.y - 1.0, 0.0, 1.0);
-  outColor = vec4(srgb_to_linear(inColor.rgb), inColor.a);
+  outColor = vec4(srgb_to_linear(inColor.rgb), inColor.a) * pushConstants.tint;
   outUV = inUV;
+  outPos = inPos;
 }
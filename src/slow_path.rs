@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Each slow path is warned about at most once per interval.
+const WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+// Texture updates per frame above which the renderer warns.
+pub(crate) const MANY_TEXTURE_UPDATES: usize = 16;
+
+// Fraction of the descriptor pool or the mesh buffers of a frame in use above which the
+// renderer warns.
+pub(crate) const SLOW_PATH_HEADROOM: f32 = 0.9;
+
+// Usage the renderer handles, but slowly enough to make the UI stutter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SlowPath {
+    // the mesh buffers of a frame are nearly full or overflowed
+    MeshBuffers,
+    // many textures are uploaded in one frame
+    TextureUpdates,
+    // the descriptor pool is nearly exhausted
+    DescriptorPool,
+}
+
+impl fmt::Display for SlowPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SlowPath::MeshBuffers => "mesh buffers",
+            SlowPath::TextureUpdates => "texture updates",
+            SlowPath::DescriptorPool => "descriptor pool",
+        })
+    }
+}
+
+// Rate limit of the slow path warnings, logged by Renderer::warn_slow_path.
+#[derive(Default)]
+pub(crate) struct SlowPathWarnings {
+    // when each slow path was last warned about, and how often it was hit since
+    last_warnings: HashMap<SlowPath, (Instant, u64)>,
+}
+
+impl SlowPathWarnings {
+    // Count a hit of `slow_path` at `now`. Returns the hits since the last warning, including
+    // this one, if it is to be warned about now.
+    pub(crate) fn hit(&mut self, slow_path: SlowPath, now: Instant) -> Option<u64> {
+        match self.last_warnings.get_mut(&slow_path) {
+            Some((last_warning, hits)) if now.duration_since(*last_warning) < WARNING_INTERVAL => {
+                *hits += 1;
+                None
+            }
+            Some((last_warning, hits)) => {
+                let since = *hits + 1;
+                (*last_warning, *hits) = (now, 0);
+                Some(since)
+            }
+            None => {
+                self.last_warnings.insert(slow_path, (now, 0));
+                Some(1)
+            }
+        }
+    }
+}
+
+// Whether `used` of `capacity` bytes (or sets) is above the headroom.
+pub(crate) fn above_headroom(used: usize, capacity: usize) -> bool {
+    used as f32 > capacity as f32 * SLOW_PATH_HEADROOM
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_rate_limited_per_slow_path() {
+        let mut warnings = SlowPathWarnings::default();
+        let start = Instant::now();
+        assert_eq!(warnings.hit(SlowPath::MeshBuffers, start), Some(1));
+        assert_eq!(warnings.hit(SlowPath::DescriptorPool, start), Some(1));
+        for second in 1..10 {
+            assert_eq!(warnings.hit(SlowPath::MeshBuffers, start + Duration::from_secs(second)), None);
+        }
+        assert_eq!(warnings.hit(SlowPath::MeshBuffers, start + WARNING_INTERVAL), Some(10));
+        assert!(above_headroom(91, 100) && !above_headroom(90, 100));
+    }
+}
@@ -0,0 +1,306 @@
+#![warn(missing_docs)]
+
+use std::collections::HashMap;
+
+use egui::epaint::{ClippedPrimitive, ImageDelta, Primitive, Vertex};
+use egui::{Color32, ColorImage, ImageData, Rgba, TextureId, TexturesDelta};
+
+/// Pure CPU renderer for egui meshes.
+///
+/// Rasterizes the same input as [`Integration::paint`](crate::Integration::paint) into a
+/// linear premultiplied RGBA buffer, blending like the GPU pipeline does. Used by the
+/// integration when [`IntegrationOptions::software_renderer`](crate::IntegrationOptions::software_renderer)
+/// is set, and usable on its own for headless tests.
+///
+/// User textures live on the GPU only, so meshes using them are skipped.
+pub struct SoftwareRenderer {
+    premultiplied_alpha: bool,
+    size: [usize; 2],
+    pixels: Vec<Rgba>,
+    textures: HashMap<TextureId, ColorImage>,
+}
+
+impl SoftwareRenderer {
+    /// Create a renderer.
+    ///
+    /// `premultiplied_alpha` mirrors [`IntegrationOptions::premultiplied_alpha`](crate::IntegrationOptions::premultiplied_alpha):
+    /// texels are converted to linear like vertex colors.
+    pub fn new(premultiplied_alpha: bool) -> Self {
+        Self {
+            premultiplied_alpha,
+            size: [0, 0],
+            pixels: vec![],
+            textures: Default::default(),
+        }
+    }
+
+    /// Size of the last painted frame in pixels.
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// The last painted frame, row by row, in linear premultiplied alpha.
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    /// The last painted frame, row by row, in sRGB premultiplied alpha.
+    pub fn to_color32(&self) -> Vec<Color32> {
+        self.pixels.iter().map(|&color| color.into()).collect()
+    }
+
+    /// Paint a frame of `size` pixels over a transparent background.
+    pub fn paint(
+        &mut self,
+        size: [usize; 2],
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        clipped_primitives: &[ClippedPrimitive],
+    ) {
+        for (id, image_delta) in textures_delta.set.iter() {
+            self.update_texture(*id, image_delta);
+        }
+
+        self.size = size;
+        self.pixels.clear();
+        self.pixels.resize(size[0] * size[1], Rgba::TRANSPARENT);
+
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            let mesh = match primitive {
+                Primitive::Mesh(mesh) => mesh,
+                Primitive::Callback(_) => continue,
+            };
+            let texture = match self.textures.get(&mesh.texture_id) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            // same rounding as the scissor rect of the GPU path
+            let clip_min_x = (clip_rect.min.x * pixels_per_point).clamp(0.0, size[0] as f32).round() as usize;
+            let clip_min_y = (clip_rect.min.y * pixels_per_point).clamp(0.0, size[1] as f32).round() as usize;
+            let clip_max_x = (clip_rect.max.x * pixels_per_point).clamp(0.0, size[0] as f32).round() as usize;
+            let clip_max_y = (clip_rect.max.y * pixels_per_point).clamp(0.0, size[1] as f32).round() as usize;
+            let clip = [clip_min_x, clip_min_y, clip_max_x, clip_max_y];
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let vertices = [
+                    &mesh.vertices[triangle[0] as usize],
+                    &mesh.vertices[triangle[1] as usize],
+                    &mesh.vertices[triangle[2] as usize],
+                ];
+                Self::rasterize_triangle(
+                    &mut self.pixels,
+                    size,
+                    clip,
+                    pixels_per_point,
+                    vertices,
+                    texture,
+                    self.premultiplied_alpha,
+                );
+            }
+        }
+
+        for id in textures_delta.free.iter() {
+            self.textures.remove(id);
+        }
+    }
+
+    fn update_texture(&mut self, id: TextureId, image_delta: &ImageDelta) {
+        let image = match &image_delta.image {
+            ImageData::Color(image) => image.clone(),
+            ImageData::Font(image) => ColorImage {
+                size: image.size,
+                pixels: image.srgba_pixels(1.0).collect(),
+            },
+        };
+
+        if let Some(pos) = image_delta.pos {
+            let texture = self
+                .textures
+                .get_mut(&id)
+                .expect("Tried to update a texture that has not been allocated yet.");
+            for y in 0..image.size[1] {
+                let src = &image.pixels[y * image.size[0]..(y + 1) * image.size[0]];
+                let start = (pos[1] + y) * texture.size[0] + pos[0];
+                texture.pixels[start..start + image.size[0]].copy_from_slice(src);
+            }
+        } else {
+            self.textures.insert(id, image);
+        }
+    }
+
+    fn rasterize_triangle(
+        pixels: &mut [Rgba],
+        size: [usize; 2],
+        clip: [usize; 4],
+        pixels_per_point: f32,
+        vertices: [&Vertex; 3],
+        texture: &ColorImage,
+        premultiplied_alpha: bool,
+    ) {
+        let p = vertices.map(|v| [v.pos.x * pixels_per_point, v.pos.y * pixels_per_point]);
+        let mut area = edge(p[0], p[1], p[2]);
+        let mut order = [0, 1, 2];
+        if area == 0.0 {
+            return;
+        }
+        if area < 0.0 {
+            order.swap(1, 2);
+            area = -area;
+        }
+        let [a, b, c] = order.map(|i| p[i]);
+        let [va, vb, vc] = order.map(|i| vertices[i]);
+        let [ca, cb, cc] = [va, vb, vc].map(|v| Rgba::from(v.color));
+
+        let min_x = (a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize).max(clip[0]);
+        let min_y = (a[1].min(b[1]).min(c[1]).floor().max(0.0) as usize).max(clip[1]);
+        let max_x = (a[0].max(b[0]).max(c[0]).ceil().max(0.0) as usize).min(clip[2]);
+        let max_y = (a[1].max(b[1]).max(c[1]).ceil().max(0.0) as usize).min(clip[3]);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let point = [x as f32 + 0.5, y as f32 + 0.5];
+                let w_a = edge(b, c, point);
+                let w_b = edge(c, a, point);
+                let w_c = edge(a, b, point);
+                if !covers(w_a, b, c) || !covers(w_b, c, a) || !covers(w_c, a, b) {
+                    continue;
+                }
+                let (w_a, w_b, w_c) = (w_a / area, w_b / area, w_c / area);
+
+                let color = ca * w_a + cb * w_b + cc * w_c;
+                let uv = [
+                    va.uv.x * w_a + vb.uv.x * w_b + vc.uv.x * w_c,
+                    va.uv.y * w_a + vb.uv.y * w_b + vc.uv.y * w_c,
+                ];
+                let texel = sample(texture, uv, premultiplied_alpha);
+                let src = Rgba::from_rgba_premultiplied(
+                    color.r() * texel[0],
+                    color.g() * texel[1],
+                    color.b() * texel[2],
+                    color.a() * texel[3],
+                );
+
+                // ONE, ONE_MINUS_SRC_ALPHA
+                let dst = &mut pixels[y * size[0] + x];
+                *dst = src + *dst * (1.0 - src.a());
+            }
+        }
+    }
+}
+
+// Twice the signed area of the triangle (a, b, p).
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+// Whether a sample with edge value `w` against the edge (a, b) is inside.
+// Samples exactly on an edge belong to only one of the two triangles sharing it,
+// so antialiasing feathers are not blended twice.
+fn covers(w: f32, a: [f32; 2], b: [f32; 2]) -> bool {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    w > 0.0 || (w == 0.0 && (dy < 0.0 || (dy == 0.0 && dx > 0.0)))
+}
+
+// Bilinear sampling with clamp to edge, like the integration's sampler.
+fn sample(texture: &ColorImage, uv: [f32; 2], premultiplied_alpha: bool) -> [f32; 4] {
+    let [width, height] = texture.size;
+    let x = uv[0] * width as f32 - 0.5;
+    let y = uv[1] * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+
+    let texel = |x: f32, y: f32| {
+        let x = (x.max(0.0) as usize).min(width - 1);
+        let y = (y.max(0.0) as usize).min(height - 1);
+        let color = texture.pixels[y * width + x];
+        if premultiplied_alpha {
+            Rgba::from(color).to_array()
+        } else {
+            color.to_array().map(|c| c as f32 / 255.0)
+        }
+    };
+    let t00 = texel(x0, y0);
+    let t10 = texel(x0 + 1.0, y0);
+    let t01 = texel(x0, y0 + 1.0);
+    let t11 = texel(x0 + 1.0, y0 + 1.0);
+
+    let mut result = [0.0; 4];
+    for i in 0..4 {
+        let top = t00[i] + (t10[i] - t00[i]) * tx;
+        let bottom = t01[i] + (t11[i] - t01[i]) * tx;
+        result[i] = top + (bottom - top) * ty;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, Rect};
+    use egui::epaint::{ImageDelta, Mesh};
+
+    fn white_texture() -> TexturesDelta {
+        let mut textures_delta = TexturesDelta::default();
+        textures_delta.set.insert(
+            TextureId::default(),
+            ImageDelta::full(ColorImage::new([1, 1], Color32::WHITE)),
+        );
+        textures_delta
+    }
+
+    fn rect(rect: Rect, clip_rect: Rect, color: Color32) -> ClippedPrimitive {
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(rect, color);
+        ClippedPrimitive {
+            clip_rect,
+            primitive: Primitive::Mesh(mesh),
+        }
+    }
+
+    #[test]
+    fn fills_rect_without_double_blending_the_diagonal() {
+        let mut renderer = SoftwareRenderer::new(true);
+        let full = Rect::from_min_max(pos2(0.0, 0.0), pos2(4.0, 4.0));
+        let color = Color32::from_rgba_premultiplied(0, 64, 0, 128);
+        renderer.paint([4, 4], 1.0, &white_texture(), &[rect(full, full, color)]);
+
+        let expected = Rgba::from(color);
+        for pixel in renderer.pixels() {
+            assert!((pixel.a() - expected.a()).abs() < 1e-6, "{:?}", pixel);
+            assert!((pixel.g() - expected.g()).abs() < 1e-6, "{:?}", pixel);
+        }
+    }
+
+    #[test]
+    fn respects_clip_rect_and_pixels_per_point() {
+        let mut renderer = SoftwareRenderer::new(true);
+        let full = Rect::from_min_max(pos2(0.0, 0.0), pos2(2.0, 2.0));
+        let clip = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 2.0));
+        renderer.paint([4, 4], 2.0, &white_texture(), &[rect(full, clip, Color32::RED)]);
+
+        let pixels = renderer.to_color32();
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x < 2 { Color32::RED } else { Color32::TRANSPARENT };
+                assert_eq!(pixels[y * 4 + x], expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn freed_textures_skip_meshes() {
+        let mut renderer = SoftwareRenderer::new(false);
+        let full = Rect::from_min_max(pos2(0.0, 0.0), pos2(2.0, 2.0));
+        let mut textures_delta = white_texture();
+        textures_delta.free.push(TextureId::default());
+        renderer.paint([2, 2], 1.0, &textures_delta, &[rect(full, full, Color32::RED)]);
+        renderer.paint([2, 2], 1.0, &Default::default(), &[rect(full, full, Color32::RED)]);
+
+        assert!(renderer.pixels().iter().all(|&pixel| pixel == Rgba::TRANSPARENT));
+    }
+}
@@ -0,0 +1,118 @@
+#![warn(missing_docs)]
+
+use ash::vk;
+
+/// Acceptable swapchain surface formats in order of preference.
+///
+/// The renderer draws correct colors into both `*_SRGB` and `*_UNORM` formats, see
+/// [`Renderer::update_swapchain`](crate::Renderer::update_swapchain), so the order only
+/// decides precision and where blending happens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceFormatPreference {
+    /// Formats and color spaces, most preferred first.
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    /// Pick the first format the surface supports if it supports none of `formats`.
+    pub fallback_to_any: bool,
+}
+
+impl Default for SurfaceFormatPreference {
+    /// 8-bit UNORM formats with sRGB nonlinear color space like the examples, then their sRGB
+    /// counterparts, then anything.
+    fn default() -> Self {
+        Self {
+            formats: [
+                vk::Format::B8G8R8A8_UNORM,
+                vk::Format::R8G8B8A8_UNORM,
+                vk::Format::B8G8R8A8_SRGB,
+                vk::Format::R8G8B8A8_SRGB,
+            ]
+            .into_iter()
+            .map(|format| vk::SurfaceFormatKHR {
+                format,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            })
+            .collect(),
+            fallback_to_any: true,
+        }
+    }
+}
+
+impl SurfaceFormatPreference {
+    /// Pick the most preferred of the `supported` formats of a surface, as returned by
+    /// `get_physical_device_surface_formats`. `None` if no format is acceptable.
+    pub fn select(&self, supported: &[vk::SurfaceFormatKHR]) -> Option<SurfaceFormatChoice> {
+        // a single UNDEFINED format means the surface takes any format
+        if let [only] = supported {
+            if only.format == vk::Format::UNDEFINED {
+                return self.formats.first().map(|&format| SurfaceFormatChoice {
+                    format,
+                    preference: Some(0),
+                });
+            }
+        }
+        self.formats
+            .iter()
+            .enumerate()
+            .find(|(_, format)| supported.contains(format))
+            .map(|(preference, &format)| SurfaceFormatChoice {
+                format,
+                preference: Some(preference),
+            })
+            .or_else(|| {
+                let format = *supported.first().filter(|_| self.fallback_to_any)?;
+                Some(SurfaceFormatChoice {
+                    format,
+                    preference: None,
+                })
+            })
+    }
+}
+
+/// The surface format picked by [`SurfaceFormatPreference::select`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceFormatChoice {
+    /// The format to create the swapchain and the [`Integration`](crate::Integration) with.
+    pub format: vk::SurfaceFormatKHR,
+    /// Index of the format in [`SurfaceFormatPreference::formats`], `None` for a fallback.
+    pub preference: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srgb_nonlinear(format: vk::Format) -> vk::SurfaceFormatKHR {
+        vk::SurfaceFormatKHR {
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        }
+    }
+
+    #[test]
+    fn the_most_preferred_supported_format_is_picked() {
+        let preference = SurfaceFormatPreference::default();
+        let supported = [
+            srgb_nonlinear(vk::Format::A2B10G10R10_UNORM_PACK32),
+            srgb_nonlinear(vk::Format::B8G8R8A8_SRGB),
+            srgb_nonlinear(vk::Format::R8G8B8A8_UNORM),
+        ];
+        assert_eq!(
+            preference.select(&supported),
+            Some(SurfaceFormatChoice {
+                format: srgb_nonlinear(vk::Format::R8G8B8A8_UNORM),
+                preference: Some(1),
+            })
+        );
+
+        let hdr = [srgb_nonlinear(vk::Format::A2B10G10R10_UNORM_PACK32)];
+        assert_eq!(preference.select(&hdr).unwrap().preference, None);
+        let strict = SurfaceFormatPreference {
+            fallback_to_any: false,
+            ..preference.clone()
+        };
+        assert_eq!(strict.select(&hdr), None);
+
+        let any = [srgb_nonlinear(vk::Format::UNDEFINED)];
+        assert_eq!(strict.select(&any).unwrap().format, preference.formats[0]);
+    }
+}
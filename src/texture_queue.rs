@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use ash::vk;
+
+pub(crate) enum QueuedRegistration {
+    UserTexture {
+        id: u64,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    },
+    Texture {
+        id: u64,
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    Unregister {
+        id: u64,
+    },
+}
+
+/// Handle for registering textures from threads other than the render thread.
+///
+/// Get one with [`Integration::texture_queue`](crate::Integration::texture_queue).
+/// TextureIds are returned immediately; the registrations are queued and applied at the
+/// start of the next [`Integration::paint`](crate::Integration::paint), in the order they
+/// were made.
+#[derive(Clone)]
+pub struct TextureQueue {
+    pub(crate) sender: Sender<QueuedRegistration>,
+    pub(crate) next_id: Arc<AtomicU64>,
+}
+
+impl TextureQueue {
+    /// Queue [`Integration::register_user_texture`](crate::Integration::register_user_texture).
+    ///
+    /// Unlike registering on the render thread, a queued registration always gets a new TextureId.
+    pub fn register_user_texture(
+        &self,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> egui::TextureId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.send(QueuedRegistration::UserTexture {
+            id,
+            image_view,
+            sampler,
+        });
+        egui::TextureId::User(id)
+    }
+
+    /// Queue the creation of a texture owned by the integration.
+    ///
+    /// `data` holds tightly packed RGBA8 (sRGB, premultiplied) texels.
+    /// Release it with [`unregister_user_texture`](Self::unregister_user_texture).
+    pub fn create_texture(&self, width: u32, height: u32, data: Vec<u8>) -> egui::TextureId {
+        assert_eq!(
+            data.len(),
+            (width * height * 4) as usize,
+            "Mismatch between texture size and data length"
+        );
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.send(QueuedRegistration::Texture {
+            id,
+            width,
+            height,
+            data,
+        });
+        egui::TextureId::User(id)
+    }
+
+    /// Queue [`Integration::unregister_user_texture`](crate::Integration::unregister_user_texture).
+    pub fn unregister_user_texture(&self, texture_id: egui::TextureId) {
+        if let egui::TextureId::User(id) = texture_id {
+            self.send(QueuedRegistration::Unregister { id });
+        } else {
+            log::warn!("The internal texture cannot be unregistered; please pass the texture ID of UserTexture.");
+        }
+    }
+
+    fn send(&self, registration: QueuedRegistration) {
+        if self.sender.send(registration).is_err() {
+            log::warn!("The integration has been dropped; the texture registration is ignored.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+    use std::collections::HashSet;
+    use std::sync::mpsc;
+
+    #[test]
+    fn registrations_from_many_threads_get_unique_ids() {
+        let (sender, receiver) = mpsc::channel();
+        let queue = TextureQueue {
+            sender,
+            next_id: Default::default(),
+        };
+
+        let threads = (0..4)
+            .map(|_| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    (0..100)
+                        .map(|i| {
+                            queue.register_user_texture(
+                                vk::ImageView::from_raw(i + 1),
+                                vk::Sampler::from_raw(1),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let returned = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(returned.len(), 400);
+
+        let queued = receiver
+            .try_iter()
+            .map(|registration| match registration {
+                QueuedRegistration::UserTexture { id, .. } => egui::TextureId::User(id),
+                _ => unreachable!(),
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(queued, returned);
+    }
+
+    #[test]
+    fn unregister_is_queued_after_create() {
+        let (sender, receiver) = mpsc::channel();
+        let queue = TextureQueue {
+            sender,
+            next_id: Default::default(),
+        };
+        let texture_id = queue.create_texture(1, 1, vec![255; 4]);
+        queue.unregister_user_texture(texture_id);
+
+        let registrations = receiver.try_iter().collect::<Vec<_>>();
+        assert!(matches!(registrations[0], QueuedRegistration::Texture { id: 0, .. }));
+        assert!(matches!(registrations[1], QueuedRegistration::Unregister { id: 0 }));
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use egui::{Event, Modifiers, PointerButton, Pos2};
+
+// Distance in points a finger may move before a press is no longer a long press.
+const LONG_PRESS_SLOP: f32 = 8.0;
+
+// Press of the finger emulating the pointer.
+struct Press {
+    id: u64,
+    start_time: Instant,
+    start_pos: Pos2,
+    pos: Pos2,
+    long_press: bool,
+    fired: bool,
+}
+
+// Emulates the pointer with the first finger touching the window, and a secondary click
+// when it is held still for the long-press duration.
+#[derive(Default)]
+pub(crate) struct TouchEmulation {
+    press: Option<Press>,
+}
+
+impl TouchEmulation {
+    pub(crate) fn start(&mut self, id: u64, pos: Pos2, now: Instant, modifiers: Modifiers) -> Vec<Event> {
+        if self.press.is_some() {
+            // only the first finger moves the pointer
+            return vec![];
+        }
+        self.press = Some(Press {
+            id,
+            start_time: now,
+            start_pos: pos,
+            pos,
+            long_press: true,
+            fired: false,
+        });
+        vec![
+            Event::PointerMoved(pos),
+            Event::PointerButton {
+                pos,
+                button: PointerButton::Primary,
+                pressed: true,
+                modifiers,
+            },
+        ]
+    }
+
+    pub(crate) fn moved(&mut self, id: u64, pos: Pos2) -> Vec<Event> {
+        match &mut self.press {
+            Some(press) if press.id == id => {
+                press.pos = pos;
+                if press.start_pos.distance(pos) > LONG_PRESS_SLOP {
+                    press.long_press = false;
+                }
+                vec![Event::PointerMoved(pos)]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub(crate) fn end(&mut self, id: u64, pos: Pos2, cancelled: bool, modifiers: Modifiers) -> Vec<Event> {
+        match &self.press {
+            Some(press) if press.id == id => {
+                let fired = press.fired;
+                self.press = None;
+                let mut events = vec![];
+                // a fired long press already released the primary button
+                if !fired && !cancelled {
+                    events.push(Event::PointerButton {
+                        pos,
+                        button: PointerButton::Primary,
+                        pressed: false,
+                        modifiers,
+                    });
+                }
+                events.push(Event::PointerGone);
+                events
+            }
+            _ => vec![],
+        }
+    }
+
+    // Whether a long press may still fire, so frames should keep coming.
+    pub(crate) fn long_press_pending(&self) -> bool {
+        self.press.as_ref().is_some_and(|press| press.long_press && !press.fired)
+    }
+
+    // Fire the secondary click of a long press held for `duration`.
+    pub(crate) fn poll(&mut self, now: Instant, duration: Duration, modifiers: Modifiers) -> Vec<Event> {
+        match &mut self.press {
+            Some(press) if press.long_press && !press.fired && now - press.start_time >= duration => {
+                press.fired = true;
+                let pos = press.pos;
+                // the primary release does not click, as long as `duration` is longer than
+                // egui's maximum click duration
+                [(PointerButton::Primary, false), (PointerButton::Secondary, true), (PointerButton::Secondary, false)]
+                    .into_iter()
+                    .map(|(button, pressed)| Event::PointerButton {
+                        pos,
+                        button,
+                        pressed,
+                        modifiers,
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    fn buttons(events: &[Event]) -> Vec<(PointerButton, bool)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::PointerButton { button, pressed, .. } => Some((*button, *pressed)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn long_press_fires_secondary_click_once() {
+        let mut touch = TouchEmulation::default();
+        let start = Instant::now();
+        let duration = Duration::from_millis(800);
+        let modifiers = Modifiers::default();
+        touch.start(0, pos2(10.0, 10.0), start, modifiers);
+
+        assert!(touch.poll(start + Duration::from_millis(500), duration, modifiers).is_empty());
+        let events = touch.poll(start + duration, duration, modifiers);
+        assert_eq!(
+            buttons(&events),
+            vec![
+                (PointerButton::Primary, false),
+                (PointerButton::Secondary, true),
+                (PointerButton::Secondary, false)
+            ]
+        );
+        assert!(touch.poll(start + duration * 2, duration, modifiers).is_empty());
+        assert!(buttons(&touch.end(0, pos2(10.0, 10.0), false, modifiers)).is_empty());
+    }
+
+    #[test]
+    fn moving_finger_is_not_a_long_press() {
+        let mut touch = TouchEmulation::default();
+        let start = Instant::now();
+        let duration = Duration::from_millis(800);
+        let modifiers = Modifiers::default();
+        touch.start(0, pos2(10.0, 10.0), start, modifiers);
+        // a second finger does not move the pointer
+        assert!(touch.start(1, pos2(50.0, 50.0), start, modifiers).is_empty());
+        touch.moved(0, pos2(30.0, 10.0));
+
+        assert!(touch.poll(start + duration, duration, modifiers).is_empty());
+        let events = touch.end(0, pos2(30.0, 10.0), false, modifiers);
+        assert_eq!(buttons(&events), vec![(PointerButton::Primary, false)]);
+    }
+}
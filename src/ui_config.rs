@@ -0,0 +1,70 @@
+#![warn(missing_docs)]
+
+use std::collections::BTreeMap;
+
+/// Tuning of the UI applied before the first frame, see [`IntegrationOptions::ui_config`].
+///
+/// With the `serde` feature it can be loaded from a configuration file, so kiosk and embedded
+/// deployments can adjust the UI without code changes. Missing fields keep the defaults.
+///
+/// [`IntegrationOptions::ui_config`]: crate::IntegrationOptions::ui_config
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct UiConfig {
+    /// Style replacing the one passed to the constructor.
+    pub style: Option<egui::Style>,
+    /// Pixels per point replacing the scale factor passed to the constructor, until the
+    /// window reports a new scale factor.
+    pub pixels_per_point: Option<f32>,
+    /// Font sizes in points of text styles, e.g. `Body`, applied on top of the style.
+    pub font_sizes: BTreeMap<egui::TextStyle, f32>,
+}
+
+impl UiConfig {
+    /// `style` with the overrides of this configuration.
+    pub fn apply_to_style(&self, style: egui::Style) -> egui::Style {
+        let mut style = self.style.clone().unwrap_or(style);
+        for (text_style, &size) in &self.font_sizes {
+            let family = match text_style {
+                egui::TextStyle::Monospace => egui::FontFamily::Monospace,
+                _ => egui::FontFamily::Proportional,
+            };
+            style
+                .text_styles
+                .entry(text_style.clone())
+                .or_insert_with(|| egui::FontId::new(size, family))
+                .size = size;
+        }
+        style
+    }
+
+    /// `scale_factor` unless this configuration overrides it.
+    pub fn scale_factor(&self, scale_factor: f64) -> f64 {
+        self.pixels_per_point.map_or(scale_factor, f64::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{FontFamily, TextStyle};
+
+    #[test]
+    fn font_sizes_override_the_style() {
+        let config = UiConfig {
+            pixels_per_point: Some(1.5),
+            font_sizes: [(TextStyle::Body, 20.0), (TextStyle::Name("Huge".into()), 48.0)].into(),
+            ..Default::default()
+        };
+        let style = config.apply_to_style(egui::Style::default());
+        assert_eq!(style.text_styles[&TextStyle::Body].size, 20.0);
+        assert_eq!(style.text_styles[&TextStyle::Name("Huge".into())].family, FontFamily::Proportional);
+        assert_eq!(
+            style.text_styles[&TextStyle::Button],
+            egui::Style::default().text_styles[&TextStyle::Button]
+        );
+        assert_eq!(config.scale_factor(2.0), 1.5);
+        assert_eq!(UiConfig::default().scale_factor(2.0), 2.0);
+    }
+}
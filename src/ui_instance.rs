@@ -0,0 +1,172 @@
+#![warn(missing_docs)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use egui::{emath::vec2, Context, TexturesDelta};
+
+use crate::platform::pointer_events;
+use crate::{InputSource, TimeSource};
+
+static NEXT_UI_INSTANCE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// An additional egui context with its own input queue, drawn by a shared
+/// [`Renderer`](crate::Renderer).
+///
+/// Unlike the [`Platform`](crate::Platform) it does not handle winit events: input is pushed
+/// explicitly, e.g. pointer events ray-cast onto an in-game screen. Paint its frames with
+/// [`Renderer::paint_ui_instance`](crate::Renderer::paint_ui_instance), or into a texture
+/// shown by another UI with
+/// [`Renderer::paint_offscreen_panel`](crate::Renderer::paint_offscreen_panel); its textures
+/// are kept apart from those of other contexts.
+pub struct UiInstance {
+    pub(crate) id: u64,
+    start_time: Option<Instant>,
+    time_source: Option<Box<TimeSource>>,
+    pixels_per_point: f32,
+    context: Context,
+    raw_input: egui::RawInput,
+    pointer_buttons: Vec<egui::PointerButton>,
+}
+
+impl UiInstance {
+    /// Create an instance for a screen of `physical_width` x `physical_height` pixels.
+    pub fn new(physical_width: u32, physical_height: u32, pixels_per_point: f32) -> Self {
+        let mut instance = Self {
+            id: NEXT_UI_INSTANCE_ID.fetch_add(1, Ordering::Relaxed),
+            start_time: None,
+            time_source: None,
+            pixels_per_point,
+            context: Context::default(),
+            raw_input: egui::RawInput {
+                time: Some(0.0),
+                ..Default::default()
+            },
+            pointer_buttons: vec![],
+        };
+        instance.set_screen_size(physical_width, physical_height, pixels_per_point);
+        instance
+    }
+
+    /// Get the [`egui::Context`] of this instance.
+    pub fn context(&self) -> Context {
+        self.context.clone()
+    }
+
+    /// Pixels per point the instance is laid out and painted with.
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    /// Resize the screen of this instance.
+    pub fn set_screen_size(&mut self, physical_width: u32, physical_height: u32, pixels_per_point: f32) {
+        self.pixels_per_point = pixels_per_point;
+        self.raw_input.pixels_per_point = Some(pixels_per_point);
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            Default::default(),
+            vec2(physical_width as f32, physical_height as f32) / pixels_per_point,
+        ));
+    }
+
+    /// Queue an input event for the next frame.
+    pub fn push_event(&mut self, event: egui::Event) {
+        self.raw_input.events.push(event);
+    }
+
+    /// Move the pointer of this instance. See [`Platform::push_pointer`](crate::Platform::push_pointer).
+    pub fn push_pointer(&mut self, pos: egui::Pos2, buttons: &[egui::PointerButton], modifiers: egui::Modifiers) {
+        self.raw_input
+            .events
+            .extend(pointer_events(pos, &self.pointer_buttons, buttons, modifiers));
+        self.raw_input.modifiers = modifiers;
+        self.pointer_buttons = buttons.to_vec();
+    }
+
+    /// Enter text. See [`Platform::push_text`](crate::Platform::push_text).
+    pub fn push_text(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.push_event(egui::Event::Text(text.to_owned()));
+        }
+    }
+
+    /// The input of the next frame, e.g. to set modifiers or dropped files.
+    pub fn raw_input_mut(&mut self) -> &mut egui::RawInput {
+        &mut self.raw_input
+    }
+
+    /// Push the input of `source` for the next frame. See [`InputSource::poll`].
+    pub fn poll_input(&mut self, source: &mut impl InputSource) {
+        source.poll(self);
+    }
+
+    /// Take the time of the frames from `time_source` instead of the system clock. See
+    /// [`Platform::set_time_source`](crate::Platform::set_time_source).
+    pub fn set_time_source(&mut self, time_source: impl FnMut() -> f64 + Send + 'static) {
+        self.time_source = Some(Box::new(time_source));
+    }
+
+    /// begin frame.
+    pub fn begin_frame(&mut self) {
+        if let Some(time_source) = self.time_source.as_mut() {
+            self.raw_input.time = Some(time_source());
+        } else if let Some(time) = self.start_time {
+            self.raw_input.time = Some(time.elapsed().as_secs_f64());
+        } else {
+            self.start_time = Some(Instant::now());
+        }
+        self.context.begin_frame(self.raw_input.take());
+    }
+
+    /// end frame.
+    pub fn end_frame(&mut self) -> egui::FullOutput {
+        self.context.end_frame()
+    }
+
+    /// end frame and tessellate the shapes.
+    ///
+    /// The platform output is dropped; use [`Self::end_frame`] to handle it.
+    pub fn end_frame_and_tessellate(&mut self) -> (TexturesDelta, Vec<egui::ClippedPrimitive>) {
+        let output = self.end_frame();
+        let clipped_primitives = self.context.tessellate(output.shapes);
+        (output.textures_delta, clipped_primitives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_have_separate_contexts_and_input() {
+        let mut a = UiInstance::new(200, 100, 2.0);
+        let mut b = UiInstance::new(200, 100, 1.0);
+        assert_ne!(a.id, b.id);
+
+        a.push_event(egui::Event::Text("a".to_owned()));
+        assert!(b.raw_input_mut().events.is_empty());
+
+        a.begin_frame();
+        b.begin_frame();
+        assert_eq!(a.context().input().screen_rect.width(), 100.0);
+        assert_eq!(b.context().input().screen_rect.width(), 200.0);
+        assert_eq!(a.context().input().events.len(), 1);
+        assert!(b.context().input().events.is_empty());
+        a.end_frame();
+        b.end_frame();
+    }
+
+    #[test]
+    fn injected_clock_sets_the_frame_time() {
+        let mut instance = UiInstance::new(200, 100, 1.0);
+        let mut frame = 0;
+        instance.set_time_source(move || {
+            frame += 1;
+            frame as f64 / 60.0
+        });
+        for expected in [1.0 / 60.0, 2.0 / 60.0] {
+            instance.begin_frame();
+            assert_eq!(instance.context().input().time, expected);
+            instance.end_frame();
+        }
+    }
+}
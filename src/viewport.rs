@@ -0,0 +1,108 @@
+#![warn(missing_docs)]
+
+use egui::{pos2, vec2, Context, Rect, Vec2};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::window::Window;
+
+/// Where the window is and what state it is in, stored in the egui context data at every
+/// [`Platform::begin_frame`](crate::Platform::begin_frame).
+///
+/// egui 0.18 has no viewport info of its own, so widgets that need it, e.g. window snapping
+/// helpers or popups that stay on the monitor, read it with [`ViewportInfo::get`]. Updated
+/// from the window by [`Platform::handle_platform_output`](crate::Platform::handle_platform_output),
+/// so it describes the window as of the previous frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewportInfo {
+    /// The client area on the desktop, in points. `None` where windows have no position,
+    /// e.g. on Wayland.
+    pub inner_rect: Option<Rect>,
+    /// The window including its decorations on the desktop, in points. `None` where windows
+    /// have no position.
+    pub outer_rect: Option<Rect>,
+    /// Size of the monitor the window is on, in points.
+    pub monitor_size: Option<Vec2>,
+    /// Physical pixels per point of the window.
+    pub pixels_per_point: f32,
+    /// The window has keyboard focus.
+    pub focused: bool,
+    /// The window is fullscreen, borderless or exclusive.
+    pub fullscreen: bool,
+    /// The window is minimized, as far as winit tells: its client area is empty.
+    pub minimized: bool,
+}
+
+impl ViewportInfo {
+    /// The viewport info stored in `context`, if the platform stored any yet.
+    pub fn get(context: &Context) -> Option<Self> {
+        context.data().get_temp(egui::Id::null())
+    }
+
+    pub(crate) fn store(&self, context: &Context) {
+        context.data().insert_temp(egui::Id::null(), self.clone());
+    }
+
+    pub(crate) fn from_window(window: &Window, focused: bool) -> Self {
+        let scale_factor = window.scale_factor();
+        let inner_size = window.inner_size();
+        Self {
+            inner_rect: window
+                .inner_position()
+                .ok()
+                .map(|position| rect_in_points(position, inner_size, scale_factor)),
+            outer_rect: window
+                .outer_position()
+                .ok()
+                .map(|position| rect_in_points(position, window.outer_size(), scale_factor)),
+            monitor_size: window.current_monitor().map(|monitor| {
+                let size = monitor.size();
+                vec2(size.width as f32, size.height as f32) / scale_factor as f32
+            }),
+            pixels_per_point: scale_factor as f32,
+            focused,
+            fullscreen: window.fullscreen().is_some(),
+            minimized: inner_size.width == 0 || inner_size.height == 0,
+        }
+    }
+}
+
+fn rect_in_points(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    scale_factor: f64,
+) -> Rect {
+    let scale = scale_factor as f32;
+    Rect::from_min_size(
+        pos2(position.x as f32 / scale, position.y as f32 / scale),
+        vec2(size.width as f32, size.height as f32) / scale,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_info_is_stored_in_the_context() {
+        let context = Context::default();
+        assert_eq!(ViewportInfo::get(&context), None);
+        let info = ViewportInfo {
+            inner_rect: Some(rect_in_points(
+                PhysicalPosition::new(100, -40),
+                PhysicalSize::new(800, 600),
+                2.0,
+            )),
+            outer_rect: None,
+            monitor_size: Some(vec2(1920.0, 1080.0)),
+            pixels_per_point: 2.0,
+            focused: true,
+            fullscreen: false,
+            minimized: false,
+        };
+        assert_eq!(
+            info.inner_rect,
+            Some(Rect::from_min_size(pos2(50.0, -20.0), vec2(400.0, 300.0)))
+        );
+        info.store(&context);
+        assert_eq!(ViewportInfo::get(&context), Some(info));
+    }
+}
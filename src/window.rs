@@ -0,0 +1,145 @@
+#![warn(missing_docs)]
+
+use std::os::raw::c_char;
+
+use anyhow::Result;
+use ash::extensions::khr::Surface;
+use ash::{vk, Entry, Instance};
+use winit::dpi::LogicalSize;
+use winit::event_loop::EventLoop;
+use winit::window::{Window, WindowBuilder};
+
+use crate::{SurfaceFormatChoice, SurfaceFormatPreference};
+
+/// Window settings for [`create_window`].
+///
+/// Sizes are logical, so the window scales with the DPI of the monitor it opens on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowOptions {
+    /// Window title.
+    pub title: String,
+    /// Initial inner size in logical pixels.
+    pub inner_size: LogicalSize<f64>,
+    /// Minimum inner size in logical pixels.
+    ///
+    /// Keeps the swapchain extent from reaching zero, which cannot be rendered to.
+    pub min_inner_size: LogicalSize<f64>,
+    /// Whether the window can be resized.
+    pub resizable: bool,
+    /// Whether the window background is transparent.
+    ///
+    /// Only enable this when the UI is composited over the desktop; the swapchain must then
+    /// use a composite alpha mode other than `OPAQUE`.
+    pub transparent: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: "egui".to_owned(),
+            inner_size: LogicalSize::new(800.0, 600.0),
+            min_inner_size: LogicalSize::new(1.0, 1.0),
+            resizable: true,
+            transparent: false,
+        }
+    }
+}
+
+impl WindowOptions {
+    /// The winit window builder for these options.
+    pub fn window_builder(&self) -> WindowBuilder {
+        WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_inner_size(self.inner_size)
+            .with_min_inner_size(self.min_inner_size)
+            .with_resizable(self.resizable)
+            .with_transparent(self.transparent)
+    }
+}
+
+/// A Vulkan surface of a window, with the window state needed to create the
+/// [`Integration`](crate::Integration).
+pub struct WindowSurface {
+    /// Surface extension loader.
+    pub surface_loader: Surface,
+    /// The surface. Destroy it with `surface_loader` before the instance.
+    pub surface: vk::SurfaceKHR,
+    /// Inner width of the window in physical pixels.
+    pub physical_width: u32,
+    /// Inner height of the window in physical pixels.
+    pub physical_height: u32,
+    /// Scale factor of the window.
+    pub scale_factor: f64,
+}
+
+impl WindowSurface {
+    /// Pick a surface format for the swapchain with the default [`SurfaceFormatPreference`].
+    ///
+    /// Prefers 8-bit UNORM formats with sRGB nonlinear color space, like the examples.
+    pub fn surface_format(&self, physical_device: vk::PhysicalDevice) -> Result<vk::SurfaceFormatKHR> {
+        Ok(self
+            .select_surface_format(physical_device, &SurfaceFormatPreference::default())?
+            .format)
+    }
+
+    /// Pick the most preferred surface format the surface supports.
+    pub fn select_surface_format(
+        &self,
+        physical_device: vk::PhysicalDevice,
+        preference: &SurfaceFormatPreference,
+    ) -> Result<SurfaceFormatChoice> {
+        let formats = unsafe {
+            self.surface_loader
+                .get_physical_device_surface_formats(physical_device, self.surface)?
+        };
+        preference
+            .select(&formats)
+            .ok_or_else(|| anyhow::anyhow!("The surface supports none of the preferred formats: {:?}", formats))
+    }
+}
+
+/// Create a window with [`WindowOptions`].
+pub fn create_window<T>(event_loop: &EventLoop<T>, options: &WindowOptions) -> Result<Window> {
+    Ok(options.window_builder().build(event_loop)?)
+}
+
+/// Instance extensions required to create a surface for `window`.
+///
+/// Enable them when creating the instance passed to [`create_surface`].
+pub fn required_instance_extensions(window: &Window) -> Result<Vec<*const c_char>> {
+    Ok(ash_window::enumerate_required_extensions(window)?.to_vec())
+}
+
+/// Create the Vulkan surface of `window`.
+///
+/// # Safety
+/// `instance` must have been created with [`required_instance_extensions`], and must be
+/// destroyed after the returned surface.
+pub unsafe fn create_surface(entry: &Entry, instance: &Instance, window: &Window) -> Result<WindowSurface> {
+    let surface_loader = Surface::new(entry, instance);
+    let surface = ash_window::create_surface(entry, instance, window, None)?;
+    let size = window.inner_size();
+    Ok(WindowSurface {
+        surface_loader,
+        surface,
+        physical_width: size.width,
+        physical_height: size.height,
+        scale_factor: window.scale_factor(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_builder_is_opaque_with_min_size_by_default() {
+        let builder = WindowOptions::default().window_builder();
+        assert!(!builder.window.transparent);
+        assert!(builder.window.resizable);
+        assert_eq!(
+            builder.window.min_inner_size,
+            Some(LogicalSize::new(1.0, 1.0).into())
+        );
+    }
+}
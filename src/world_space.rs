@@ -0,0 +1,77 @@
+#![warn(missing_docs)]
+
+use egui::{pos2, Pos2, Vec2};
+
+/// Map a pointer ray to UI coordinates, for UIs rendered with
+/// [`Renderer::set_transform`](crate::Renderer::set_transform).
+///
+/// `model` (column-major) places the UI in world space: it maps (-1, -1, 0) to the top left
+/// corner of the UI and (1, 1, 0) to the bottom right corner, so the transform passed to
+/// `set_transform` is `view_projection * model`. `screen_size` is the size of the UI in points.
+///
+/// Returns `None` if the ray misses the UI.
+pub fn ui_pos_from_ray(
+    model: [[f32; 4]; 4],
+    screen_size: Vec2,
+    origin: [f32; 3],
+    direction: [f32; 3],
+) -> Option<Pos2> {
+    // solve origin + t * direction = model * (u, v, 0, 1) for (u, v, t)
+    let [c0, c1, _, c3] = model.map(|c| [c[0], c[1], c[2]]);
+    let d = direction.map(|x| -x);
+    let rhs = [origin[0] - c3[0], origin[1] - c3[1], origin[2] - c3[2]];
+
+    let det = determinant(c0, c1, d);
+    if det.abs() <= f32::EPSILON {
+        // parallel to the UI
+        return None;
+    }
+    let u = determinant(rhs, c1, d) / det;
+    let v = determinant(c0, rhs, d) / det;
+    let t = determinant(c0, c1, rhs) / det;
+    if t < 0.0 || !(-1.0..=1.0).contains(&u) || !(-1.0..=1.0).contains(&v) {
+        return None;
+    }
+    Some(pos2((u + 1.0) * 0.5 * screen_size.x, (v + 1.0) * 0.5 * screen_size.y))
+}
+
+// Determinant of the 3x3 matrix with columns a, b and c.
+fn determinant(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    a[0] * (b[1] * c[2] - b[2] * c[1]) - b[0] * (a[1] * c[2] - a[2] * c[1])
+        + c[0] * (a[1] * b[2] - a[2] * b[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::vec2;
+
+    // a 4 x 2 quad at z = -5, centered on the z axis
+    const MODEL: [[f32; 4]; 4] = [
+        [2.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, -5.0, 1.0],
+    ];
+
+    #[test]
+    fn ray_hits_ui_coordinates() {
+        let size = vec2(400.0, 200.0);
+        let center = ui_pos_from_ray(MODEL, size, [0.0; 3], [0.0, 0.0, -1.0]).unwrap();
+        assert_eq!(center, pos2(200.0, 100.0));
+
+        let corner = ui_pos_from_ray(MODEL, size, [-2.0, -1.0, 0.0], [0.0, 0.0, -1.0]).unwrap();
+        assert_eq!(corner, pos2(0.0, 0.0));
+    }
+
+    #[test]
+    fn ray_misses_ui() {
+        let size = vec2(400.0, 200.0);
+        // pointing away
+        assert!(ui_pos_from_ray(MODEL, size, [0.0; 3], [0.0, 0.0, 1.0]).is_none());
+        // parallel
+        assert!(ui_pos_from_ray(MODEL, size, [0.0; 3], [1.0, 0.0, 0.0]).is_none());
+        // beside the quad
+        assert!(ui_pos_from_ray(MODEL, size, [3.0, 0.0, 0.0], [0.0, 0.0, -1.0]).is_none());
+    }
+}